@@ -33,9 +33,8 @@ fn main() {
         kind:
           ParseErrorKind::InvalidWireFormatVersion(..)
           | ParseErrorKind::HostObjectNotSupported
-          | ParseErrorKind::SharedObjectNotSupported
-          | ParseErrorKind::SharedArrayBufferNotSupported
-          | ParseErrorKind::WasmModuleTransferNotSupported
+          | ParseErrorKind::MissingSharedArrayBuffer(..)
+          | ParseErrorKind::MissingWasmModule(..)
           | ParseErrorKind::InvalidRegExpFlags(..) // https://bugs.chromium.org/p/v8/issues/detail?id=14412
           | ParseErrorKind::TooDeeplyNested,
         ..
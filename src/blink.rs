@@ -0,0 +1,641 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use crate::de::Input;
+use crate::value::HostObject;
+use crate::value::HostObjectPayload;
+use crate::Heap;
+use crate::HostObjectReader;
+use crate::ParseError;
+use crate::ParseErrorKind;
+use crate::Value;
+use crate::ValueDeserializer;
+use crate::ValueDeserializerDelegate;
+
+/// The first byte of the Blink envelope, which is the same as the V8 header.
+const VERSION_TAG: u8 = 0xFF;
+/// Precedes the position and size of the trailer in the envelope.
+const TRAILER_OFFSET_TAG: u8 = 0xFE;
+/// The first Blink version with an envelope of its own. Before that, Blink
+/// data started with the V8 header.
+const MIN_VERSION_FOR_SEPARATE_ENVELOPE: u32 = 16;
+/// The first Blink version with a trailer offset in the envelope.
+const MIN_VERSION_WITH_TRAILER: u32 = 21;
+
+/// The tags that Blink writes at the start of its host objects.
+const MESSAGE_PORT_TAG: u8 = b'M';
+const MOJO_HANDLE_TAG: u8 = b'h';
+const BLOB_TAG: u8 = b'b';
+const BLOB_INDEX_TAG: u8 = b'i';
+const FILE_TAG: u8 = b'f';
+const FILE_INDEX_TAG: u8 = b'e';
+const FILE_LIST_TAG: u8 = b'l';
+const FILE_LIST_INDEX_TAG: u8 = b'L';
+const IMAGE_DATA_TAG: u8 = b'#';
+const IMAGE_BITMAP_TAG: u8 = b'g';
+const IMAGE_BITMAP_TRANSFER_TAG: u8 = b'G';
+const DOM_POINT_TAG: u8 = b'Q';
+const DOM_POINT_READ_ONLY_TAG: u8 = b'W';
+const DOM_RECT_TAG: u8 = b'E';
+const DOM_RECT_READ_ONLY_TAG: u8 = b'R';
+const DOM_QUAD_TAG: u8 = b'T';
+const DOM_MATRIX_TAG: u8 = b'Y';
+const DOM_MATRIX_READ_ONLY_TAG: u8 = b'U';
+const DOM_MATRIX_2D_TAG: u8 = b'I';
+const DOM_MATRIX_2D_READ_ONLY_TAG: u8 = b'O';
+const CRYPTO_KEY_TAG: u8 = b'K';
+const DOM_EXCEPTION_TAG: u8 = b'x';
+
+/// The `ImageSerializationTag`s that precede the pixels of an image.
+const IMAGE_END_TAG: u32 = 0;
+const IMAGE_ORIGIN_CLEAN_TAG: u32 = 4;
+const IMAGE_IS_PREMULTIPLIED_TAG: u32 = 5;
+const IMAGE_PARAMETRIC_COLOR_SPACE_TAG: u32 = 7;
+const IMAGE_LAST_TAG: u32 = 8;
+/// The number of doubles in a parametric color space: a transfer function
+/// and a matrix to XYZD50.
+const PARAMETRIC_COLOR_SPACE_LENGTH: usize = 16;
+
+/// The subtags of a CryptoKey, which select how its algorithm is written.
+const AES_KEY_TAG: u8 = 1;
+const HMAC_KEY_TAG: u8 = 2;
+const RSA_HASHED_KEY_TAG: u8 = 4;
+const EC_KEY_TAG: u8 = 5;
+const NO_PARAMS_KEY_TAG: u8 = 6;
+const ED25519_KEY_TAG: u8 = 7;
+const X25519_KEY_TAG: u8 = 8;
+
+/// The envelope that Blink (the rendering engine of Chromium) wraps around the
+/// V8 wire format in a `SerializedScriptValue`, as used by IndexedDB and
+/// `postMessage`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlinkEnvelope {
+  /// The Blink version. Data written before Blink had an envelope of its own
+  /// has none, and its version is the version of the V8 header.
+  pub version: u32,
+  /// The number of bytes that the envelope takes up, at the start of the data.
+  pub length: usize,
+  /// The position and the size of the trailer, if there is one. It follows
+  /// the V8 data, and lists the interfaces that the value needs.
+  pub trailer: Option<(u64, u32)>,
+}
+
+impl BlinkEnvelope {
+  /// Read the envelope at the start of `bytes`.
+  pub fn read(bytes: &[u8]) -> Result<BlinkEnvelope, ParseError> {
+    let mut input = Input {
+      bytes: Cow::Borrowed(bytes),
+      cursor: 0,
+      offset: 0,
+      message_start: 0,
+      reader: None,
+      strict: false,
+    };
+    if input.peek_byte()? != Some(VERSION_TAG) {
+      return Ok(BlinkEnvelope {
+        version: 0,
+        length: 0,
+        trailer: None,
+      });
+    }
+    input.read_byte()?;
+    let version = input.read_varint()?;
+    if version < MIN_VERSION_FOR_SEPARATE_ENVELOPE {
+      return Ok(BlinkEnvelope {
+        version,
+        length: 0,
+        trailer: None,
+      });
+    }
+    let mut trailer = None;
+    if version >= MIN_VERSION_WITH_TRAILER {
+      let tag = input.read_byte()?;
+      if tag != TRAILER_OFFSET_TAG {
+        return Err(input.err(ParseErrorKind::InvalidBlinkEnvelope(format!(
+          "expected the trailer offset tag, got 0x{:02x}",
+          tag
+        ))));
+      }
+      // Unlike the rest of the format, these are big endian and fixed width.
+      let offset = u64::from_be_bytes(input.read_bytes(8)?.try_into().unwrap());
+      let size = u32::from_be_bytes(input.read_bytes(4)?.try_into().unwrap());
+      if offset != 0 || size != 0 {
+        let end = offset.checked_add(size as u64);
+        if offset < input.position() as u64
+          || end.map_or(true, |end| end > bytes.len() as u64)
+        {
+          return Err(input.err(ParseErrorKind::InvalidBlinkEnvelope(
+            format!("trailer of {} bytes at {} is out of bounds", size, offset),
+          )));
+        }
+        trailer = Some((offset, size));
+      }
+    }
+    Ok(BlinkEnvelope {
+      version,
+      length: input.position(),
+      trailer,
+    })
+  }
+}
+
+/// Reads the data of a Blink `SerializedScriptValue`. The envelope is
+/// stripped, and Blink's host objects are read as [BlinkHostObject]s.
+///
+/// Many host objects, like Blobs and MessagePorts, refer to state outside of
+/// the data, so only their references (like the UUID of a Blob, or an index
+/// into the ports that were transferred along) are read.
+pub struct BlinkDeserializer {
+  de: ValueDeserializer,
+}
+
+impl BlinkDeserializer {
+  /// Wrap a deserializer, which may have options and transferred values. Its
+  /// delegate is replaced.
+  pub fn new(de: ValueDeserializer) -> Self {
+    Self { de }
+  }
+
+  pub fn read(mut self, bytes: &[u8]) -> Result<(Value, Heap), ParseError> {
+    let envelope = BlinkEnvelope::read(bytes)?;
+    let end = match envelope.trailer {
+      Some((offset, _)) => offset as usize,
+      None => bytes.len(),
+    };
+    self.de.set_delegate(Box::new(BlinkDelegate {
+      version: envelope.version,
+    }));
+    let mut input = Input {
+      bytes: Cow::Borrowed(&bytes[envelope.length..end]),
+      cursor: 0,
+      // Errors are reported at their position in `bytes`.
+      offset: envelope.length,
+      // V8 aligns two byte strings in the whole data, including the envelope.
+      message_start: 0,
+      reader: None,
+      strict: self.de.options.strict,
+    };
+    let (value, heap) = self.de.read_message(&mut input, true)?;
+    Ok((value.into_owned(), heap.into_owned()))
+  }
+}
+
+/// A File as Blink writes it. Its contents are not part of the data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlinkFile {
+  /// The path of the file on disk, if it is backed by one.
+  pub path: String,
+  pub name: String,
+  pub relative_path: String,
+  /// The UUID of the blob that holds the contents of the file.
+  pub uuid: String,
+  pub content_type: String,
+  /// The size and the last modification time, in milliseconds since the
+  /// epoch, if they were known.
+  pub snapshot: Option<(u64, f64)>,
+  pub is_user_visible: bool,
+}
+
+/// The pixels of an ImageData or ImageBitmap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlinkImage {
+  /// Blink's `ImageSerializationTag`s, like the color space, and their values.
+  pub settings: Vec<(u32, u32)>,
+  /// The parametric color space of an ImageBitmap, as a transfer function
+  /// followed by a matrix to XYZD50.
+  pub parametric_color_space: Option<Vec<f64>>,
+  pub width: u32,
+  pub height: u32,
+  pub pixels: Vec<u8>,
+}
+
+/// The algorithm of a CryptoKey. Algorithms, hashes, key types and curves are
+/// the ids that Blink writes for them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlinkCryptoKeyAlgorithm {
+  Aes {
+    id: u8,
+    length_bytes: u32,
+  },
+  Hmac {
+    length_bytes: u32,
+    hash: u8,
+  },
+  RsaHashed {
+    id: u8,
+    key_type: u8,
+    modulus_length_bits: u32,
+    public_exponent: Vec<u8>,
+    hash: u8,
+  },
+  Ec {
+    id: u8,
+    key_type: u8,
+    named_curve: u8,
+  },
+  NoParams {
+    id: u8,
+  },
+  Ed25519 {
+    key_type: u8,
+  },
+  X25519 {
+    key_type: u8,
+  },
+}
+
+/// A host object written by Blink. Objects that refer to state outside of the
+/// data are read as references to it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlinkHostObject {
+  Blob {
+    uuid: String,
+    content_type: String,
+    size: u64,
+  },
+  /// A Blob that is stored next to the data, for example by IndexedDB, by its
+  /// index in the list of those blobs.
+  BlobIndex(u32),
+  File(BlinkFile),
+  /// A File that is stored next to the data, like [BlinkHostObject::BlobIndex].
+  FileIndex(u32),
+  FileList(Vec<BlinkFile>),
+  FileListIndex(Vec<u32>),
+  ImageData(BlinkImage),
+  ImageBitmap(BlinkImage),
+  /// An ImageBitmap, by its index in the transferred ImageBitmaps.
+  ImageBitmapTransfer(u32),
+  /// A MessagePort, by its index in the transferred ports.
+  MessagePort(u32),
+  /// A Mojo handle, by its index in the transferred handles.
+  MojoHandle(u32),
+  DomPoint {
+    x: f64,
+    y: f64,
+    z: f64,
+    w: f64,
+    read_only: bool,
+  },
+  DomRect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    read_only: bool,
+  },
+  /// The four corners of a DOMQuad, as x, y, z and w.
+  DomQuad([[f64; 4]; 4]),
+  /// The values of a DOMMatrix: `a` to `f` for a 2D matrix, or `m11` to `m44`.
+  DomMatrix {
+    values: Vec<f64>,
+    read_only: bool,
+  },
+  CryptoKey {
+    algorithm: BlinkCryptoKeyAlgorithm,
+    /// The usages of the key. Bit 0 is whether the key is extractable.
+    usages: u32,
+    key_data: Vec<u8>,
+  },
+  DomException {
+    name: String,
+    message: String,
+  },
+}
+
+impl HostObjectPayload for BlinkHostObject {
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn payload_eq(&self, other: &dyn HostObjectPayload) -> bool {
+    other.as_any().downcast_ref::<Self>() == Some(self)
+  }
+
+  fn display(&self, writer: &mut dyn Write) -> std::fmt::Result {
+    let (class, args, read_only) = match self {
+      Self::DomPoint {
+        x,
+        y,
+        z,
+        w,
+        read_only,
+      } => ("DOMPoint", vec![*x, *y, *z, *w], *read_only),
+      Self::DomRect {
+        x,
+        y,
+        width,
+        height,
+        read_only,
+      } => ("DOMRect", vec![*x, *y, *width, *height], *read_only),
+      Self::DomMatrix { values, read_only } => {
+        ("DOMMatrix", values.clone(), *read_only)
+      }
+      Self::DomException { name, message } => {
+        return write!(writer, "new DOMException({:?}, {:?})", message, name);
+      }
+      _ => return write!(writer, "undefined /* Blink host object */"),
+    };
+    let args = args
+      .iter()
+      .map(|arg| match arg {
+        arg if *arg == f64::INFINITY => "Infinity".to_string(),
+        arg if *arg == f64::NEG_INFINITY => "-Infinity".to_string(),
+        arg => arg.to_string(),
+      })
+      .collect::<Vec<_>>()
+      .join(", ");
+    let suffix = if read_only { "ReadOnly" } else { "" };
+    match self {
+      // The values of a matrix are passed as an array.
+      Self::DomMatrix { .. } => {
+        write!(writer, "new {}{}([{}])", class, suffix, args)
+      }
+      _ => write!(writer, "new {}{}({})", class, suffix, args),
+    }
+  }
+}
+
+struct BlinkDelegate {
+  version: u32,
+}
+
+impl ValueDeserializerDelegate for BlinkDelegate {
+  fn read_host_object(
+    &mut self,
+    reader: &mut HostObjectReader<'_, '_>,
+  ) -> Result<HostObject, ParseError> {
+    let tag = read_byte(reader)?;
+    let host_object = match tag {
+      MESSAGE_PORT_TAG => BlinkHostObject::MessagePort(reader.read_uint32()?),
+      MOJO_HANDLE_TAG => BlinkHostObject::MojoHandle(reader.read_uint32()?),
+      BLOB_TAG => {
+        // Before version 3, blobs were written without their type.
+        let uuid = read_string(reader)?;
+        let content_type = if self.version >= 3 {
+          read_string(reader)?
+        } else {
+          String::new()
+        };
+        let size = reader.read_uint64()?;
+        BlinkHostObject::Blob {
+          uuid,
+          content_type,
+          size,
+        }
+      }
+      BLOB_INDEX_TAG => BlinkHostObject::BlobIndex(reader.read_uint32()?),
+      FILE_TAG => BlinkHostObject::File(self.read_file(reader)?),
+      FILE_INDEX_TAG => BlinkHostObject::FileIndex(reader.read_uint32()?),
+      FILE_LIST_TAG => {
+        let length = reader.read_uint32()?;
+        let mut files = vec![];
+        for _ in 0..length {
+          files.push(self.read_file(reader)?);
+        }
+        BlinkHostObject::FileList(files)
+      }
+      FILE_LIST_INDEX_TAG => {
+        let length = reader.read_uint32()?;
+        let mut indices = vec![];
+        for _ in 0..length {
+          indices.push(reader.read_uint32()?);
+        }
+        BlinkHostObject::FileListIndex(indices)
+      }
+      IMAGE_DATA_TAG => {
+        BlinkHostObject::ImageData(self.read_image(reader, tag)?)
+      }
+      IMAGE_BITMAP_TAG => {
+        BlinkHostObject::ImageBitmap(self.read_image(reader, tag)?)
+      }
+      IMAGE_BITMAP_TRANSFER_TAG => {
+        BlinkHostObject::ImageBitmapTransfer(reader.read_uint32()?)
+      }
+      DOM_POINT_TAG | DOM_POINT_READ_ONLY_TAG => {
+        let [x, y, z, w] = read_doubles(reader)?;
+        BlinkHostObject::DomPoint {
+          x,
+          y,
+          z,
+          w,
+          read_only: tag == DOM_POINT_READ_ONLY_TAG,
+        }
+      }
+      DOM_RECT_TAG | DOM_RECT_READ_ONLY_TAG => {
+        let [x, y, width, height] = read_doubles(reader)?;
+        BlinkHostObject::DomRect {
+          x,
+          y,
+          width,
+          height,
+          read_only: tag == DOM_RECT_READ_ONLY_TAG,
+        }
+      }
+      DOM_QUAD_TAG => BlinkHostObject::DomQuad([
+        read_doubles(reader)?,
+        read_doubles(reader)?,
+        read_doubles(reader)?,
+        read_doubles(reader)?,
+      ]),
+      DOM_MATRIX_2D_TAG | DOM_MATRIX_2D_READ_ONLY_TAG => {
+        BlinkHostObject::DomMatrix {
+          values: read_doubles::<6>(reader)?.to_vec(),
+          read_only: tag == DOM_MATRIX_2D_READ_ONLY_TAG,
+        }
+      }
+      DOM_MATRIX_TAG | DOM_MATRIX_READ_ONLY_TAG => BlinkHostObject::DomMatrix {
+        values: read_doubles::<16>(reader)?.to_vec(),
+        read_only: tag == DOM_MATRIX_READ_ONLY_TAG,
+      },
+      CRYPTO_KEY_TAG => read_crypto_key(reader)?,
+      DOM_EXCEPTION_TAG => {
+        let name = read_string(reader)?;
+        let message = read_string(reader)?;
+        // The stack is always written as an empty string.
+        read_string(reader)?;
+        BlinkHostObject::DomException { name, message }
+      }
+      _ => {
+        return Err(
+          reader
+            .error(format!("unsupported Blink host object tag 0x{:02x}", tag)),
+        )
+      }
+    };
+    Ok(HostObject::new(host_object))
+  }
+}
+
+impl BlinkDelegate {
+  fn read_file(
+    &self,
+    reader: &mut HostObjectReader<'_, '_>,
+  ) -> Result<BlinkFile, ParseError> {
+    // Fields were added over time, so which ones are present depends on the
+    // version.
+    let path = read_string(reader)?;
+    let (name, relative_path) = if self.version >= 4 {
+      (read_string(reader)?, read_string(reader)?)
+    } else {
+      (String::new(), String::new())
+    };
+    let uuid = read_string(reader)?;
+    let content_type = if self.version >= 3 {
+      read_string(reader)?
+    } else {
+      String::new()
+    };
+    let mut snapshot = None;
+    if self.version >= 4 && reader.read_uint32()? != 0 {
+      let size = reader.read_uint64()?;
+      let mut last_modified = reader.read_double()?;
+      // Before version 8, the time was in seconds.
+      if self.version < 8 {
+        last_modified *= 1000.0;
+      }
+      snapshot = Some((size, last_modified));
+    }
+    let is_user_visible = self.version < 7 || reader.read_uint32()? != 0;
+    Ok(BlinkFile {
+      path,
+      name,
+      relative_path,
+      uuid,
+      content_type,
+      snapshot,
+      is_user_visible,
+    })
+  }
+
+  fn read_image(
+    &self,
+    reader: &mut HostObjectReader<'_, '_>,
+    tag: u8,
+  ) -> Result<BlinkImage, ParseError> {
+    let mut settings = vec![];
+    let mut parametric_color_space = None;
+    if self.version >= 18 {
+      loop {
+        let tag = reader.read_uint32()?;
+        match tag {
+          IMAGE_END_TAG => break,
+          IMAGE_PARAMETRIC_COLOR_SPACE_TAG => {
+            let values = read_doubles::<PARAMETRIC_COLOR_SPACE_LENGTH>(reader)?;
+            parametric_color_space = Some(values.to_vec());
+          }
+          tag if tag <= IMAGE_LAST_TAG => {
+            settings.push((tag, reader.read_uint32()?));
+          }
+          tag => {
+            return Err(
+              reader.error(format!("unknown image serialization tag {}", tag)),
+            )
+          }
+        }
+      }
+    } else if tag == IMAGE_BITMAP_TAG {
+      // ImageBitmaps were written with these two flags before there were
+      // tags. ImageData had none.
+      settings.push((IMAGE_ORIGIN_CLEAN_TAG, reader.read_uint32()?));
+      settings.push((IMAGE_IS_PREMULTIPLIED_TAG, reader.read_uint32()?));
+    }
+    let width = reader.read_uint32()?;
+    let height = reader.read_uint32()?;
+    // The pixel length is a u32 for ImageBitmaps and older ImageData, and a
+    // u64 otherwise. Both are varints, so a u64 can be read for either.
+    let length = reader.read_uint64()?;
+    let Ok(length) = usize::try_from(length) else {
+      return Err(reader.error("image is too large"));
+    };
+    let pixels = reader.read_raw_bytes(length)?.to_vec();
+    Ok(BlinkImage {
+      settings,
+      parametric_color_space,
+      width,
+      height,
+      pixels,
+    })
+  }
+}
+
+fn read_crypto_key(
+  reader: &mut HostObjectReader<'_, '_>,
+) -> Result<BlinkHostObject, ParseError> {
+  let subtag = read_byte(reader)?;
+  let algorithm = match subtag {
+    AES_KEY_TAG => BlinkCryptoKeyAlgorithm::Aes {
+      id: read_byte(reader)?,
+      length_bytes: reader.read_uint32()?,
+    },
+    HMAC_KEY_TAG => BlinkCryptoKeyAlgorithm::Hmac {
+      length_bytes: reader.read_uint32()?,
+      hash: read_byte(reader)?,
+    },
+    RSA_HASHED_KEY_TAG => {
+      let id = read_byte(reader)?;
+      let key_type = read_byte(reader)?;
+      let modulus_length_bits = reader.read_uint32()?;
+      let length = reader.read_uint32()?;
+      let public_exponent = reader.read_raw_bytes(length as usize)?.to_vec();
+      BlinkCryptoKeyAlgorithm::RsaHashed {
+        id,
+        key_type,
+        modulus_length_bits,
+        public_exponent,
+        hash: read_byte(reader)?,
+      }
+    }
+    EC_KEY_TAG => BlinkCryptoKeyAlgorithm::Ec {
+      id: read_byte(reader)?,
+      key_type: read_byte(reader)?,
+      named_curve: read_byte(reader)?,
+    },
+    NO_PARAMS_KEY_TAG => BlinkCryptoKeyAlgorithm::NoParams {
+      id: read_byte(reader)?,
+    },
+    ED25519_KEY_TAG => BlinkCryptoKeyAlgorithm::Ed25519 {
+      key_type: read_byte(reader)?,
+    },
+    X25519_KEY_TAG => BlinkCryptoKeyAlgorithm::X25519 {
+      key_type: read_byte(reader)?,
+    },
+    _ => {
+      return Err(
+        reader.error(format!("unknown CryptoKey subtag 0x{:02x}", subtag)),
+      )
+    }
+  };
+  let usages = reader.read_uint32()?;
+  let length = reader.read_uint32()?;
+  let key_data = reader.read_raw_bytes(length as usize)?.to_vec();
+  Ok(BlinkHostObject::CryptoKey {
+    algorithm,
+    usages,
+    key_data,
+  })
+}
+
+fn read_byte(reader: &mut HostObjectReader<'_, '_>) -> Result<u8, ParseError> {
+  Ok(reader.read_raw_bytes(1)?[0])
+}
+
+fn read_doubles<const N: usize>(
+  reader: &mut HostObjectReader<'_, '_>,
+) -> Result<[f64; N], ParseError> {
+  let mut values = [0.0; N];
+  for value in &mut values {
+    *value = reader.read_double()?;
+  }
+  Ok(values)
+}
+
+/// Read a string that Blink wrote as its UTF-8 length and bytes. Blink replaces
+/// unpaired surrogates when it writes strings, so they are valid UTF-8.
+fn read_string(
+  reader: &mut HostObjectReader<'_, '_>,
+) -> Result<String, ParseError> {
+  let length = reader.read_uint32()?;
+  let bytes = reader.read_raw_bytes(length as usize)?;
+  Ok(String::from_utf8_lossy(bytes).into_owned())
+}
@@ -0,0 +1,73 @@
+use std::borrow::Cow;
+
+use crate::de::Input;
+use crate::tags::SerializationTag;
+use crate::BorrowedHeap;
+use crate::BorrowedValue;
+use crate::ParseError;
+use crate::ParseErrorKind;
+use crate::ValueDeserializer;
+
+/// An iterator over values that were written back to back, each with its own
+/// header. Created by [ValueDeserializer::read_concatenated].
+///
+/// Every value must start with a version header, because versionless data has
+/// no end marker. Iteration stops after the first error, because where the
+/// next value starts is not known after it.
+pub struct ConcatenatedValues<'a> {
+  de: ValueDeserializer,
+  input: Input<'a>,
+  failed: bool,
+}
+
+impl<'a> ConcatenatedValues<'a> {
+  pub(crate) fn new(de: ValueDeserializer, bytes: &'a [u8]) -> Self {
+    let strict = de.options.strict;
+    Self {
+      de,
+      input: Input {
+        bytes: Cow::Borrowed(bytes),
+        cursor: 0,
+        offset: 0,
+        message_start: 0,
+        reader: None,
+        strict,
+      },
+      failed: false,
+    }
+  }
+
+  /// The offset in the input of the next value.
+  pub fn position(&self) -> usize {
+    self.input.position()
+  }
+
+  fn read_next(
+    &mut self,
+  ) -> Result<(BorrowedValue<'a>, BorrowedHeap<'a>), ParseError> {
+    let byte = self.input.read_byte()?;
+    if byte != SerializationTag::Version as u8 {
+      return Err(
+        self
+          .input
+          .err(ParseErrorKind::ExpectedTag(SerializationTag::Version, byte)),
+      );
+    }
+    self.input.unread_byte();
+    self.input.message_start = self.input.position();
+    self.de.read_message(&mut self.input, false)
+  }
+}
+
+impl<'a> Iterator for ConcatenatedValues<'a> {
+  type Item = Result<(BorrowedValue<'a>, BorrowedHeap<'a>), ParseError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.failed || self.input.buffered() == 0 {
+      return None;
+    }
+    let result = self.read_next();
+    self.failed = result.is_err();
+    Some(result)
+  }
+}
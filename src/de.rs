@@ -1,47 +1,87 @@
 use num_bigint::BigInt;
-use std::alloc::Layout;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::io::Read;
 use std::mem::align_of;
 use std::mem::size_of;
+use std::ops::ControlFlow;
 use thiserror::Error;
 
+use crate::concat::ConcatenatedValues;
+use crate::diagnostics::in_path;
+use crate::diagnostics::ErrorContext;
+use crate::diagnostics::PathSegment;
+use crate::lazy::LazyReader;
+use crate::lazy::ObjectPosition;
+use crate::node::array_buffer_view_kind;
+use crate::node::NodeFormat;
+use crate::node::HOST_OBJECT_ARRAY_BUFFER_VIEW;
 use crate::tags::ArrayBufferViewTag;
 use crate::tags::ErrorTag;
 use crate::tags::SerializationTag;
+use crate::value::AlignedBox;
+use crate::value::AlignedBytes;
 use crate::value::ArrayBuffer;
 use crate::value::ArrayBufferView;
 use crate::value::ArrayBufferViewKind;
+use crate::value::BorrowedArrayBuffer;
+use crate::value::BorrowedError;
+use crate::value::BorrowedHeap;
+use crate::value::BorrowedHeapBuilder;
+use crate::value::BorrowedHeapValue;
+use crate::value::BorrowedObject;
+use crate::value::BorrowedOneByteString;
+use crate::value::BorrowedPropertyKey;
+use crate::value::BorrowedRegExp;
+use crate::value::BorrowedSparseArray;
+use crate::value::BorrowedValue;
+use crate::value::BorrowedWtf8String;
 use crate::value::Date;
-use crate::value::DenseArray;
-use crate::value::Error;
 use crate::value::ErrorName;
 use crate::value::Heap;
-use crate::value::HeapBuilder;
-use crate::value::HeapValue;
-use crate::value::Map;
-use crate::value::Object;
-use crate::value::OneByteString;
-use crate::value::PropertyKey;
-use crate::value::RegExp;
+use crate::value::HostObject;
 use crate::value::RegExpFlags;
-use crate::value::Set;
-use crate::value::SparseArray;
+use crate::value::SharedArrayBuffer;
 use crate::value::Value;
-use crate::value::Wtf8String;
+use crate::value::WasmModule;
+use crate::visit::ExternalVisitor;
+use crate::visit::HeapVisitor;
+use crate::visit::ObjectKind;
+use crate::visit::ValueVisitor;
+use crate::visit::Visit;
+use crate::BorrowedStringValue;
+use crate::BorrowedTwoByteString;
 use crate::HeapReference;
-use crate::StringValue;
-use crate::TwoByteString;
 
-const MINIMUM_WIRE_FORMAT_VERSION: u32 = 14;
 const MAXIMUM_WIRE_FORMAT_VERSION: u32 = 15;
 
 #[derive(Debug, Error)]
-#[error("parse error at position {position}: {kind}")]
+#[error("parse error at position {position}{}: {kind}", in_path(.path))]
 pub struct ParseError {
   position: usize,
+  path: Vec<PathSegment>,
   pub kind: ParseErrorKind,
 }
 
+impl ParseError {
+  /// The offset in the input at which the error occurred.
+  pub fn position(&self) -> usize {
+    self.position
+  }
+
+  /// The path from the root value to the value in which the error occurred.
+  /// Empty if the error did not occur inside of an object.
+  pub fn path(&self) -> &[PathSegment] {
+    &self.path
+  }
+
+  /// Display the error together with the bytes of `input` around its
+  /// position. `input` must be the input that the error occurred in.
+  pub fn with_context<'e>(&'e self, input: &'e [u8]) -> ErrorContext<'e> {
+    ErrorContext { error: self, input }
+  }
+}
+
 #[derive(Debug, Error)]
 pub enum ParseErrorKind {
   #[error("unexpected end of file")]
@@ -106,224 +146,1508 @@ pub enum ParseErrorKind {
   HostObjectNotSupported,
   #[error("shared objects are not supported")]
   SharedObjectNotSupported,
-  #[error("an object is too deeply nested, hit recursion depth limit")]
+  #[error("invalid host object: {0}")]
+  InvalidHostObject(String),
+  #[error("an object is too deeply nested, hit nesting depth limit")]
   TooDeeplyNested,
   #[error("invalid regexp flags: {:b}", .0)]
   InvalidRegExpFlags(u32),
+  #[error("varint does not fit in its integer type")]
+  VarintOverflow,
+  #[error("legacy data must contain exactly one value, but contains {0}")]
+  InvalidLegacyValueCount(usize),
+  #[error("failed to read input: {0}")]
+  Io(std::io::Error),
+  #[error("lazy reading is not supported for wire format version {0}")]
+  UnsupportedLazyReadVersion(u32),
+  #[error("visiting is not supported for wire format version {0}")]
+  UnsupportedVisitVersion(u32),
+  #[error("visitor failed: {0}")]
+  Visitor(String),
+  #[error("allocation limit of {limit} bytes exceeded")]
+  AllocationLimitExceeded { limit: usize },
+  #[error(
+    "string length limit exceeded: byte length: {byte_length}, limit: {limit}"
+  )]
+  StringLengthLimitExceeded { byte_length: u32, limit: usize },
+  #[error("array buffer length limit exceeded: byte length: {byte_length}, limit: {limit}")]
+  ArrayBufferLengthLimitExceeded { byte_length: u32, limit: usize },
+  #[error("array length limit exceeded: length: {length}, limit: {limit}")]
+  ArrayLengthLimitExceeded { length: u32, limit: usize },
+  #[error("heap object limit of {limit} objects exceeded")]
+  HeapObjectLimitExceeded { limit: usize },
+  #[error("varint is not encoded in the fewest possible bytes")]
+  NonCanonicalVarint,
+  #[error("padding is only allowed to align the contents of two byte strings")]
+  NonCanonicalPadding,
+  #[error("object count checks are not written by V8")]
+  NonCanonicalVerifyObjectCount,
+  #[error("UTF-8 string only contains one byte characters")]
+  NonCanonicalUtf8String,
+  #[error("invalid Blink envelope: {0}")]
+  InvalidBlinkEnvelope(String),
 }
 
-struct Input<'a> {
-  bytes: &'a [u8],
-  position: usize,
+/// A cursor over the input. When reading from a slice, `bytes` is the whole
+/// input. When reading from a [Read], `bytes` is a window of buffered input
+/// that is refilled on demand.
+pub(crate) struct Input<'a> {
+  pub(crate) bytes: Cow<'a, [u8]>,
+  /// The position of the cursor in `bytes`.
+  pub(crate) cursor: usize,
+  /// The position of `bytes[0]` in the whole input.
+  pub(crate) offset: usize,
+  /// The position in the whole input that two byte strings are aligned
+  /// relative to, which is usually where the message starts.
+  pub(crate) message_start: usize,
+  pub(crate) reader: Option<&'a mut dyn Read>,
+  /// Whether only the canonical encoding is accepted, see
+  /// [DeserializerOptions::strict].
+  pub(crate) strict: bool,
+}
+
+/// The minimum number of bytes that are read from a [Read] at a time.
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Limits on the resources that a [ValueDeserializer] may use, and on the
+/// encodings it accepts. Every limit is checked before the memory it guards is
+/// allocated, so that untrusted input can be read safely. By default, nothing
+/// is limited.
+#[derive(Debug, Clone)]
+pub struct DeserializerOptions {
+  pub(crate) max_depth: usize,
+  pub(crate) max_allocation: usize,
+  pub(crate) max_string_length: usize,
+  pub(crate) max_array_buffer_length: usize,
+  pub(crate) max_array_length: usize,
+  pub(crate) max_heap_objects: usize,
+  pub(crate) strict: bool,
+}
+
+impl Default for DeserializerOptions {
+  fn default() -> Self {
+    Self {
+      max_depth: usize::MAX,
+      max_allocation: usize::MAX,
+      max_string_length: usize::MAX,
+      max_array_buffer_length: usize::MAX,
+      max_array_length: usize::MAX,
+      max_heap_objects: usize::MAX,
+      strict: false,
+    }
+  }
+}
+
+impl DeserializerOptions {
+  /// The maximum nesting depth of values. Exceeding it fails with
+  /// [ParseErrorKind::TooDeeplyNested]. Nested values are read without
+  /// recursion, so deep values only need to be limited to bound memory use.
+  pub fn max_depth(mut self, max_depth: usize) -> Self {
+    self.max_depth = max_depth;
+    self
+  }
+
+  /// The maximum number of bytes allocated for strings, BigInt digits,
+  /// ArrayBuffer contents, array elements, properties, map and set entries
+  /// and heap objects, in total. This is an estimate of the memory used by the
+  /// result.
+  pub fn max_allocation(mut self, max_allocation: usize) -> Self {
+    self.max_allocation = max_allocation;
+    self
+  }
+
+  /// The maximum byte length of a single string.
+  pub fn max_string_length(mut self, max_string_length: usize) -> Self {
+    self.max_string_length = max_string_length;
+    self
+  }
+
+  /// The maximum byte length of a single ArrayBuffer.
+  pub fn max_array_buffer_length(
+    mut self,
+    max_array_buffer_length: usize,
+  ) -> Self {
+    self.max_array_buffer_length = max_array_buffer_length;
+    self
+  }
+
+  /// The maximum length of a single dense or sparse array.
+  pub fn max_array_length(mut self, max_array_length: usize) -> Self {
+    self.max_array_length = max_array_length;
+    self
+  }
+
+  /// The maximum number of objects in the heap.
+  pub fn max_heap_objects(mut self, max_heap_objects: usize) -> Self {
+    self.max_heap_objects = max_heap_objects;
+    self
+  }
+
+  /// Only accept input that is encoded exactly the way V8 would encode it, so
+  /// that every value has a single encoding. Varints must be as short as
+  /// possible, padding may only align the contents of two byte strings and is
+  /// required there, object count checks are not allowed, UTF-8 strings must
+  /// contain characters that do not fit in a one byte string.
+  pub fn strict(mut self, strict: bool) -> Self {
+    self.strict = strict;
+    self
+  }
+}
+
+/// A delegate that decodes embedder specific data in the wire format. This
+/// mirrors `v8::ValueDeserializer::Delegate`.
+pub trait ValueDeserializerDelegate {
+  /// Read a host object from the input. The delegate must consume exactly the
+  /// bytes that the corresponding serializer delegate wrote.
+  fn read_host_object(
+    &mut self,
+    reader: &mut HostObjectReader<'_, '_>,
+  ) -> Result<HostObject, ParseError> {
+    Err(reader.input.err(ParseErrorKind::HostObjectNotSupported))
+  }
+
+  /// Look up the WebAssembly.Module that was transferred with the given id.
+  /// Returning `None` fails with
+  /// [ParseErrorKind::WasmModuleTransferNotSupported].
+  fn get_wasm_module_from_id(
+    &mut self,
+    transfer_id: u32,
+  ) -> Option<WasmModule> {
+    let _ = transfer_id;
+    None
+  }
+}
+
+/// A cursor over the remaining input, handed to a [ValueDeserializerDelegate]
+/// to read the payload of a host object.
+pub struct HostObjectReader<'i, 'a> {
+  input: &'i mut Input<'a>,
+}
+
+impl<'i, 'a> HostObjectReader<'i, 'a> {
+  /// Read a varint encoded u32.
+  pub fn read_uint32(&mut self) -> Result<u32, ParseError> {
+    self.input.read_varint()
+  }
+
+  /// Read a varint encoded u64.
+  pub fn read_uint64(&mut self) -> Result<u64, ParseError> {
+    self.input.read_varint_u64()
+  }
+
+  /// Read a little endian f64.
+  pub fn read_double(&mut self) -> Result<f64, ParseError> {
+    self.input.read_double()
+  }
+
+  /// Read `length` raw bytes.
+  pub fn read_raw_bytes(&mut self, length: usize) -> Result<&[u8], ParseError> {
+    self.input.read_bytes(length)
+  }
+
+  /// Create an error for a host object that the delegate could not decode, at
+  /// the position most recently read from.
+  pub fn error(&self, message: impl Into<String>) -> ParseError {
+    self
+      .input
+      .err(ParseErrorKind::InvalidHostObject(message.into()))
+  }
 }
 
 #[derive(Default)]
 pub struct ValueDeserializer {
   transfer_map: HashMap<u32, ArrayBuffer>,
-  recursion_depth: usize,
+  shared_array_buffers: HashMap<u32, SharedArrayBuffer>,
+  delegate: Option<Box<dyn ValueDeserializerDelegate>>,
+  pub(crate) options: DeserializerOptions,
+  /// The number of objects that are being decoded from their position in the
+  /// input, which recurses. This only happens when reading lazily.
+  pub(crate) recursion_depth: usize,
+  /// The number of bytes allocated for the value that is being read, as
+  /// counted towards [DeserializerOptions::max_allocation].
+  pub(crate) allocated: usize,
+  /// The number of heap objects in the value that is being read, as counted
+  /// towards [DeserializerOptions::max_heap_objects].
+  pub(crate) heap_objects: usize,
+  /// The wire format version read from the header, or 0 for versionless data.
+  pub(crate) version: u32,
+  /// The positions of the objects in the input, indexed by object id. This is
+  /// only populated when reading lazily.
+  pub(crate) object_positions: Option<Vec<ObjectPosition>>,
+  /// The errors that were recovered from so far. This is only populated when
+  /// salvaging, see [ValueDeserializer::read_salvaged].
+  pub(crate) salvaged_errors: Option<Vec<ParseError>>,
+  /// Whether the contents of ArrayBuffers and two byte strings other than
+  /// property keys are skipped over instead of being read, see
+  /// [ValueDeserializer::validate].
+  pub(crate) skip_payloads: bool,
+  /// How Node.js writes array buffer views as host objects, if they are read
+  /// natively instead of through the delegate.
+  pub(crate) node_format: Option<NodeFormat>,
 }
 
 impl ValueDeserializer {
+  /// Create a deserializer that enforces the given resource limits.
+  pub fn with_options(options: DeserializerOptions) -> Self {
+    Self {
+      options,
+      ..Default::default()
+    }
+  }
+
   pub fn transfer_array_buffer(&mut self, id: u32, ab: ArrayBuffer) {
     self.transfer_map.insert(id, ab);
   }
 
-  pub fn read(mut self, bytes: &[u8]) -> Result<(Value, Heap), ParseError> {
-    let mut input = Input { bytes, position: 0 };
-    input.expect_tag(SerializationTag::Version)?;
-    let version = input.read_varint()?;
-    if !(MINIMUM_WIRE_FORMAT_VERSION..=MAXIMUM_WIRE_FORMAT_VERSION)
-      .contains(&version)
-    {
-      return Err(input.err(ParseErrorKind::InvalidWireFormatVersion(version)));
+  /// Register the SharedArrayBuffer for the given id. Unlike transferred array
+  /// buffers, a shared array buffer may be referenced by any number of values.
+  /// Reading a shared array buffer whose id is not registered fails with
+  /// [ParseErrorKind::SharedArrayBufferNotSupported].
+  pub fn transfer_shared_array_buffer(
+    &mut self,
+    id: u32,
+    sab: SharedArrayBuffer,
+  ) {
+    self.shared_array_buffers.insert(id, sab);
+  }
+
+  /// Set the delegate that is used to read host objects. Without a delegate,
+  /// host objects fail with [ParseErrorKind::HostObjectNotSupported].
+  pub fn set_delegate(&mut self, delegate: Box<dyn ValueDeserializerDelegate>) {
+    self.delegate = Some(delegate);
+  }
+
+  /// Read host objects the way Node.js writes them in `v8.serialize()`: as
+  /// array buffer views, which are read as an [ArrayBufferView] of a buffer of
+  /// their own. Node writes nothing but array buffer views as host objects, so
+  /// the delegate is not used for host objects in this mode. Buffers are read
+  /// as `Uint8Array`s.
+  pub fn set_node_compat(&mut self, node_compat: bool) {
+    self.node_format = node_compat.then_some(NodeFormat::Default);
+  }
+
+  /// Read the value in `bytes`. Nothing may follow the value: use
+  /// [ValueDeserializer::read_prefix] or
+  /// [ValueDeserializer::read_concatenated] to read values that are followed
+  /// by other data.
+  pub fn read(self, bytes: &[u8]) -> Result<(Value, Heap), ParseError> {
+    let (value, heap) = self.read_borrowed(bytes)?;
+    Ok((value.into_owned(), heap.into_owned()))
+  }
+
+  /// Read a value whose strings and ArrayBuffer contents borrow from `bytes`
+  /// instead of being copied out of it. Data is only copied where borrowing is
+  /// not possible: ArrayBuffer contents that are not aligned to 8 bytes in
+  /// `bytes`, and two byte strings that are not aligned to 2 bytes. Use
+  /// [BorrowedValue::into_owned] and [BorrowedHeap::into_owned] to detach the
+  /// result from `bytes`.
+  pub fn read_borrowed(
+    self,
+    bytes: &[u8],
+  ) -> Result<(BorrowedValue<'_>, BorrowedHeap<'_>), ParseError> {
+    let strict = self.options.strict;
+    self.read_input(Input {
+      bytes: Cow::Borrowed(bytes),
+      cursor: 0,
+      offset: 0,
+      message_start: 0,
+      reader: None,
+      strict,
+    })
+  }
+
+  /// Read a value from `reader`. The input is decoded incrementally, so only
+  /// a small window of it is buffered at a time, and large ArrayBuffer and
+  /// string contents are read straight into their final allocations.
+  pub fn read_from<R: Read>(
+    self,
+    mut reader: R,
+  ) -> Result<(Value, Heap), ParseError> {
+    let strict = self.options.strict;
+    let (value, heap) = self.read_input(Input {
+      bytes: Cow::Owned(vec![]),
+      cursor: 0,
+      offset: 0,
+      message_start: 0,
+      reader: Some(&mut reader),
+      strict,
+    })?;
+    // Nothing is borrowed when reading from a reader, so this does not copy.
+    Ok((value.into_owned(), heap.into_owned()))
+  }
+
+  /// Read the value at the start of `bytes`, which may be followed by other
+  /// data. Returns the value and the number of bytes that it took up,
+  /// including its header.
+  ///
+  /// Versionless data has no end marker, so it is read to the end of `bytes`.
+  pub fn read_prefix(
+    mut self,
+    bytes: &[u8],
+  ) -> Result<(Value, Heap, usize), ParseError> {
+    let mut input = Input {
+      bytes: Cow::Borrowed(bytes),
+      cursor: 0,
+      offset: 0,
+      message_start: 0,
+      reader: None,
+      strict: self.options.strict,
+    };
+    let (value, heap) = self.read_message(&mut input, false)?;
+    Ok((value.into_owned(), heap.into_owned(), input.position()))
+  }
+
+  /// Read the values in `bytes`, each with its own header, that were written
+  /// back to back. Values borrow from `bytes` like with
+  /// [ValueDeserializer::read_borrowed].
+  pub fn read_concatenated(self, bytes: &[u8]) -> ConcatenatedValues<'_> {
+    ConcatenatedValues::new(self, bytes)
+  }
+
+  /// Index `bytes` for lazy reading. This walks over the whole input once to
+  /// record where every object starts, but does not decode any values. Parts
+  /// of the value can then be looked up through [LazyReader::root] and
+  /// decoded on demand.
+  ///
+  /// Versionless data is not supported, because objects in it can only be
+  /// found by reading everything before them.
+  pub fn read_lazy(self, bytes: &[u8]) -> Result<LazyReader<'_>, ParseError> {
+    LazyReader::new(self, bytes)
+  }
+
+  /// Read `bytes` and report the value in it to `visitor` as it is read,
+  /// without building a [Heap].
+  ///
+  /// Versionless data is not supported, because its objects are only known to
+  /// be objects once all of their properties have been read.
+  pub fn visit<'a>(
+    mut self,
+    bytes: &'a [u8],
+    visitor: &mut impl ValueVisitor<'a>,
+  ) -> Result<(), ParseError> {
+    let mut input = Input {
+      bytes: Cow::Borrowed(bytes),
+      cursor: 0,
+      offset: 0,
+      message_start: 0,
+      reader: None,
+      strict: self.options.strict,
+    };
+    self.read_header(&mut input)?;
+    if self.version == 0 {
+      return Err(
+        input.err_current(ParseErrorKind::UnsupportedVisitVersion(0)),
+      );
+    }
+    visit_object(&mut self, &mut input, &mut ExternalVisitor::new(visitor))?;
+    input.expect_eof()
+  }
+
+  /// Check that `bytes` can be read, without building a [Heap]. This performs
+  /// every check that [ValueDeserializer::read] does, and fails with the same
+  /// error, but does not copy strings or ArrayBuffer contents out of `bytes`.
+  ///
+  /// Versionless data is checked by reading it, see
+  /// [ValueDeserializer::visit].
+  pub fn validate(mut self, bytes: &[u8]) -> Result<(), ParseError> {
+    let mut input = Input {
+      bytes: Cow::Borrowed(bytes),
+      cursor: 0,
+      offset: 0,
+      message_start: 0,
+      reader: None,
+      strict: self.options.strict,
+    };
+    self.read_header(&mut input)?;
+    if self.version == 0 {
+      return self.read_value(&mut input, true).map(|_| ());
+    }
+    self.skip_payloads = true;
+    self.validate_value(&mut input)
+  }
+
+  /// Read as much of a corrupted or truncated value as possible, instead of
+  /// failing at the first error. Objects that are cut short are closed off
+  /// with the contents that could be read, values that could not be read at
+  /// all are replaced by [BorrowedHeapValue::Placeholder], and reading
+  /// continues after unknown tags where possible. Returns every error that was
+  /// encountered, in input order.
+  pub fn read_salvaged(
+    mut self,
+    bytes: &[u8],
+  ) -> (Value, Heap, Vec<ParseError>) {
+    let mut input = Input {
+      bytes: Cow::Borrowed(bytes),
+      cursor: 0,
+      offset: 0,
+      message_start: 0,
+      reader: None,
+      strict: self.options.strict,
+    };
+    let mut errors = vec![];
+    if let Err(err) = self.read_header(&mut input) {
+      errors.push(err);
+      self.version = MAXIMUM_WIRE_FORMAT_VERSION;
+    }
+    let (value, heap, mut value_errors) = self.salvage_value(&mut input);
+    errors.append(&mut value_errors);
+    (value.into_owned(), heap.into_owned(), errors)
+  }
+
+  pub(crate) fn read_header(
+    &mut self,
+    input: &mut Input<'_>,
+  ) -> Result<(), ParseError> {
+    // Data written by very old V8 releases has no version header.
+    if input.maybe_read_tag(SerializationTag::Version)? {
+      self.version = input.read_varint()?;
+      if self.version > MAXIMUM_WIRE_FORMAT_VERSION {
+        return Err(
+          input.err(ParseErrorKind::InvalidWireFormatVersion(self.version)),
+        );
+      }
+    }
+    Ok(())
+  }
+
+  fn read_input(
+    mut self,
+    mut input: Input<'_>,
+  ) -> Result<(BorrowedValue<'_>, BorrowedHeap<'_>), ParseError> {
+    self.read_message(&mut input, true)
+  }
+
+  /// Read a header and the value after it. If `to_end` is set, nothing may
+  /// follow the value.
+  pub(crate) fn read_message<'a>(
+    &mut self,
+    input: &mut Input<'a>,
+    to_end: bool,
+  ) -> Result<(BorrowedValue<'a>, BorrowedHeap<'a>), ParseError> {
+    // A previous message may have set this.
+    self.version = 0;
+    self.read_header(input)?;
+    self.read_value(input, to_end)
+  }
+
+  fn read_value<'a>(
+    &mut self,
+    input: &mut Input<'a>,
+    to_end: bool,
+  ) -> Result<(BorrowedValue<'a>, BorrowedHeap<'a>), ParseError> {
+    self.allocated = 0;
+    self.heap_objects = 0;
+    let mut heap_builder = BorrowedHeapBuilder::default();
+    let value = if self.version == 0 {
+      read_legacy_object(self, input, &mut heap_builder)?
+    } else {
+      read_object(self, input, &mut heap_builder)?
+    };
+    if to_end {
+      input.expect_eof()?;
     }
-    let mut heap_builder = HeapBuilder::default();
-    let value = read_object(&mut self, &mut input, &mut heap_builder)?;
-    input.expect_eof()?;
     let heap = heap_builder
       .build()
       .map_err(|err| input.err_current(err.into()))?;
     Ok((value, heap))
   }
+
+  fn validate_value(
+    &mut self,
+    input: &mut Input<'_>,
+  ) -> Result<(), ParseError> {
+    self.allocated = 0;
+    self.heap_objects = 0;
+    visit_object(self, input, &mut ExternalVisitor::new(&mut Validator))?;
+    input.expect_eof()
+  }
+
+  fn salvage_value<'a>(
+    &mut self,
+    input: &mut Input<'a>,
+  ) -> (BorrowedValue<'a>, BorrowedHeap<'a>, Vec<ParseError>) {
+    self.allocated = 0;
+    self.heap_objects = 0;
+    self.salvaged_errors = Some(vec![]);
+    let mut heap_builder = BorrowedHeapBuilder::default();
+    let value = if self.version == 0 {
+      read_legacy_object(self, input, &mut heap_builder)
+    } else {
+      read_object(self, input, &mut heap_builder)
+    };
+    let mut errors = self.salvaged_errors.take().unwrap();
+    let value = value.unwrap_or_else(|err| {
+      errors.push(err);
+      let reference =
+        heap_builder.insert_without_id(BorrowedHeapValue::Placeholder);
+      BorrowedValue::HeapReference(reference)
+    });
+    heap_builder.fill_with_placeholders();
+    let heap = heap_builder.build().unwrap();
+    (value, heap, errors)
+  }
 }
 
-const RECURSION_DEPTH_LIMIT: usize = 256;
+/// The visitor for [ValueDeserializer::validate], which only needs the checks
+/// that are done while reading.
+struct Validator;
+
+impl ValueVisitor<'_> for Validator {}
 
-fn read_object(
+/// When salvaging, record `err` so that reading can continue. Otherwise, fail
+/// with it.
+fn tolerate(
   de: &mut ValueDeserializer,
-  input: &mut Input<'_>,
-  heap: &mut HeapBuilder,
-) -> Result<Value, ParseError> {
-  if de.recursion_depth > RECURSION_DEPTH_LIMIT {
-    return Err(input.err(ParseErrorKind::TooDeeplyNested));
+  err: ParseError,
+) -> Result<(), ParseError> {
+  match &mut de.salvaged_errors {
+    Some(errors) => {
+      errors.push(err);
+      Ok(())
+    }
+    None => Err(err),
   }
-  de.recursion_depth += 1;
-  let res = read_object_internal(de, input, heap);
-  de.recursion_depth -= 1;
-  let value = res?;
-
-  if let Value::HeapReference(reference) = value {
-    match heap.try_open(reference) {
-      Some(HeapValue::ArrayBuffer(ab))
-        if input.maybe_read_tag(SerializationTag::ArrayBufferView) =>
-      {
-        let buffer_byte_length = ab.byte_length();
-        let view =
-          read_js_array_buffer_view(input, buffer_byte_length, reference)?;
-        let heap_value = HeapValue::ArrayBufferView(view);
-        let reference = heap.insert(heap_value);
-        return Ok(Value::HeapReference(reference));
+}
+
+/// Whether `byte` is a tag that a value or the end of an object starts with.
+/// When salvaging, bytes are skipped up to the next such tag after an unknown
+/// tag.
+fn is_known_tag(byte: u8) -> bool {
+  SerializationTag::from_u8(byte).is_some_and(|tag| {
+    !matches!(
+      tag,
+      SerializationTag::Version
+        | SerializationTag::Padding
+        | SerializationTag::ArrayBufferView
+    )
+  })
+}
+
+/// An object whose contents are being read. Nested values are read by pushing
+/// frames onto a work stack instead of recursing, so that the nesting depth of
+/// a value is not limited by the native stack.
+enum Frame<'a> {
+  Object {
+    properties: Properties<'a>,
+  },
+  SparseArray {
+    length: u32,
+    properties: Properties<'a>,
+  },
+  DenseArray {
+    length: u32,
+    /// The number of elements that were read, including holes.
+    elements: u32,
+    properties: Properties<'a>,
+  },
+  Map {
+    /// The number of keys and values that were read.
+    values: u32,
+  },
+  Set {
+    values: u32,
+  },
+  Error {
+    /// The fields of the error other than the cause.
+    error: BorrowedError<'a>,
+  },
+  WasmMemory {
+    /// Whether the buffer of the memory was read.
+    has_buffer: bool,
+  },
+}
+
+/// The properties of an object or array that is being read.
+struct Properties<'a> {
+  end_tag: SerializationTag,
+  /// The number of properties that were read.
+  count: u32,
+  /// The key of the property whose value is read next.
+  key: Option<BorrowedPropertyKey<'a>>,
+}
+
+/// The start of a value: either the complete value, or the frame of an object
+/// whose contents follow.
+enum Begin<'a> {
+  /// For an array buffer, its byte length, because a view may follow it.
+  Value {
+    buffer_byte_length: Option<u32>,
+  },
+  Frame(Frame<'a>),
+}
+
+/// Report the result of a [ValueVisitor] callback as an error at the position
+/// most recently read from.
+pub(crate) fn emit(
+  input: &Input<'_>,
+  result: Result<(), ParseErrorKind>,
+) -> Result<(), ParseError> {
+  result.map_err(|kind| input.err(kind))
+}
+
+/// Read a value into `heap`.
+pub(crate) fn read_object<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  heap: &mut BorrowedHeapBuilder<'a>,
+) -> Result<BorrowedValue<'a>, ParseError> {
+  let mut visitor = HeapVisitor::new(heap);
+  visit_object(de, input, &mut visitor)?;
+  Ok(visitor.finish())
+}
+
+/// Read a value into `heap`, but not an ArrayBufferView that may follow it.
+pub(crate) fn read_object_internal<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  heap: &mut BorrowedHeapBuilder<'a>,
+) -> Result<BorrowedValue<'a>, ParseError> {
+  let mut visitor = HeapVisitor::new(heap);
+  visit_object_internal(de, input, &mut visitor)?;
+  Ok(visitor.finish())
+}
+
+/// Read a value and report it to `visitor`, including an ArrayBufferView that
+/// may follow it.
+pub(crate) fn visit_object<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+) -> Result<(), ParseError> {
+  let buffer_byte_length = visit_object_internal(de, input, visitor)?;
+  visit_array_buffer_view(de, input, visitor, buffer_byte_length)
+}
+
+/// If the value that was just read is an array buffer of the given byte length
+/// and it is followed by a view, read the view.
+fn visit_array_buffer_view<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+  buffer_byte_length: Option<u32>,
+) -> Result<(), ParseError> {
+  let Some(buffer_byte_length) = buffer_byte_length else {
+    return Ok(());
+  };
+  if input.maybe_read_tag(SerializationTag::ArrayBufferView)? {
+    let offset = input.position() - 1;
+    allocate_heap_object(de, input)?;
+    let view = read_js_array_buffer_view(de, input, buffer_byte_length)?;
+    visitor.assign_id(ObjectKind::ArrayBufferView, offset);
+    emit(
+      input,
+      visitor.visitor().array_buffer_view(
+        view.kind,
+        view.byte_offset,
+        view.length,
+        view.is_length_tracking,
+        view.is_backed_by_rab,
+      ),
+    )?;
+  }
+  Ok(())
+}
+
+/// Read a value and report it to `visitor`, but not an ArrayBufferView that
+/// may follow it. Returns the byte length of the value if it is an array
+/// buffer.
+pub(crate) fn visit_object_internal<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+) -> Result<Option<u32>, ParseError> {
+  let mut stack: Vec<Frame<'a>> = vec![];
+  loop {
+    let recorded = de.salvaged_errors.as_ref().map_or(0, Vec::len);
+    let mut reading_value = false;
+    let result = read_step(de, input, visitor, &mut stack, &mut reading_value);
+    if let Some(errors) = &mut de.salvaged_errors {
+      for err in &mut errors[recorded..] {
+        set_path(err, &stack);
       }
-      _ => {}
+    }
+    let mut err = match result {
+      Ok(ControlFlow::Break(buffer_byte_length)) => {
+        return Ok(buffer_byte_length)
+      }
+      Ok(ControlFlow::Continue(())) => continue,
+      Err(err) => err,
     };
+    set_path(&mut err, &stack);
+    let resync = matches!(
+      err.kind,
+      ParseErrorKind::UnexpectedTag(_)
+        | ParseErrorKind::InvalidObjectReference(_)
+    );
+    // A value that could not be read is replaced by a placeholder, but one
+    // that is cut off is left out.
+    let placeholder =
+      reading_value && !matches!(err.kind, ParseErrorKind::UnexpectedEof);
+    tolerate(de, err)?;
+    if placeholder {
+      visitor.placeholder();
+      match stack.last_mut() {
+        Some(frame) => frame.count_value(),
+        None => return Ok(None),
+      }
+    }
+    if !resync {
+      close_frames(de, input, visitor, stack);
+      return Ok(None);
+    }
+    // Skip over whatever the unreadable value consists of, up to the next
+    // value or end of an object.
+    while let Ok(Some(byte)) = input.peek_byte() {
+      if is_known_tag(byte) {
+        break;
+      }
+      input.cursor += 1;
+    }
   }
+}
 
-  Ok(value)
+/// Set the path of an error that occurred while the objects in `stack` were
+/// being read, unless it already has one.
+fn set_path(err: &mut ParseError, stack: &[Frame<'_>]) {
+  if err.path.is_empty() {
+    err.path = stack.iter().map(Frame::path_segment).collect();
+  }
 }
 
-fn read_object_internal(
+/// Read the next value, property key, or start or end of an object at the
+/// cursor. Breaks once the outermost value is complete, with its byte length if
+/// it is an array buffer. `reading_value` is set while a value is read, which
+/// is what a failure is about then.
+fn read_step<'a>(
   de: &mut ValueDeserializer,
-  input: &mut Input<'_>,
-  heap: &mut HeapBuilder,
-) -> Result<Value, ParseError> {
-  input.skip_padding();
-  let tag = input.read_byte()?;
-  if tag == SerializationTag::VerifyObjectCount as u8 {
+  input: &mut Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+  stack: &mut Vec<Frame<'a>>,
+  reading_value: &mut bool,
+) -> Result<ControlFlow<Option<u32>>, ParseError> {
+  let at_end = match stack.last_mut() {
+    Some(frame) => read_frame_end(de, input, visitor, frame)?,
+    None => false,
+  };
+  let buffer_byte_length = if at_end {
+    read_frame_trailer(de, input, stack.last().unwrap())?;
+    end_frame(input, visitor, stack.pop().unwrap())?;
+    None
+  } else if let Some(properties) = stack.last_mut().and_then(Frame::expects_key)
+  {
+    let key = read_property_key(de, input)?;
+    allocate(de, input, size_of::<(BorrowedPropertyKey, BorrowedValue)>())?;
+    emit(input, visitor.visitor().property_key(key.clone()))?;
+    properties.key = Some(key);
+    return Ok(ControlFlow::Continue(()));
+  } else {
+    *reading_value = true;
+    if stack.len() > de.options.max_depth {
+      return Err(input.err(ParseErrorKind::TooDeeplyNested));
+    }
+    let begin = if stack.last().is_some_and(Frame::expects_buffer) {
+      read_wasm_memory_buffer(de, input, visitor)?
+    } else {
+      let is_element = stack.last().is_some_and(Frame::expects_element);
+      begin_value(de, input, visitor, is_element)?
+    };
+    *reading_value = false;
+    match begin {
+      Begin::Value { buffer_byte_length } => buffer_byte_length,
+      Begin::Frame(frame) => {
+        stack.push(frame);
+        return Ok(ControlFlow::Continue(()));
+      }
+    }
+  };
+  let Some(frame) = stack.last_mut() else {
+    return Ok(ControlFlow::Break(buffer_byte_length));
+  };
+  visit_array_buffer_view(de, input, visitor, buffer_byte_length)?;
+  if let Frame::Map { .. } | Frame::Set { .. } = frame {
+    // Map entries are pairs of values, which are read one at a time.
+    allocate(de, input, size_of::<BorrowedValue>())?;
+  }
+  frame.count_value();
+  Ok(ControlFlow::Continue(()))
+}
+
+/// When salvaging, close off the objects that are still being read with the
+/// contents read so far, after an error that reading can not continue after.
+fn close_frames<'a>(
+  de: &mut ValueDeserializer,
+  input: &Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+  mut stack: Vec<Frame<'a>>,
+) {
+  while let Some(frame) = stack.pop() {
+    if let Err(err) = end_frame(input, visitor, frame) {
+      let _ = tolerate(de, err);
+    }
+  }
+}
+
+/// Read the tag of the next value, skipping over padding and object count
+/// checks.
+fn read_tag(input: &mut Input<'_>) -> Result<u8, ParseError> {
+  input.skip_padding()?;
+  let mut tag = input.read_byte()?;
+  while tag == SerializationTag::VerifyObjectCount as u8 {
+    if input.strict {
+      return Err(input.err(ParseErrorKind::NonCanonicalVerifyObjectCount));
+    }
     // Read the count and ignore it.
     let _ = input.read_varint()?;
-    read_object(de, input, heap)
-  } else if tag == SerializationTag::Undefined as u8 {
-    Ok(Value::Undefined)
+    input.skip_padding()?;
+    tag = input.read_byte()?;
+  }
+  Ok(tag)
+}
+
+/// Read a value that is not stored in the heap, after its tag. Returns None if
+/// the tag is not that of such a value.
+fn read_primitive<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  tag: u8,
+) -> Result<Option<BorrowedValue<'a>>, ParseError> {
+  let value = if tag == SerializationTag::Undefined as u8 {
+    BorrowedValue::Undefined
   } else if tag == SerializationTag::Null as u8 {
-    Ok(Value::Null)
+    BorrowedValue::Null
   } else if tag == SerializationTag::True as u8 {
-    Ok(Value::Bool(true))
+    BorrowedValue::Bool(true)
   } else if tag == SerializationTag::False as u8 {
-    Ok(Value::Bool(false))
+    BorrowedValue::Bool(false)
   } else if tag == SerializationTag::Int32 as u8 {
-    let value = input.read_zigzag()?;
-    Ok(Value::I32(value))
+    BorrowedValue::I32(input.read_zigzag()?)
   } else if tag == SerializationTag::Uint32 as u8 {
-    let value = input.read_varint()?;
-    Ok(Value::U32(value))
+    BorrowedValue::U32(input.read_varint()?)
   } else if tag == SerializationTag::Double as u8 {
-    let value = input.read_double()?;
-    Ok(Value::Double(value))
+    BorrowedValue::Double(input.read_double()?)
   } else if tag == SerializationTag::BigInt as u8 {
-    let value = read_bigint(input)?;
-    Ok(Value::BigInt(value))
+    BorrowedValue::BigInt(read_bigint(de, input)?)
   } else if tag == SerializationTag::Utf8String as u8 {
-    let value = read_utf8_string(input)?;
-    Ok(Value::String(StringValue::Wtf8(value)))
+    let value = read_utf8_string(de, input)?;
+    check_utf8_string(input, &value)?;
+    BorrowedValue::String(BorrowedStringValue::Wtf8(value))
   } else if tag == SerializationTag::OneByteString as u8 {
-    let value = read_one_byte_string(input)?;
-    Ok(Value::String(StringValue::OneByte(value)))
+    BorrowedValue::String(BorrowedStringValue::OneByte(read_one_byte_string(
+      de, input,
+    )?))
   } else if tag == SerializationTag::TwoByteString as u8 {
-    let str = read_two_byte_string(input)?;
-    Ok(Value::String(StringValue::TwoByte(str)))
-  } else if tag == SerializationTag::ObjectReference as u8 {
-    let reference = read_object_reference(input, heap)?;
-    Ok(Value::HeapReference(reference))
-  } else if tag == SerializationTag::BeginJsObject as u8 {
-    let reference = heap.reserve();
-    let object = read_js_object(de, input, heap)?;
-    let heap_value = HeapValue::Object(object);
-    heap.insert_reserved(reference, heap_value);
-    Ok(Value::HeapReference(reference))
+    BorrowedValue::String(BorrowedStringValue::TwoByte(read_two_byte_string(
+      de, input,
+    )?))
+  } else {
+    return Ok(None);
+  };
+  Ok(Some(value))
+}
+
+/// Read the key of a property of an object or array.
+fn read_property_key<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+) -> Result<BorrowedPropertyKey<'a>, ParseError> {
+  let tag = read_tag(input)?;
+  // Keys are part of the paths of errors, so their contents are read even
+  // when payloads are skipped.
+  let skip_payloads = std::mem::replace(&mut de.skip_payloads, false);
+  let value = read_primitive(de, input, tag);
+  de.skip_payloads = skip_payloads;
+  match value? {
+    Some(value) => value_to_property_key(input, value),
+    None => Err(input.err(ParseErrorKind::UnexpectedTag(tag))),
+  }
+}
+
+/// Read a value that is not stored in the heap, or an object reference, or
+/// the start of an object. `is_element` is whether the value is an element of
+/// a dense array.
+fn begin_value<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+  is_element: bool,
+) -> Result<Begin<'a>, ParseError> {
+  input.skip_padding()?;
+  if let Some(buffer_byte_length) = visitor.reuse_decoded(de, input)? {
+    return Ok(Begin::Value { buffer_byte_length });
+  }
+  let tag = read_tag(input)?;
+  if let Some(value) = read_primitive(de, input, tag)? {
+    let visitor = visitor.visitor();
+    let result = match value {
+      // Before version 11, undefined and the hole were not distinguished.
+      BorrowedValue::Undefined if is_element && de.version < 11 => {
+        visitor.hole()
+      }
+      BorrowedValue::Undefined => visitor.undefined(),
+      BorrowedValue::Null => visitor.null(),
+      BorrowedValue::Bool(value) => visitor.bool(value),
+      BorrowedValue::I32(value) => visitor.int32(value),
+      BorrowedValue::U32(value) => visitor.uint32(value),
+      BorrowedValue::Double(value) => visitor.double(value),
+      BorrowedValue::BigInt(value) => visitor.bigint(value),
+      BorrowedValue::String(value) => visitor.string(value),
+      BorrowedValue::HeapReference(_) => unreachable!(),
+    };
+    emit(input, result)?;
+    return Ok(Begin::Value {
+      buffer_byte_length: None,
+    });
+  }
+  if tag == SerializationTag::ObjectReference as u8 {
+    let id = input.read_varint()?;
+    let kind = visitor.object_reference(de, input, id)?;
+    return Ok(Begin::Value {
+      buffer_byte_length: kind.buffer_byte_length(),
+    });
+  }
+  read_heap_object(de, input, visitor, tag)
+}
+
+/// Read the buffer of a WebAssembly memory. Like V8, this accepts a reference
+/// to a SharedArrayBuffer that was read before, as well as a SharedArrayBuffer.
+/// No view may follow the buffer.
+fn read_wasm_memory_buffer<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+) -> Result<Begin<'a>, ParseError> {
+  let tag = read_tag(input)?;
+  if tag == SerializationTag::ObjectReference as u8 {
+    let id = input.read_varint()?;
+    let kind = visitor.object_reference(de, input, id)?;
+    if !matches!(kind, ObjectKind::SharedArrayBuffer(_)) {
+      return Err(input.err(ParseErrorKind::InvalidObjectReference(id)));
+    }
+  } else if tag == SerializationTag::SharedArrayBuffer as u8 {
+    let offset = input.position() - 1;
+    allocate_heap_object(de, input)?;
+    let sab = read_shared_array_buffer(de, input)?;
+    visitor.assign_id(ObjectKind::SharedArrayBuffer(sab.byte_length()), offset);
+    emit(input, visitor.visitor().shared_array_buffer(sab))?;
+  } else {
+    return Err(input.err(ParseErrorKind::ExpectedTag(
+      SerializationTag::SharedArrayBuffer,
+      tag,
+    )));
+  }
+  Ok(Begin::Value {
+    buffer_byte_length: None,
+  })
+}
+
+/// Read a value that is stored in the heap, after its tag.
+fn read_heap_object<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+  tag: u8,
+) -> Result<Begin<'a>, ParseError> {
+  let offset = input.position() - 1;
+  allocate_heap_object(de, input)?;
+  let mut kind = ObjectKind::Other;
+  let result = if tag == SerializationTag::BeginJsObject as u8 {
+    allocate(de, input, size_of::<Frame>())?;
+    visitor.assign_id(ObjectKind::Other, offset);
+    emit(input, visitor.visitor().begin_object())?;
+    return Ok(Begin::Frame(Frame::Object {
+      properties: Properties::new(SerializationTag::EndJsObject),
+    }));
   } else if tag == SerializationTag::BeginSparseJsArray as u8 {
-    let reference = heap.reserve();
-    let array = read_sparse_js_array(de, input, heap)?;
-    let heap_value = HeapValue::SparseArray(array);
-    heap.insert_reserved(reference, heap_value);
-    Ok(Value::HeapReference(reference))
+    let length = input.read_varint()?;
+    check_array_length(de, input, length)?;
+    allocate(de, input, size_of::<Frame>())?;
+    visitor.assign_id(ObjectKind::Other, offset);
+    emit(input, visitor.visitor().begin_sparse_array(length))?;
+    return Ok(Begin::Frame(Frame::SparseArray {
+      length,
+      properties: Properties::new(SerializationTag::EndSparseJsArray),
+    }));
   } else if tag == SerializationTag::BeginDenseJsArray as u8 {
-    let reference = heap.reserve();
-    let array = read_dense_js_array(de, input, heap)?;
-    let heap_value = HeapValue::DenseArray(array);
-    heap.insert_reserved(reference, heap_value);
-    Ok(Value::HeapReference(reference))
+    let length = input.read_varint()?;
+    check_array_length(de, input, length)?;
+    allocate(
+      de,
+      input,
+      length as usize * size_of::<Option<BorrowedValue>>(),
+    )?;
+    // Every element is at least one byte long, so this bounds the length by
+    // the size of the input.
+    input.ensure_minimum_available(length as usize)?;
+    allocate(de, input, size_of::<Frame>())?;
+    visitor.assign_id(ObjectKind::Other, offset);
+    emit(input, visitor.visitor().begin_dense_array(length))?;
+    visitor.reserve_elements(input.capacity_for(length as usize));
+    return Ok(Begin::Frame(Frame::DenseArray {
+      length,
+      elements: 0,
+      properties: Properties::new(SerializationTag::EndDenseJsArray),
+    }));
+  } else if tag == SerializationTag::BeginJsMap as u8 {
+    allocate(de, input, size_of::<Frame>())?;
+    visitor.assign_id(ObjectKind::Other, offset);
+    emit(input, visitor.visitor().begin_map())?;
+    return Ok(Begin::Frame(Frame::Map { values: 0 }));
+  } else if tag == SerializationTag::BeginJsSet as u8 {
+    allocate(de, input, size_of::<Frame>())?;
+    visitor.assign_id(ObjectKind::Other, offset);
+    emit(input, visitor.visitor().begin_set())?;
+    return Ok(Begin::Frame(Frame::Set { values: 0 }));
+  } else if tag == SerializationTag::Error as u8 {
+    allocate(de, input, size_of::<Frame>())?;
+    visitor.assign_id(ObjectKind::Other, offset);
+    emit(input, visitor.visitor().begin_error())?;
+    return Ok(Begin::Frame(Frame::Error {
+      error: BorrowedError {
+        name: ErrorName::Error,
+        message: None,
+        stack: None,
+        cause: None,
+      },
+    }));
   } else if tag == SerializationTag::Date as u8 {
     let date = read_date(input)?;
-    let heap_value = HeapValue::Date(date);
-    let reference = heap.insert(heap_value);
-    Ok(Value::HeapReference(reference))
+    visitor.assign_id(ObjectKind::Other, offset);
+    visitor.visitor().date(date)
   } else if tag == SerializationTag::TrueObject as u8 {
-    let heap_value = HeapValue::BooleanObject(true);
-    let reference = heap.insert(heap_value);
-    Ok(Value::HeapReference(reference))
+    visitor.assign_id(ObjectKind::Other, offset);
+    visitor.visitor().boolean_object(true)
   } else if tag == SerializationTag::FalseObject as u8 {
-    let heap_value = HeapValue::BooleanObject(false);
-    let reference = heap.insert(heap_value);
-    Ok(Value::HeapReference(reference))
+    visitor.assign_id(ObjectKind::Other, offset);
+    visitor.visitor().boolean_object(false)
   } else if tag == SerializationTag::NumberObject as u8 {
     let value = input.read_double()?;
-    let heap_value = HeapValue::NumberObject(value);
-    let reference = heap.insert(heap_value);
-    Ok(Value::HeapReference(reference))
+    visitor.assign_id(ObjectKind::Other, offset);
+    visitor.visitor().number_object(value)
   } else if tag == SerializationTag::BigIntObject as u8 {
-    let value = read_bigint(input)?;
-    let heap_value = HeapValue::BigIntObject(value);
-    let reference = heap.insert(heap_value);
-    Ok(Value::HeapReference(reference))
+    let value = read_bigint(de, input)?;
+    visitor.assign_id(ObjectKind::Other, offset);
+    visitor.visitor().bigint_object(value)
   } else if tag == SerializationTag::StringObject as u8 {
     let value = read_string_value(de, input)?;
-    let heap_value = HeapValue::StringObject(value);
-    let reference = heap.insert(heap_value);
-    Ok(Value::HeapReference(reference))
+    visitor.assign_id(ObjectKind::Other, offset);
+    visitor.visitor().string_object(value)
   } else if tag == SerializationTag::RegExp as u8 {
     let regexp = read_regexp(de, input)?;
-    let heap_value = HeapValue::RegExp(regexp);
-    let reference = heap.insert(heap_value);
-    Ok(Value::HeapReference(reference))
-  } else if tag == SerializationTag::BeginJsMap as u8 {
-    let reference = heap.reserve();
-    let map = read_js_map(de, input, heap)?;
-    let heap_value = HeapValue::Map(map);
-    heap.insert_reserved(reference, heap_value);
-    Ok(Value::HeapReference(reference))
-  } else if tag == SerializationTag::BeginJsSet as u8 {
-    let reference = heap.reserve();
-    let set = read_js_set(de, input, heap)?;
-    let heap_value = HeapValue::Set(set);
-    heap.insert_reserved(reference, heap_value);
-    Ok(Value::HeapReference(reference))
-  } else if tag == SerializationTag::ArrayBuffer as u8 {
-    let array_buffer = read_js_array_buffer(input, false)?;
-    let heap_value = HeapValue::ArrayBuffer(array_buffer);
-    let reference = heap.insert(heap_value);
-    Ok(Value::HeapReference(reference))
-  } else if tag == SerializationTag::ResizableArrayBuffer as u8 {
-    let array_buffer = read_js_array_buffer(input, true)?;
-    let heap_value = HeapValue::ArrayBuffer(array_buffer);
-    let reference = heap.insert(heap_value);
-    Ok(Value::HeapReference(reference))
+    visitor.assign_id(ObjectKind::Other, offset);
+    visitor.visitor().regexp(regexp)
+  } else if tag == SerializationTag::ArrayBuffer as u8
+    || tag == SerializationTag::ResizableArrayBuffer as u8
+  {
+    let is_resizable = tag == SerializationTag::ResizableArrayBuffer as u8;
+    if de.skip_payloads {
+      // Only the length of the buffer is needed, to check views on it.
+      let (byte_length, _) =
+        read_js_array_buffer_lengths(de, input, is_resizable)?;
+      input.ensure_minimum_available(byte_length as usize)?;
+      input.read_bytes(byte_length as usize)?;
+      kind = ObjectKind::ArrayBuffer(byte_length);
+      visitor.assign_id(kind, offset);
+      Ok(())
+    } else {
+      let array_buffer = read_js_array_buffer(de, input, is_resizable)?;
+      kind = ObjectKind::ArrayBuffer(array_buffer.byte_length());
+      visitor.assign_id(kind, offset);
+      visitor.visitor().array_buffer(array_buffer)
+    }
   } else if tag == SerializationTag::ArrayBufferTransfer as u8 {
     let array_buffer = read_transferred_js_array_buffer(de, input)?;
-    let heap_value = HeapValue::ArrayBuffer(array_buffer);
-    let reference = heap.insert(heap_value);
-    Ok(Value::HeapReference(reference))
+    kind = ObjectKind::ArrayBuffer(array_buffer.byte_length());
+    visitor.assign_id(kind, offset);
+    visitor.visitor().array_buffer(array_buffer)
   } else if tag == SerializationTag::SharedArrayBuffer as u8 {
-    Err(input.err(ParseErrorKind::SharedArrayBufferNotSupported))
-  } else if tag == SerializationTag::Error as u8 {
-    let reference = heap.reserve();
-    let error = read_js_error(de, input, heap)?;
-    let heap_value = HeapValue::Error(error);
-    heap.insert_reserved(reference, heap_value);
-    Ok(Value::HeapReference(reference))
+    let sab = read_shared_array_buffer(de, input)?;
+    kind = ObjectKind::SharedArrayBuffer(sab.byte_length());
+    visitor.assign_id(kind, offset);
+    visitor.visitor().shared_array_buffer(sab)
   } else if tag == SerializationTag::WasmModuleTransfer as u8 {
-    Err(input.err(ParseErrorKind::WasmModuleTransferNotSupported))
+    let module = read_wasm_module_transfer(de, input)?;
+    visitor.assign_id(ObjectKind::Other, offset);
+    visitor.visitor().wasm_module(module)
   } else if tag == SerializationTag::WasmMemoryTransfer as u8 {
-    Err(input.err(ParseErrorKind::SharedArrayBufferNotSupported))
+    let maximum_pages = input.read_zigzag()?;
+    allocate(de, input, size_of::<Frame>())?;
+    visitor.assign_id(ObjectKind::Other, offset);
+    emit(input, visitor.visitor().begin_wasm_memory(maximum_pages))?;
+    return Ok(Begin::Frame(Frame::WasmMemory { has_buffer: false }));
   } else if tag == SerializationTag::HostObject as u8 {
-    Err(input.err(ParseErrorKind::HostObjectNotSupported))
-  } else if tag == SerializationTag::SharedObject as u8 {
-    Err(input.err(ParseErrorKind::SharedObjectNotSupported))
+    if de.node_format.is_some() {
+      return read_node_host_object(de, input, visitor, offset);
+    } else {
+      let host_object = read_host_object(de, input)?;
+      visitor.assign_id(ObjectKind::Other, offset);
+      visitor.visitor().host_object(host_object)
+    }
+  } else if tag == SerializationTag::SharedObject as u8 && de.version >= 15 {
+    let shared_value_id = input.read_varint()?;
+    visitor.assign_id(ObjectKind::Other, offset);
+    visitor.visitor().shared_object(shared_value_id)
+  } else if tag == SerializationTag::SharedObject as u8 && de.version >= 13 {
+    return Err(input.err(ParseErrorKind::SharedObjectNotSupported));
+  } else if de.version < 13 {
+    // Before there was an explicit tag for host objects, all unknown tags
+    // were delegated to the host, which reads the tag itself.
+    input.unread_byte();
+    let host_object = read_host_object(de, input)?;
+    visitor.assign_id(ObjectKind::Other, offset);
+    visitor.visitor().host_object(host_object)
   } else {
-    Err(input.err(ParseErrorKind::UnexpectedTag(tag)))
+    return Err(input.err(ParseErrorKind::UnexpectedTag(tag)));
+  };
+  emit(input, result)?;
+  Ok(Begin::Value {
+    buffer_byte_length: kind.buffer_byte_length(),
+  })
+}
+
+impl<'a> Frame<'a> {
+  /// The properties of the object, if a property key is read next.
+  fn expects_key(&mut self) -> Option<&mut Properties<'a>> {
+    let properties = match self {
+      Frame::Object { properties } | Frame::SparseArray { properties, .. } => {
+        properties
+      }
+      Frame::DenseArray {
+        length,
+        elements,
+        properties,
+      } if elements == length => properties,
+      _ => return None,
+    };
+    properties.key.is_none().then_some(properties)
+  }
+
+  /// Whether the buffer of a WebAssembly memory is read next.
+  fn expects_buffer(&self) -> bool {
+    matches!(self, Frame::WasmMemory { has_buffer: false })
+  }
+
+  /// Whether an element of a dense array is read next.
+  fn expects_element(&self) -> bool {
+    matches!(
+      self,
+      Frame::DenseArray { length, elements, .. } if elements < length
+    )
+  }
+
+  /// Count a value that was read as part of the object.
+  fn count_value(&mut self) {
+    match self {
+      Frame::Object { properties } | Frame::SparseArray { properties, .. } => {
+        properties.count_value()
+      }
+      Frame::DenseArray {
+        length,
+        elements,
+        properties,
+      } => {
+        if elements < length {
+          *elements += 1;
+        } else {
+          properties.count_value();
+        }
+      }
+      Frame::Map { values } | Frame::Set { values } => *values += 1,
+      Frame::Error { .. } => {}
+      Frame::WasmMemory { has_buffer } => *has_buffer = true,
+    }
+  }
+
+  /// The position in the object of the value that is read next.
+  fn path_segment(&self) -> PathSegment {
+    match self {
+      Frame::Object { properties } | Frame::SparseArray { properties, .. } => {
+        properties.path_segment()
+      }
+      Frame::DenseArray {
+        length,
+        elements,
+        properties,
+      } => {
+        if elements < length {
+          PathSegment::Element(*elements)
+        } else {
+          properties.path_segment()
+        }
+      }
+      Frame::Map { values } => {
+        let index = *values as usize / 2;
+        if values % 2 == 0 {
+          PathSegment::MapKey(index)
+        } else {
+          PathSegment::MapValue(index)
+        }
+      }
+      Frame::Set { values } => PathSegment::SetValue(*values as usize),
+      Frame::Error { .. } => PathSegment::ErrorCause,
+      Frame::WasmMemory { .. } => PathSegment::WasmMemoryBuffer,
+    }
+  }
+}
+
+impl<'a> Properties<'a> {
+  fn new(end_tag: SerializationTag) -> Self {
+    Self {
+      end_tag,
+      count: 0,
+      key: None,
+    }
+  }
+
+  fn path_segment(&self) -> PathSegment {
+    match &self.key {
+      Some(key) => PathSegment::Property(key.clone().into_owned()),
+      None => PathSegment::PropertyKey(self.count as usize),
+    }
+  }
+
+  /// Whether the end tag follows. It can only follow between properties.
+  fn read_end(&self, input: &mut Input<'a>) -> Result<bool, ParseError> {
+    Ok(self.key.is_none() && input.maybe_read_tag(self.end_tag)?)
+  }
+
+  /// Count the value of the property whose key was read last.
+  fn count_value(&mut self) {
+    self.key = None;
+    self.count += 1;
+  }
+
+  /// Read the property count that follows the end tag, and check it.
+  fn read_count(
+    &self,
+    de: &mut ValueDeserializer,
+    input: &mut Input<'a>,
+  ) -> Result<(), ParseError> {
+    let property_count = input.read_varint()?;
+    if property_count != self.count {
+      let err = input.err(ParseErrorKind::InvalidPropertyCount {
+        expected: property_count,
+        actual: self.count,
+      });
+      tolerate(de, err)?;
+    }
+    Ok(())
+  }
+}
+
+/// Whether all contents of the object in `frame` have been read. Holes in
+/// dense arrays and the fields of errors other than the cause are read here,
+/// because they are not values.
+fn read_frame_end<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+  frame: &mut Frame<'a>,
+) -> Result<bool, ParseError> {
+  match frame {
+    Frame::Object { properties } | Frame::SparseArray { properties, .. } => {
+      properties.read_end(input)
+    }
+    Frame::DenseArray {
+      length,
+      elements,
+      properties,
+    } => {
+      while elements < length {
+        if !input.maybe_read_tag(SerializationTag::TheHole)? {
+          return Ok(false);
+        }
+        emit(input, visitor.visitor().hole())?;
+        *elements += 1;
+      }
+      properties.read_end(input)
+    }
+    Frame::Map { values } => {
+      Ok(*values % 2 == 0 && input.maybe_read_tag(SerializationTag::EndJsMap)?)
+    }
+    Frame::Set { .. } => input.maybe_read_tag(SerializationTag::EndJsSet),
+    Frame::Error { error } => read_js_error_fields(de, input, error),
+    Frame::WasmMemory { has_buffer } => Ok(*has_buffer),
+  }
+}
+
+/// Read and check the counts that follow the end tag of the object in
+/// `frame`.
+fn read_frame_trailer<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  frame: &Frame<'a>,
+) -> Result<(), ParseError> {
+  let err = match frame {
+    Frame::Object { properties } => {
+      properties.read_count(de, input)?;
+      None
+    }
+    Frame::SparseArray { length, properties } => {
+      properties.read_count(de, input)?;
+      let expected_length = input.read_varint()?;
+      (expected_length != *length).then(|| {
+        input.err(ParseErrorKind::InvalidArrayElementsLength {
+          expected: expected_length,
+          actual: *length,
+        })
+      })
+    }
+    Frame::DenseArray {
+      length, properties, ..
+    } => {
+      properties.read_count(de, input)?;
+      let final_elements_length = input.read_varint()?;
+      (final_elements_length != *length).then(|| {
+        input.err(ParseErrorKind::InvalidArrayElementsLength {
+          expected: final_elements_length,
+          actual: *length,
+        })
+      })
+    }
+    Frame::Map { values } | Frame::Set { values } => {
+      let expected_length = input.read_varint()?;
+      (expected_length != *values).then(|| {
+        input.err(ParseErrorKind::InvalidEntryCount {
+          expected: expected_length,
+          actual: *values,
+        })
+      })
+    }
+    Frame::Error { .. } | Frame::WasmMemory { .. } => None,
+  };
+  match err {
+    Some(err) => tolerate(de, err),
+    None => Ok(()),
+  }
+}
+
+/// Report the end of the object in `frame`.
+fn end_frame<'a>(
+  input: &Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+  frame: Frame<'a>,
+) -> Result<(), ParseError> {
+  let visitor = visitor.visitor();
+  let result = match frame {
+    Frame::Object { properties } => visitor.end_object(properties.count),
+    Frame::SparseArray { length, properties } => {
+      visitor.end_sparse_array(properties.count, length)
+    }
+    Frame::DenseArray {
+      length, properties, ..
+    } => visitor.end_dense_array(properties.count, length),
+    Frame::Map { values } => visitor.end_map(values / 2),
+    Frame::Set { values } => visitor.end_set(values),
+    Frame::Error { error } => {
+      visitor.end_error(error.name, error.message, error.stack)
+    }
+    Frame::WasmMemory { .. } => visitor.end_wasm_memory(),
+  };
+  emit(input, result)
+}
+
+/// Account for `bytes` bytes that are about to be allocated.
+fn allocate(
+  de: &mut ValueDeserializer,
+  input: &Input<'_>,
+  bytes: usize,
+) -> Result<(), ParseError> {
+  de.allocated = de.allocated.saturating_add(bytes);
+  let limit = de.options.max_allocation;
+  if de.allocated > limit {
+    return Err(input.err(ParseErrorKind::AllocationLimitExceeded { limit }));
+  }
+  Ok(())
+}
+
+/// Account for a heap object that is about to be read.
+fn allocate_heap_object(
+  de: &mut ValueDeserializer,
+  input: &Input<'_>,
+) -> Result<(), ParseError> {
+  let limit = de.options.max_heap_objects;
+  if de.heap_objects >= limit {
+    return Err(input.err(ParseErrorKind::HeapObjectLimitExceeded { limit }));
   }
+  de.heap_objects += 1;
+  allocate(de, input, size_of::<BorrowedHeapValue>())
 }
 
-fn read_bigint(input: &mut Input<'_>) -> Result<BigInt, ParseError> {
+/// Versionless data does not have begin tags for objects and sparse arrays.
+/// Instead, the properties are written first, and the end tag collects them
+/// from a stack of previously read values.
+fn read_legacy_object<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  heap: &mut BorrowedHeapBuilder<'a>,
+) -> Result<BorrowedValue<'a>, ParseError> {
+  let mut stack = vec![];
+  loop {
+    input.skip_padding()?;
+    let Some(tag) = input.peek_byte()? else {
+      break;
+    };
+    let value = if tag == SerializationTag::EndJsObject as u8 {
+      input.read_byte()?;
+      let property_count = input.read_varint()?;
+      let properties =
+        read_legacy_properties(input, &mut stack, property_count)?;
+      allocate_heap_object(de, input)?;
+      let heap_value = BorrowedHeapValue::Object(BorrowedObject { properties });
+      BorrowedValue::HeapReference(heap.insert_without_id(heap_value))
+    } else if tag == SerializationTag::EndSparseJsArray as u8 {
+      input.read_byte()?;
+      let property_count = input.read_varint()?;
+      let length = input.read_varint()?;
+      check_array_length(de, input, length)?;
+      let properties =
+        read_legacy_properties(input, &mut stack, property_count)?;
+      allocate_heap_object(de, input)?;
+      let heap_value = BorrowedHeapValue::SparseArray(BorrowedSparseArray {
+        length,
+        properties,
+      });
+      BorrowedValue::HeapReference(heap.insert_without_id(heap_value))
+    } else if tag == SerializationTag::EndDenseJsArray as u8 {
+      input.read_byte()?;
+      return Err(input.err(ParseErrorKind::UnexpectedTag(tag)));
+    } else {
+      read_object(de, input, heap)?
+    };
+    stack.push(value);
+  }
+  if stack.len() != 1 {
+    return Err(
+      input.err_current(ParseErrorKind::InvalidLegacyValueCount(stack.len())),
+    );
+  }
+  Ok(stack.pop().unwrap())
+}
+
+fn read_legacy_properties<'a>(
+  input: &mut Input<'a>,
+  stack: &mut Vec<BorrowedValue<'a>>,
+  property_count: u32,
+) -> Result<Vec<(BorrowedPropertyKey<'a>, BorrowedValue<'a>)>, ParseError> {
+  if stack.len() / 2 < property_count as usize {
+    return Err(input.err(ParseErrorKind::InvalidPropertyCount {
+      expected: property_count,
+      actual: (stack.len() / 2) as u32,
+    }));
+  }
+  let begin = stack.len() - 2 * property_count as usize;
+  let mut properties = Vec::with_capacity(property_count as usize);
+  let mut values = stack.drain(begin..);
+  while let (Some(key), Some(value)) = (values.next(), values.next()) {
+    properties.push((value_to_property_key(input, key)?, value));
+  }
+  Ok(properties)
+}
+
+fn read_bigint(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'_>,
+) -> Result<BigInt, ParseError> {
   const BIGINT_SIGN_BIT_MASK: u32 = 1;
   const BIGINT_BYTE_LENGTH_MASK: u32 = 0x7FFFFFFE;
   // This bitfield stores both the sign (least significant bit) and the byte
@@ -336,190 +1660,250 @@ fn read_bigint(input: &mut Input<'_>) -> Result<BigInt, ParseError> {
     num_bigint::Sign::Minus
   };
   let byte_length = ((bitfield & BIGINT_BYTE_LENGTH_MASK) >> 1) as usize;
-  let bytes = input.read_bytes(byte_length)?;
-  Ok(BigInt::from_bytes_le(sign, bytes))
+  allocate(de, input, byte_length)?;
+  let bytes = input.read_vec(byte_length)?;
+  Ok(BigInt::from_bytes_le(sign, &bytes))
 }
 
-fn read_utf8_string(input: &mut Input<'_>) -> Result<Wtf8String, ParseError> {
+/// Read the byte length of a string, and account for its contents.
+fn read_string_length(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'_>,
+) -> Result<u32, ParseError> {
   let byte_length = input.read_varint()?;
-  let bytes = input.read_bytes(byte_length as usize)?;
-  let string = Wtf8String::new(bytes.to_vec());
+  let limit = de.options.max_string_length;
+  if byte_length as usize > limit {
+    return Err(
+      input
+        .err(ParseErrorKind::StringLengthLimitExceeded { byte_length, limit }),
+    );
+  }
+  allocate(de, input, byte_length as usize)?;
+  Ok(byte_length)
+}
+
+fn read_utf8_string<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+) -> Result<BorrowedWtf8String<'a>, ParseError> {
+  let byte_length = read_string_length(de, input)?;
+  let bytes = input.read_cow(byte_length as usize)?;
+  let string = BorrowedWtf8String::new(bytes);
   Ok(string)
 }
 
-fn read_one_byte_string(
-  input: &mut Input<'_>,
-) -> Result<OneByteString, ParseError> {
-  let byte_length = input.read_varint()?;
-  let bytes = input.read_bytes(byte_length as usize)?;
-  let string = OneByteString::new(bytes.to_vec());
+/// In strict mode, check that a tagged UTF-8 string could not have been written
+/// as a one byte string, which is what V8 does for such strings.
+fn check_utf8_string(
+  input: &Input<'_>,
+  value: &BorrowedWtf8String<'_>,
+) -> Result<(), ParseError> {
+  if !input.strict {
+    return Ok(());
+  }
+  // Strings with unpaired surrogates are not valid UTF-8, and do not fit in a
+  // one byte string either.
+  let is_one_byte = std::str::from_utf8(value.as_bytes())
+    .is_ok_and(|str| str.chars().all(|c| c as u32 <= 0xFF));
+  if is_one_byte {
+    return Err(input.err(ParseErrorKind::NonCanonicalUtf8String));
+  }
+  Ok(())
+}
+
+fn read_one_byte_string<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+) -> Result<BorrowedOneByteString<'a>, ParseError> {
+  let byte_length = read_string_length(de, input)?;
+  let bytes = input.read_cow(byte_length as usize)?;
+  let string = BorrowedOneByteString::new(bytes);
   Ok(string)
 }
 
-fn read_two_byte_string(
-  input: &mut Input<'_>,
-) -> Result<TwoByteString, ParseError> {
-  let byte_length = input.read_varint()?;
+fn read_two_byte_string<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+) -> Result<BorrowedTwoByteString<'a>, ParseError> {
+  let byte_length = read_string_length(de, input)?;
   if byte_length % 2 != 0 {
     return Err(input.err(ParseErrorKind::InvalidLengthTwoByteString));
   }
-  let bytes = input.read_bytes(byte_length as usize)?;
-  // This allocation is not unbounded, because it will only occur if the input
-  // contained at least byte_length bytes.
+  // V8 pads two byte strings so that their contents are aligned to 2 bytes
+  // within the message.
+  if input.strict && (input.position() - input.message_start) % 2 != 0 {
+    return Err(input.err(ParseErrorKind::NonCanonicalPadding));
+  }
+  if de.skip_payloads {
+    input.ensure_minimum_available(byte_length as usize)?;
+    input.read_bytes(byte_length as usize)?;
+    return Ok(BorrowedTwoByteString::new(&[][..]));
+  }
+  if let Some(bytes) = input.borrowable(byte_length as usize) {
+    if bytes.as_ptr() as usize % align_of::<u16>() == 0 {
+      input.cursor += bytes.len();
+      // Safety: we checked that the bytes are aligned to 2 bytes, and that
+      // their length is a multiple of 2. Any bytes are valid u16s.
+      let chars = unsafe {
+        std::slice::from_raw_parts(
+          bytes.as_ptr() as *const u16,
+          bytes.len() / 2,
+        )
+      };
+      return Ok(BorrowedTwoByteString::new(chars));
+    }
+  }
+  input.ensure_minimum_available(byte_length as usize)?;
+  if input.reader.is_some() {
+    // The length is not bounded by the size of the input, so the characters
+    // are collected as they arrive.
+    let bytes = input.read_vec(byte_length as usize)?;
+    let chars = bytes
+      .chunks_exact(2)
+      .map(|pair| u16::from_ne_bytes([pair[0], pair[1]]))
+      .collect::<Vec<_>>();
+    return Ok(BorrowedTwoByteString::new(chars));
+  }
+  // This allocation is not unbounded when reading from a slice, because it
+  // will only occur if the input contained at least byte_length bytes.
   let mut chars = vec![0u16; byte_length as usize / 2];
-  // Safety: we checked that bytes is a multiple of 2, and chars has the same
-  // length as bytes divided by 2. Therefore, the length of bytes and chars is
-  // the same, and we can copy bytes into chars.
-  unsafe {
-    std::ptr::copy_nonoverlapping(
-      bytes.as_ptr(),
+  // Safety: we checked that byte_length is a multiple of 2, and chars has the
+  // length of byte_length divided by 2. Therefore, chars is exactly
+  // byte_length bytes long, and any bytes are valid u16s.
+  let bytes = unsafe {
+    std::slice::from_raw_parts_mut(
       chars.as_mut_ptr() as *mut u8,
       byte_length as usize,
     )
   };
-  Ok(TwoByteString::new(chars))
+  input.read_into(bytes)?;
+  Ok(BorrowedTwoByteString::new(chars))
 }
 
-fn read_string_value(
+pub(crate) fn read_string_value<'a>(
   de: &mut ValueDeserializer,
-  input: &mut Input<'_>,
-) -> Result<StringValue, ParseError> {
-  if de.recursion_depth > RECURSION_DEPTH_LIMIT {
-    return Err(input.err(ParseErrorKind::TooDeeplyNested));
+  input: &mut Input<'a>,
+) -> Result<BorrowedStringValue<'a>, ParseError> {
+  // Before version 12, strings in regexps and string objects were always
+  // written as raw UTF-8, without a tag.
+  if de.version < 12 {
+    let value = read_utf8_string(de, input)?;
+    return Ok(BorrowedStringValue::Wtf8(value));
   }
-  input.skip_padding();
-  let tag = input.read_byte()?;
-  if tag == SerializationTag::VerifyObjectCount as u8 {
-    // Read the count and ignore it.
-    let _ = input.read_varint()?;
-    de.recursion_depth += 1;
-    let res = read_string_value(de, input);
-    de.recursion_depth -= 1;
-    res
-  } else if tag == SerializationTag::Utf8String as u8 {
-    let value = read_utf8_string(input)?;
-    Ok(StringValue::Wtf8(value))
+  let tag = read_tag(input)?;
+  if tag == SerializationTag::Utf8String as u8 {
+    let value = read_utf8_string(de, input)?;
+    check_utf8_string(input, &value)?;
+    Ok(BorrowedStringValue::Wtf8(value))
   } else if tag == SerializationTag::OneByteString as u8 {
-    let value = read_one_byte_string(input)?;
-    Ok(StringValue::OneByte(value))
+    let value = read_one_byte_string(de, input)?;
+    Ok(BorrowedStringValue::OneByte(value))
   } else if tag == SerializationTag::TwoByteString as u8 {
-    let value = read_two_byte_string(input)?;
-    Ok(StringValue::TwoByte(value))
+    let value = read_two_byte_string(de, input)?;
+    Ok(BorrowedStringValue::TwoByte(value))
   } else {
     Err(input.err(ParseErrorKind::UnexpectedTag(tag)))
   }
 }
 
-fn read_object_reference(
-  input: &mut Input<'_>,
-  heap: &mut HeapBuilder,
-) -> Result<HeapReference, ParseError> {
-  let index = input.read_varint()?;
-  heap
-    .reference_by_id(index)
-    .ok_or_else(|| input.err(ParseErrorKind::InvalidObjectReference(index)))
-}
+/// The maximum number of nested objects that are decoded from their position
+/// in the input, see [read_object_by_id].
+const LAZY_RECURSION_DEPTH_LIMIT: usize = 256;
 
-fn read_js_object_properties(
+/// Decode the object with the given id from its position in the input, which
+/// is only known when reading lazily. The cursor is restored afterwards.
+pub(crate) fn read_object_by_id<'a>(
   de: &mut ValueDeserializer,
-  input: &mut Input<'_>,
-  heap: &mut HeapBuilder,
-  end_tag: SerializationTag,
-) -> Result<Vec<(PropertyKey, Value)>, ParseError> {
-  let mut properties = vec![];
-  loop {
-    if input.maybe_read_tag(end_tag) {
-      break;
-    }
-    let key = match read_object(de, input, heap)? {
-      Value::I32(int) => PropertyKey::I32(int),
-      Value::U32(uint) => PropertyKey::U32(uint),
-      Value::Double(double) => PropertyKey::Double(double),
-      Value::String(str) => PropertyKey::String(str),
-      value => {
-        return Err(input.err(ParseErrorKind::InvalidPropertyKey(value)));
-      }
-    };
-    let value = read_object(de, input, heap)?;
-    properties.push((key, value));
-  }
-  let property_count = input.read_varint()?;
-  if property_count != properties.len() as u32 {
-    return Err(input.err(ParseErrorKind::InvalidPropertyCount {
-      expected: property_count,
-      actual: properties.len() as u32,
-    }));
+  input: &mut Input<'a>,
+  heap: &mut BorrowedHeapBuilder<'a>,
+  id: u32,
+) -> Result<Option<HeapReference>, ParseError> {
+  let Some(position) = de
+    .object_positions
+    .as_ref()
+    .and_then(|positions| positions.get(id as usize))
+    .copied()
+  else {
+    return Ok(None);
+  };
+  if de.recursion_depth > LAZY_RECURSION_DEPTH_LIMIT {
+    return Err(input.err(ParseErrorKind::TooDeeplyNested));
   }
-  Ok(properties)
+  let cursor = input.cursor;
+  let next_id = heap.next_id();
+  input.cursor = position.offset;
+  heap.set_next_id(id);
+  de.recursion_depth += 1;
+  let res = read_object_at_position(de, input, heap, id, position);
+  de.recursion_depth -= 1;
+  input.cursor = cursor;
+  heap.set_next_id(next_id);
+  res.map(Some)
 }
 
-fn read_js_object(
+fn read_object_at_position<'a>(
   de: &mut ValueDeserializer,
-  input: &mut Input<'_>,
-  heap: &mut HeapBuilder,
-) -> Result<Object, ParseError> {
-  let properties =
-    read_js_object_properties(de, input, heap, SerializationTag::EndJsObject)?;
-  Ok(Object { properties })
+  input: &mut Input<'a>,
+  heap: &mut BorrowedHeapBuilder<'a>,
+  id: u32,
+  position: ObjectPosition,
+) -> Result<HeapReference, ParseError> {
+  let Some(buffer_id) = position.view_of else {
+    return match read_object_internal(de, input, heap)? {
+      BorrowedValue::HeapReference(reference) => Ok(reference),
+      _ => Err(input.err(ParseErrorKind::InvalidObjectReference(id))),
+    };
+  };
+  let buffer = match heap.reference_by_id(buffer_id) {
+    Some(buffer) => buffer,
+    None => {
+      read_object_by_id(de, input, heap, buffer_id)?.ok_or_else(|| {
+        input.err_current(ParseErrorKind::InvalidObjectReference(buffer_id))
+      })?
+    }
+  };
+  let buffer_byte_length = match heap.try_open(buffer) {
+    Some(BorrowedHeapValue::ArrayBuffer(ab)) => ab.byte_length(),
+    Some(BorrowedHeapValue::SharedArrayBuffer(sab)) => sab.byte_length(),
+    _ => {
+      return Err(
+        input.err_current(ParseErrorKind::InvalidObjectReference(buffer_id)),
+      )
+    }
+  };
+  input.expect_tag(SerializationTag::ArrayBufferView)?;
+  let view = read_js_array_buffer_view(de, input, buffer_byte_length)?;
+  heap.set_next_id(id);
+  Ok(heap.insert(BorrowedHeapValue::ArrayBufferView(view.into_view(buffer))))
 }
 
-fn read_sparse_js_array(
-  de: &mut ValueDeserializer,
-  input: &mut Input<'_>,
-  heap: &mut HeapBuilder,
-) -> Result<SparseArray, ParseError> {
-  let length = input.read_varint()?;
-  let properties = read_js_object_properties(
-    de,
-    input,
-    heap,
-    SerializationTag::EndSparseJsArray,
-  )?;
-  let expected_length = input.read_varint()?;
-  if expected_length != length {
-    return Err(input.err(ParseErrorKind::InvalidArrayElementsLength {
-      expected: expected_length,
-      actual: length,
-    }));
+fn value_to_property_key<'a>(
+  input: &Input<'a>,
+  value: BorrowedValue<'a>,
+) -> Result<BorrowedPropertyKey<'a>, ParseError> {
+  match value {
+    BorrowedValue::I32(int) => Ok(BorrowedPropertyKey::I32(int)),
+    BorrowedValue::U32(uint) => Ok(BorrowedPropertyKey::U32(uint)),
+    BorrowedValue::Double(double) => Ok(BorrowedPropertyKey::Double(double)),
+    BorrowedValue::String(str) => Ok(BorrowedPropertyKey::String(str)),
+    value => {
+      Err(input.err(ParseErrorKind::InvalidPropertyKey(value.into_owned())))
+    }
   }
-  Ok(SparseArray { length, properties })
 }
 
-fn read_dense_js_array(
-  de: &mut ValueDeserializer,
-  input: &mut Input<'_>,
-  heap: &mut HeapBuilder,
-) -> Result<DenseArray, ParseError> {
-  let length = input.read_varint()?;
-  input.ensure_minimum_available(length as usize)?;
-  // This allocation is not unbounded, because it will only occur if the input
-  // contained at least length bytes. This is checked by the function above.
-  let mut elements = Vec::with_capacity(length as usize);
-  for _ in 0..length {
-    if input.maybe_read_tag(SerializationTag::TheHole) {
-      elements.push(None);
-    } else {
-      let value = read_object(de, input, heap)?;
-      elements.push(Some(value));
-    }
-  }
-  let properties = read_js_object_properties(
-    de,
-    input,
-    heap,
-    SerializationTag::EndDenseJsArray,
-  )?;
-  let final_elements_length = input.read_varint()?;
-  if final_elements_length != length {
-    return Err(input.err(ParseErrorKind::InvalidArrayElementsLength {
-      expected: final_elements_length,
-      actual: length,
-    }));
+fn check_array_length(
+  de: &ValueDeserializer,
+  input: &Input<'_>,
+  length: u32,
+) -> Result<(), ParseError> {
+  let limit = de.options.max_array_length;
+  if length as usize > limit {
+    return Err(
+      input.err(ParseErrorKind::ArrayLengthLimitExceeded { length, limit }),
+    );
   }
-  Ok(DenseArray {
-    elements,
-    properties,
-  })
+  Ok(())
 }
 
 fn read_date(input: &mut Input<'_>) -> Result<Date, ParseError> {
@@ -527,10 +1911,10 @@ fn read_date(input: &mut Input<'_>) -> Result<Date, ParseError> {
   Ok(Date::new(time_since_epoch))
 }
 
-fn read_regexp(
+fn read_regexp<'a>(
   de: &mut ValueDeserializer,
-  input: &mut Input<'_>,
-) -> Result<RegExp, ParseError> {
+  input: &mut Input<'a>,
+) -> Result<BorrowedRegExp<'a>, ParseError> {
   let pattern = read_string_value(de, input)?;
   let flags = input.read_varint()?;
   let flags = RegExpFlags::from_bits(flags)
@@ -543,68 +1927,24 @@ fn read_regexp(
   {
     return Err(input.err(ParseErrorKind::InvalidRegExpFlags(flags.bits())));
   }
-  Ok(RegExp { pattern, flags })
-}
-
-fn read_js_map(
-  de: &mut ValueDeserializer,
-  input: &mut Input<'_>,
-  heap: &mut HeapBuilder,
-) -> Result<Map, ParseError> {
-  let mut entries = vec![];
-
-  loop {
-    if input.maybe_read_tag(SerializationTag::EndJsMap) {
-      break;
-    }
-    let key = read_object(de, input, heap)?;
-    let value = read_object(de, input, heap)?;
-    entries.push((key, value));
-  }
-
-  let expected_length = input.read_varint()?;
-  let actual_length = (entries.len() * 2) as u32;
-  if expected_length != actual_length {
-    return Err(input.err(ParseErrorKind::InvalidEntryCount {
-      expected: expected_length,
-      actual: actual_length,
-    }));
-  }
-
-  Ok(Map { entries })
+  Ok(BorrowedRegExp { pattern, flags })
 }
 
-fn read_js_set(
+/// Read the byte length and maximum byte length of an ArrayBuffer, and account
+/// for its contents.
+fn read_js_array_buffer_lengths(
   de: &mut ValueDeserializer,
-  input: &mut Input<'_>,
-  heap: &mut HeapBuilder,
-) -> Result<Set, ParseError> {
-  let mut values = vec![];
-
-  loop {
-    if input.maybe_read_tag(SerializationTag::EndJsSet) {
-      break;
-    }
-    let value = read_object(de, input, heap)?;
-    values.push(value);
-  }
-
-  let expected_length = input.read_varint()?;
-  if expected_length != values.len() as u32 {
-    return Err(input.err(ParseErrorKind::InvalidEntryCount {
-      expected: expected_length,
-      actual: values.len() as u32,
-    }));
-  }
-
-  Ok(Set { values })
-}
-
-fn read_js_array_buffer(
   input: &mut Input<'_>,
   is_resizable: bool,
-) -> Result<ArrayBuffer, ParseError> {
+) -> Result<(u32, Option<u32>), ParseError> {
   let byte_length = input.read_varint()?;
+  let limit = de.options.max_array_buffer_length;
+  if byte_length as usize > limit {
+    return Err(input.err(ParseErrorKind::ArrayBufferLengthLimitExceeded {
+      byte_length,
+      limit,
+    }));
+  }
   let mut max_byte_length = None;
   if is_resizable {
     let max_byte_length_value = input.read_varint()?;
@@ -618,32 +1958,63 @@ fn read_js_array_buffer(
     }
     max_byte_length = Some(max_byte_length_value);
   }
-  let bytes = input.read_bytes(byte_length as usize)?;
-  // This allocation is not unbounded, because it will only occur if the input
-  // contained at least byte_length bytes.
-  let mut data = alloc_aligned_u8_slice(byte_length as usize);
-  unsafe {
-    std::ptr::copy_nonoverlapping(
-      bytes.as_ptr(),
-      data.as_mut_ptr(),
-      byte_length as usize,
-    )
-  };
-  Ok(ArrayBuffer {
-    data,
+  allocate(de, input, byte_length as usize)?;
+  Ok((byte_length, max_byte_length))
+}
+
+fn read_js_array_buffer<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  is_resizable: bool,
+) -> Result<BorrowedArrayBuffer<'a>, ParseError> {
+  let (byte_length, max_byte_length) =
+    read_js_array_buffer_lengths(de, input, is_resizable)?;
+  if let Some(bytes) = input.borrowable(byte_length as usize) {
+    input.cursor += bytes.len();
+    return Ok(BorrowedArrayBuffer {
+      data: AlignedBytes::new(bytes),
+      max_byte_length,
+    });
+  }
+  input.ensure_minimum_available(byte_length as usize)?;
+  let data = input.read_aligned(byte_length as usize)?;
+  Ok(BorrowedArrayBuffer {
+    data: AlignedBytes::Owned(data),
     max_byte_length,
   })
 }
 
+/// An ArrayBufferView as it is read, before it is attached to its buffer.
+pub(crate) struct ViewFields {
+  pub(crate) kind: ArrayBufferViewKind,
+  pub(crate) byte_offset: u32,
+  pub(crate) length: u32,
+  pub(crate) is_length_tracking: bool,
+  pub(crate) is_backed_by_rab: bool,
+}
+
+impl ViewFields {
+  pub(crate) fn into_view(self, buffer: HeapReference) -> ArrayBufferView {
+    ArrayBufferView {
+      kind: self.kind,
+      buffer,
+      byte_offset: self.byte_offset,
+      length: self.length,
+      is_length_tracking: self.is_length_tracking,
+      is_backed_by_rab: self.is_backed_by_rab,
+    }
+  }
+}
+
 fn read_js_array_buffer_view(
+  de: &ValueDeserializer,
   input: &mut Input<'_>,
   buffer_byte_length: u32,
-  buffer: HeapReference,
-) -> Result<ArrayBufferView, ParseError> {
+) -> Result<ViewFields, ParseError> {
   let tag = input.read_varint_u8()?;
   let byte_offset = input.read_varint()?;
   let byte_length = input.read_varint()?;
-  let flags = input.read_varint()?;
+  let flags = input.read_array_buffer_view_flags(de.version)?.unwrap_or(0);
 
   if byte_offset > buffer_byte_length {
     return Err(input.err(ParseErrorKind::InvalidArrayBufferViewOffset {
@@ -687,6 +2058,9 @@ fn read_js_array_buffer_view(
   } else if tag == ArrayBufferViewTag::Uint32Array as u8 {
     kind = ArrayBufferViewKind::Uint32Array;
     element_size = 4;
+  } else if tag == ArrayBufferViewTag::Float16Array as u8 {
+    kind = ArrayBufferViewKind::Float16Array;
+    element_size = 2;
   } else if tag == ArrayBufferViewTag::Float32Array as u8 {
     kind = ArrayBufferViewKind::Float32Array;
     element_size = 4;
@@ -718,9 +2092,8 @@ fn read_js_array_buffer_view(
 
   let length = byte_length / element_size;
 
-  Ok(ArrayBufferView {
+  Ok(ViewFields {
     kind,
-    buffer,
     byte_offset,
     length,
     is_length_tracking,
@@ -728,99 +2101,213 @@ fn read_js_array_buffer_view(
   })
 }
 
-fn alloc_aligned_u8_slice(size: usize) -> Box<[u8]> {
-  let layout = Layout::from_size_align(size, align_of::<u64>()).unwrap();
-  let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
-  let type_vec = unsafe { Vec::<u8>::from_raw_parts(ptr, size, size) };
-  debug_assert_eq!(type_vec.as_ptr() as usize % align_of::<u64>(), 0);
-  debug_assert_eq!(type_vec.as_ptr() as usize, ptr as usize);
-  type_vec.into_boxed_slice()
-}
-
 fn read_transferred_js_array_buffer(
   de: &mut ValueDeserializer,
   input: &mut Input<'_>,
 ) -> Result<ArrayBuffer, ParseError> {
   let id = input.read_varint()?;
-  let Some(ab) = de.transfer_map.remove(&id) else {
+  // A lazily read value may be decoded any number of times.
+  let ab = if de.object_positions.is_some() {
+    de.transfer_map.get(&id).cloned()
+  } else {
+    de.transfer_map.remove(&id)
+  };
+  let Some(ab) = ab else {
     return Err(input.err(ParseErrorKind::MissingTransferredArrayBuffer(id)));
   };
   Ok(ab)
 }
 
-fn read_js_error(
+fn read_shared_array_buffer(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'_>,
+) -> Result<SharedArrayBuffer, ParseError> {
+  let id = input.read_varint()?;
+  let Some(sab) = de.shared_array_buffers.get(&id) else {
+    return Err(input.err(ParseErrorKind::SharedArrayBufferNotSupported));
+  };
+  Ok(sab.clone())
+}
+
+fn read_wasm_module_transfer(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'_>,
+) -> Result<WasmModule, ParseError> {
+  let transfer_id = input.read_varint()?;
+  de.delegate
+    .as_mut()
+    .and_then(|delegate| delegate.get_wasm_module_from_id(transfer_id))
+    .ok_or_else(|| input.err(ParseErrorKind::WasmModuleTransferNotSupported))
+}
+
+pub(crate) fn read_host_object(
   de: &mut ValueDeserializer,
   input: &mut Input<'_>,
-  heap: &mut HeapBuilder,
-) -> Result<Error, ParseError> {
-  let mut name = ErrorName::Error;
-  let mut message = None;
-  let mut cause = None;
-  let mut stack = None;
+) -> Result<HostObject, ParseError> {
+  let Some(delegate) = de.delegate.as_mut() else {
+    return Err(input.err(ParseErrorKind::HostObjectNotSupported));
+  };
+  delegate.read_host_object(&mut HostObjectReader { input })
+}
+
+/// Read a host object that Node.js wrote, after its tag at `offset`. Array
+/// buffer views are written as the type index of the view, followed by the
+/// bytes that it covers. In the child process IPC format, other host objects
+/// are written as a nested value with a copy of their properties.
+fn read_node_host_object<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+  offset: usize,
+) -> Result<Begin<'a>, ParseError> {
+  if let Some(NodeFormat::ChildProcess) = de.node_format {
+    let tag = input.read_varint()?;
+    if tag != HOST_OBJECT_ARRAY_BUFFER_VIEW {
+      return read_node_nested_value(de, input, visitor, offset);
+    }
+  }
+  let (kind, buffer) = read_node_array_buffer_view(de, input)?;
+  visitor.assign_id(ObjectKind::Other, offset);
+  emit(
+    input,
+    visitor.visitor().host_array_buffer_view(kind, buffer),
+  )?;
+  Ok(Begin::Value {
+    buffer_byte_length: None,
+  })
+}
+
+/// Read the nested value of a host object that Node.js wrote in the child
+/// process IPC format. V8 assigns the host object an object id, and the
+/// nested value the next one, and both refer to the nested value.
+fn read_node_nested_value<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  visitor: &mut dyn Visit<'a>,
+  offset: usize,
+) -> Result<Begin<'a>, ParseError> {
+  let tag = read_tag(input)?;
+  // Node writes a new object, so the nested value can neither be a primitive
+  // nor an object that was read before. Host objects are not nested either,
+  // which keeps this from recursing further.
+  if tag == SerializationTag::ObjectReference as u8
+    || tag == SerializationTag::HostObject as u8
+    || read_primitive(de, input, tag)?.is_some()
+  {
+    return Err(input.err(ParseErrorKind::InvalidHostObject(
+      "the nested value of a host object is not an object".to_string(),
+    )));
+  }
+  visitor.alias_next_id(offset);
+  read_heap_object(de, input, visitor, tag)
+}
+
+/// Read an array buffer view that Node.js wrote as a host object.
+fn read_node_array_buffer_view<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+) -> Result<(ArrayBufferViewKind, BorrowedArrayBuffer<'a>), ParseError> {
+  let type_index = input.read_varint()?;
+  let Some(kind) = array_buffer_view_kind(type_index) else {
+    return Err(input.err(ParseErrorKind::InvalidHostObject(format!(
+      "unknown array buffer view type {}",
+      type_index
+    ))));
+  };
+  // The bytes are encoded like the contents of an ArrayBuffer.
+  let buffer = read_js_array_buffer(de, input, false)?;
+  let byte_length = buffer.byte_length();
+  if byte_length % kind.byte_width() != 0 {
+    return Err(input.err(ParseErrorKind::UnalignedArrayBufferViewLength {
+      byte_length,
+      element_size: kind.byte_width(),
+    }));
+  }
+  Ok((kind, buffer))
+}
+
+/// Read the fields of an error up to its cause or its end. Returns whether the
+/// end was reached.
+fn read_js_error_fields<'a>(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'a>,
+  error: &mut BorrowedError<'a>,
+) -> Result<bool, ParseError> {
   loop {
     let tag = input.read_varint_u8()?;
     if tag == ErrorTag::EvalErrorPrototype as u8 {
-      name = ErrorName::EvalError;
+      error.name = ErrorName::EvalError;
     } else if tag == ErrorTag::RangeErrorPrototype as u8 {
-      name = ErrorName::RangeError;
+      error.name = ErrorName::RangeError;
     } else if tag == ErrorTag::ReferenceErrorPrototype as u8 {
-      name = ErrorName::ReferenceError;
+      error.name = ErrorName::ReferenceError;
     } else if tag == ErrorTag::SyntaxErrorPrototype as u8 {
-      name = ErrorName::SyntaxError;
+      error.name = ErrorName::SyntaxError;
     } else if tag == ErrorTag::TypeErrorPrototype as u8 {
-      name = ErrorName::TypeError;
+      error.name = ErrorName::TypeError;
     } else if tag == ErrorTag::UriErrorPrototype as u8 {
-      name = ErrorName::UriError;
+      error.name = ErrorName::UriError;
     } else if tag == ErrorTag::Message as u8 {
-      let str = read_string_value(de, input)?;
-      message = Some(str);
+      error.message = Some(read_string_value(de, input)?);
     } else if tag == ErrorTag::Stack as u8 {
-      let str = read_string_value(de, input)?;
-      stack = Some(str);
+      error.stack = Some(read_string_value(de, input)?);
     } else if tag == ErrorTag::Cause as u8 {
-      let obj = read_object(de, input, heap)?;
-      cause = Some(obj);
+      return Ok(false);
     } else if tag == ErrorTag::End as u8 {
-      break;
+      return Ok(true);
     } else {
       return Err(input.err(ParseErrorKind::UnexpectedErrorTag(tag)));
     }
   }
-
-  Ok(Error {
-    name,
-    message,
-    stack,
-    cause,
-  })
 }
 
 impl<'a> Input<'a> {
+  /// The position of the cursor in the whole input.
+  pub(crate) fn position(&self) -> usize {
+    self.offset + self.cursor
+  }
+
   /// Creates a new ParseError at the position that is currently being read.
-  fn err_current(&self, kind: ParseErrorKind) -> ParseError {
+  pub(crate) fn err_current(&self, kind: ParseErrorKind) -> ParseError {
     ParseError {
-      position: self.position,
+      position: self.position(),
+      path: vec![],
       kind,
     }
   }
 
   /// Creates a new ParseError at the position most recently read from.
-  fn err(&self, kind: ParseErrorKind) -> ParseError {
+  pub(crate) fn err(&self, kind: ParseErrorKind) -> ParseError {
     ParseError {
-      position: self.position - 1,
+      position: self.position() - 1,
+      path: vec![],
       kind,
     }
   }
 
-  fn expect_eof(&self) -> Result<(), ParseError> {
-    if self.bytes.len() < self.position {
+  pub(crate) fn expect_eof(&mut self) -> Result<(), ParseError> {
+    if self.peek_byte()?.is_some() {
       return Err(self.err_current(ParseErrorKind::ExpectedEof));
     }
     Ok(())
   }
 
-  fn ensure_minimum_available(&self, bytes: usize) -> Result<(), ParseError> {
-    let available = self.bytes.len() - self.position;
+  /// The number of bytes that can be read without reading from the reader.
+  pub(crate) fn buffered(&self) -> usize {
+    self.bytes.len() - self.cursor
+  }
+
+  /// Ensures that at least `bytes` more bytes are available. This can only be
+  /// checked up front when reading from a slice, so it always succeeds when
+  /// reading from a reader.
+  pub(crate) fn ensure_minimum_available(
+    &self,
+    bytes: usize,
+  ) -> Result<(), ParseError> {
+    if self.reader.is_some() {
+      return Ok(());
+    }
+    let available = self.buffered();
     if available < bytes {
       return Err(
         self
@@ -830,33 +2317,195 @@ impl<'a> Input<'a> {
     Ok(())
   }
 
-  fn skip_padding(&mut self) {
-    while self.bytes.get(self.position)
-      == Some(&(SerializationTag::Padding as u8))
+  /// Buffers at least `len` bytes, reading from the reader if necessary.
+  /// Returns false if the input ends before that.
+  pub(crate) fn fill(&mut self, len: usize) -> Result<bool, ParseError> {
+    if self.buffered() >= len {
+      return Ok(true);
+    }
+    let Some(reader) = self.reader.as_mut() else {
+      return Ok(false);
+    };
+    let bytes = self.bytes.to_mut();
+    // Drop the consumed bytes, but keep the last one so it can be unread.
+    let consumed = self.cursor.saturating_sub(1);
+    bytes.drain(..consumed);
+    self.cursor -= consumed;
+    self.offset += consumed;
+    let mut filled = bytes.len();
+    while filled - self.cursor < len {
+      if filled == bytes.len() {
+        // Grow the buffer as the bytes arrive, so that a corrupt length does
+        // not cause a large allocation up front.
+        bytes.resize(filled + READ_CHUNK_SIZE, 0);
+      }
+      match read_retrying(reader, &mut bytes[filled..]) {
+        Ok(0) => break,
+        Ok(read) => filled += read,
+        Err(err) => {
+          bytes.truncate(filled);
+          return Err(ParseError {
+            position: self.offset + filled,
+            path: vec![],
+            kind: ParseErrorKind::Io(err),
+          });
+        }
+      }
+    }
+    bytes.truncate(filled);
+    Ok(filled - self.cursor >= len)
+  }
+
+  pub(crate) fn skip_padding(&mut self) -> Result<(), ParseError> {
+    let mut padding = 0;
+    while self.peek_byte()? == Some(SerializationTag::Padding as u8) {
+      self.cursor += 1;
+      padding += 1;
+    }
+    // V8 only writes a single padding byte, in front of a two byte string
+    // whose contents it aligns. That it aligns them is checked when reading
+    // the string.
+    if self.strict
+      && padding > 0
+      && (padding > 1
+        || self.peek_byte()? != Some(SerializationTag::TwoByteString as u8))
     {
-      self.position += 1;
+      return Err(self.err_current(ParseErrorKind::NonCanonicalPadding));
     }
+    Ok(())
   }
 
-  fn read_byte(&mut self) -> Result<u8, ParseError> {
-    let val = self
-      .bytes
-      .get(self.position)
-      .ok_or_else(|| self.err_current(ParseErrorKind::UnexpectedEof))?;
-    self.position += 1;
-    Ok(*val)
+  pub(crate) fn peek_byte(&mut self) -> Result<Option<u8>, ParseError> {
+    self.fill(1)?;
+    Ok(self.bytes.get(self.cursor).copied())
   }
 
-  fn read_bytes(&mut self, len: usize) -> Result<&[u8], ParseError> {
+  pub(crate) fn read_byte(&mut self) -> Result<u8, ParseError> {
     let val = self
-      .bytes
-      .get(self.position..self.position + len)
+      .peek_byte()?
       .ok_or_else(|| self.err_current(ParseErrorKind::UnexpectedEof))?;
-    self.position += len;
+    self.cursor += 1;
+    Ok(val)
+  }
+
+  /// Moves the cursor back by one byte. This must only be called directly
+  /// after a byte was read.
+  pub(crate) fn unread_byte(&mut self) {
+    self.cursor -= 1;
+  }
+
+  pub(crate) fn read_bytes(&mut self, len: usize) -> Result<&[u8], ParseError> {
+    if !self.fill(len)? {
+      return Err(self.err_current(ParseErrorKind::UnexpectedEof));
+    }
+    let val = &self.bytes[self.cursor..self.cursor + len];
+    self.cursor += len;
     Ok(val)
   }
 
-  fn read_bytes_copied<const N: usize>(
+  /// Reads exactly `dst.len()` bytes into `dst`. When reading from a reader,
+  /// the bytes that are not buffered yet are read directly into `dst`.
+  pub(crate) fn read_into(&mut self, dst: &mut [u8]) -> Result<(), ParseError> {
+    let buffered = self.buffered().min(dst.len());
+    dst[..buffered]
+      .copy_from_slice(&self.bytes[self.cursor..self.cursor + buffered]);
+    self.cursor += buffered;
+    if buffered == dst.len() {
+      return Ok(());
+    }
+    let Some(reader) = self.reader.as_mut() else {
+      return Err(self.err_current(ParseErrorKind::UnexpectedEof));
+    };
+    // The buffer has been consumed completely, so it can be discarded.
+    self.offset += self.cursor;
+    self.cursor = 0;
+    self.bytes.to_mut().clear();
+    let mut rest = &mut dst[buffered..];
+    while !rest.is_empty() {
+      match read_retrying(reader, rest) {
+        Ok(0) => {
+          return Err(self.err_current(ParseErrorKind::UnexpectedEof));
+        }
+        Ok(read) => {
+          self.offset += read;
+          rest = &mut rest[read..];
+        }
+        Err(err) => return Err(self.err_current(ParseErrorKind::Io(err))),
+      }
+    }
+    Ok(())
+  }
+
+  /// Returns the next `len` bytes without consuming them, if the input is a
+  /// slice that contains them. Values can borrow these bytes directly.
+  pub(crate) fn borrowable(&self, len: usize) -> Option<&'a [u8]> {
+    let Cow::Borrowed(bytes) = self.bytes else {
+      return None;
+    };
+    bytes.get(self.cursor..self.cursor.checked_add(len)?)
+  }
+
+  /// Reads `len` bytes, borrowing them from the input if it is a slice.
+  pub(crate) fn read_cow(
+    &mut self,
+    len: usize,
+  ) -> Result<Cow<'a, [u8]>, ParseError> {
+    if let Some(bytes) = self.borrowable(len) {
+      self.cursor += len;
+      return Ok(Cow::Borrowed(bytes));
+    }
+    self.read_vec(len).map(Cow::Owned)
+  }
+
+  /// Reads `len` bytes into a new Vec. When reading from a reader, the Vec
+  /// grows as the bytes arrive, so that a corrupt length does not cause a
+  /// large allocation up front.
+  pub(crate) fn read_vec(&mut self, len: usize) -> Result<Vec<u8>, ParseError> {
+    if self.reader.is_none() || self.buffered() >= len {
+      return self.read_bytes(len).map(|bytes| bytes.to_vec());
+    }
+    let mut vec = Vec::with_capacity(self.buffered() + READ_CHUNK_SIZE);
+    while vec.len() < len {
+      let chunk = (len - vec.len()).min(READ_CHUNK_SIZE);
+      if !self.fill(chunk)? {
+        return Err(self.err_current(ParseErrorKind::UnexpectedEof));
+      }
+      vec.extend_from_slice(&self.bytes[self.cursor..self.cursor + chunk]);
+      self.cursor += chunk;
+    }
+    Ok(vec)
+  }
+
+  /// Reads `len` bytes into a new buffer that is aligned to 8 bytes. Like in
+  /// [Input::read_vec], the buffer grows as the bytes arrive when reading from
+  /// a reader.
+  pub(crate) fn read_aligned(
+    &mut self,
+    len: usize,
+  ) -> Result<AlignedBox, ParseError> {
+    let mut data = AlignedBox::zeroed(self.capacity_for(len));
+    let mut filled = 0;
+    loop {
+      self.read_into(&mut data[filled..])?;
+      filled = data.len();
+      if filled == len {
+        return Ok(data);
+      }
+      data.resize(filled.saturating_mul(2).min(len));
+    }
+  }
+
+  /// How many of `count` items, each at least one byte long, to make room for
+  /// up front. When reading from a reader, the input is not known to be long
+  /// enough for all of them, so this is limited by what is buffered.
+  pub(crate) fn capacity_for(&self, count: usize) -> usize {
+    if self.reader.is_none() {
+      return count;
+    }
+    count.min(self.buffered() + READ_CHUNK_SIZE)
+  }
+
+  pub(crate) fn read_bytes_copied<const N: usize>(
     &mut self,
   ) -> Result<[u8; N], ParseError> {
     let bytes = self.read_bytes(N)?;
@@ -865,8 +2514,11 @@ impl<'a> Input<'a> {
     Ok(val)
   }
 
-  fn expect_tag(&mut self, tag: SerializationTag) -> Result<(), ParseError> {
-    self.skip_padding();
+  pub(crate) fn expect_tag(
+    &mut self,
+    tag: SerializationTag,
+  ) -> Result<(), ParseError> {
+    self.skip_padding()?;
     let byte = self.read_byte()?;
     if byte != tag as u8 {
       return Err(self.err(ParseErrorKind::ExpectedTag(tag, byte)));
@@ -874,18 +2526,19 @@ impl<'a> Input<'a> {
     Ok(())
   }
 
-  fn maybe_read_tag(&mut self, tag: SerializationTag) -> bool {
-    self.skip_padding();
-    match self.bytes.get(self.position) {
-      Some(byte) if *byte == tag as u8 => {
-        self.position += 1;
-        true
-      }
-      _ => false,
+  pub(crate) fn maybe_read_tag(
+    &mut self,
+    tag: SerializationTag,
+  ) -> Result<bool, ParseError> {
+    self.skip_padding()?;
+    if self.peek_byte()? == Some(tag as u8) {
+      self.cursor += 1;
+      return Ok(true);
     }
+    Ok(false)
   }
 
-  fn read_varint(&mut self) -> Result<u32, ParseError> {
+  pub(crate) fn read_varint(&mut self) -> Result<u32, ParseError> {
     let mut value = 0u32;
     let mut i = 0;
     loop {
@@ -893,13 +2546,30 @@ impl<'a> Input<'a> {
       value |= ((byte & 0b01111111) as u32) << (i * 7);
       i += 1;
       if byte & 0b10000000 == 0 || i > size_of::<u32>() {
+        self.check_varint(i, byte, u32::BITS)?;
+        break;
+      }
+    }
+    Ok(value)
+  }
+
+  pub(crate) fn read_varint_u64(&mut self) -> Result<u64, ParseError> {
+    let mut value = 0u64;
+    let mut i = 0;
+    loop {
+      let byte = self.read_byte()?;
+      value |= ((byte & 0b01111111) as u64) << (i * 7);
+      i += 1;
+      // A u64 needs up to ten 7-bit groups.
+      if byte & 0b10000000 == 0 || i >= 10 {
+        self.check_varint(i, byte, u64::BITS)?;
         break;
       }
     }
     Ok(value)
   }
 
-  fn read_varint_u8(&mut self) -> Result<u8, ParseError> {
+  pub(crate) fn read_varint_u8(&mut self) -> Result<u8, ParseError> {
     let mut value = 0u8;
     let mut i = 0;
     loop {
@@ -907,19 +2577,75 @@ impl<'a> Input<'a> {
       value |= (byte & 0b01111111) << (i * 7);
       i += 1;
       if byte & 0b10000000 == 0 || i > size_of::<u8>() {
+        self.check_varint(i, byte, u8::BITS)?;
         break;
       }
     }
     Ok(value)
   }
 
-  fn read_zigzag(&mut self) -> Result<i32, ParseError> {
+  /// Check that a varint for a value of `bits` bits, which was `len` bytes
+  /// long and ended with `last`, has no bits beyond `bits`. In strict mode,
+  /// also check that it is as short as possible.
+  fn check_varint(
+    &self,
+    len: usize,
+    last: u8,
+    bits: u32,
+  ) -> Result<(), ParseError> {
+    let max_len = (bits as usize + 6) / 7;
+    // The number of bits of the last group that are part of the value.
+    let last_bits = bits as usize - (max_len - 1) * 7;
+    if len == max_len && last >> last_bits != 0 {
+      return Err(self.err(ParseErrorKind::VarintOverflow));
+    }
+    if self.strict && len > 1 && last == 0 {
+      return Err(self.err(ParseErrorKind::NonCanonicalVarint));
+    }
+    Ok(())
+  }
+
+  /// Read the flags that follow an array buffer view, if data of the given
+  /// version has them. Flags were added in version 14, but some V8 releases
+  /// already wrote them in version 13 data (https://crbug.com/1284506). Flags
+  /// are at most 3, and in version 13 data without flags a view is followed
+  /// by a tag, by padding, or by nothing, so a following byte of at most 3 is
+  /// read as flags. Padding is a 0 byte, which means the same as no flags.
+  pub(crate) fn read_array_buffer_view_flags(
+    &mut self,
+    version: u32,
+  ) -> Result<Option<u32>, ParseError> {
+    if version >= 14 {
+      return Ok(Some(self.read_varint()?));
+    }
+    if version == 13 {
+      if let Some(flags @ 0..=3) = self.peek_byte()? {
+        self.cursor += 1;
+        return Ok(Some(flags as u32));
+      }
+    }
+    Ok(None)
+  }
+
+  pub(crate) fn read_zigzag(&mut self) -> Result<i32, ParseError> {
     let unsigned = self.read_varint()?;
     Ok((unsigned >> 1) as i32 ^ -((unsigned & 1) as i32))
   }
 
-  fn read_double(&mut self) -> Result<f64, ParseError> {
+  pub(crate) fn read_double(&mut self) -> Result<f64, ParseError> {
     let bytes = self.read_bytes_copied::<8>()?;
     Ok(f64::from_le_bytes(bytes))
   }
 }
+
+fn read_retrying(
+  reader: &mut dyn Read,
+  buf: &mut [u8],
+) -> std::io::Result<usize> {
+  loop {
+    match reader.read(buf) {
+      Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+      res => return res,
+    }
+  }
+}
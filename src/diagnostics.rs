@@ -0,0 +1,130 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use crate::disasm::tokenize;
+use crate::BorrowedPropertyKey;
+use crate::ParseError;
+use crate::PropertyKey;
+
+/// A step on the path from the root value to the value in which a
+/// [ParseError] occurred.
+#[derive(Debug, Clone)]
+pub enum PathSegment {
+  /// The value of the property with this key, of an object or array.
+  Property(PropertyKey),
+  /// The key of the property at this index, of an object or array.
+  PropertyKey(usize),
+  /// The element at this index of a dense array.
+  Element(u32),
+  /// The key of the entry at this index of a map.
+  MapKey(usize),
+  /// The value of the entry at this index of a map.
+  MapValue(usize),
+  /// The value at this index of a set.
+  SetValue(usize),
+  /// The cause of an error.
+  ErrorCause,
+  /// The buffer of a WebAssembly memory.
+  WasmMemoryBuffer,
+}
+
+/// Displays a path like `$.users[3].avatar`.
+pub(crate) struct DisplayPath<'p>(pub(crate) &'p [PathSegment]);
+
+impl Display for DisplayPath<'_> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "$")?;
+    for segment in self.0 {
+      match segment {
+        PathSegment::Property(BorrowedPropertyKey::String(key)) => {
+          let key = key.to_string();
+          if is_identifier(&key) {
+            write!(f, ".{}", key)?;
+          } else {
+            write!(f, "[{:?}]", key)?;
+          }
+        }
+        PathSegment::Property(BorrowedPropertyKey::I32(index)) => {
+          write!(f, "[{}]", index)?
+        }
+        PathSegment::Property(BorrowedPropertyKey::U32(index)) => {
+          write!(f, "[{}]", index)?
+        }
+        PathSegment::Property(BorrowedPropertyKey::Double(key)) => {
+          write!(f, "[{}]", key)?
+        }
+        PathSegment::PropertyKey(index) => {
+          write!(f, "<property {} key>", index)?
+        }
+        PathSegment::Element(index) => write!(f, "[{}]", index)?,
+        PathSegment::MapKey(index) => write!(f, "<map entry {} key>", index)?,
+        PathSegment::MapValue(index) => {
+          write!(f, "<map entry {} value>", index)?
+        }
+        PathSegment::SetValue(index) => write!(f, "<set value {}>", index)?,
+        PathSegment::ErrorCause => write!(f, ".cause")?,
+        PathSegment::WasmMemoryBuffer => write!(f, ".buffer")?,
+      }
+    }
+    Ok(())
+  }
+}
+
+fn is_identifier(key: &str) -> bool {
+  let mut chars = key.chars();
+  chars
+    .next()
+    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// The ` in <path>` part of the message of a [ParseError], if it has a path.
+pub(crate) fn in_path(path: &[PathSegment]) -> String {
+  if path.is_empty() {
+    String::new()
+  } else {
+    format!(" in {}", DisplayPath(path))
+  }
+}
+
+/// The number of bytes that are shown before and after the position of the
+/// error by [ErrorContext].
+const CONTEXT_BYTES: usize = 8;
+
+/// Displays a [ParseError] followed by a hexdump of the input around the
+/// position of the error, one byte per line. Bytes that start a token are
+/// annotated with it, as it is shown by [crate::disassemble]. Created by
+/// [ParseError::with_context].
+pub struct ErrorContext<'e> {
+  pub(crate) error: &'e ParseError,
+  pub(crate) input: &'e [u8],
+}
+
+impl Display for ErrorContext<'_> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "{}", self.error)?;
+    let position = self.error.position();
+    let start = position.saturating_sub(CONTEXT_BYTES);
+    let end = position
+      .saturating_add(CONTEXT_BYTES + 1)
+      .min(self.input.len());
+    let tokens = tokenize(self.input, end);
+    for (offset, byte) in self.input.iter().enumerate().take(end).skip(start) {
+      let marker = if offset == position { ">" } else { " " };
+      let char = if byte.is_ascii_graphic() {
+        *byte as char
+      } else {
+        '.'
+      };
+      write!(f, "{} {:08x}  {:02x}  {}", marker, offset, byte, char)?;
+      match tokens.iter().find(|token| token.start == offset) {
+        Some(token) => writeln!(f, "  {}", token.text)?,
+        None => writeln!(f)?,
+      }
+    }
+    if position >= self.input.len() {
+      writeln!(f, "> {:08x}  end of input", self.input.len())?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,567 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use num_bigint::BigInt;
+
+use crate::de::Input;
+use crate::tags::ArrayBufferViewTag;
+use crate::tags::ErrorTag;
+use crate::tags::SerializationTag;
+use crate::ParseError;
+use crate::ParseErrorKind;
+use crate::RegExpFlags;
+
+/// The number of raw bytes that are shown for a token. Longer tokens are cut
+/// short.
+const BYTES_PER_LINE: usize = 8;
+
+/// The nesting depth up to which tokens are indented. Deeper tokens are marked
+/// with their depth instead, so that the listing does not grow quadratically
+/// with the depth.
+const MAX_INDENT_DEPTH: usize = 64;
+
+/// The number of characters that are shown of a string.
+const STRING_PREVIEW_CHARS: usize = 32;
+
+/// Disassemble serialized data into a listing with one line per token, to help
+/// with debugging serialized data by hand.
+///
+/// Each line shows the offset of the token, its raw bytes, the name of its tag
+/// and its decoded operands, indented by nesting. Tokens nested more than 64
+/// levels deep are marked with their depth, as `[depth 65]`, instead. Tokens
+/// that are assigned an object id are annotated with it, as `#0`. The data is not validated, so
+/// tokens after invalid references, unknown tags or mismatched end tags are
+/// still shown. The listing stops at the first token that can not be read at
+/// all. The contents of host objects are defined by the host, so they are
+/// shown as a single block.
+pub fn disassemble(bytes: &[u8]) -> String {
+  let mut disassembler = Disassembler::new(bytes, usize::MAX);
+  if let Err(err) = disassembler.disassemble() {
+    let position = err.position();
+    let depth = disassembler.stack.len();
+    disassembler.line(position, depth, &format!("error: {}", err.kind));
+  }
+  let mut output = String::new();
+  for token in &disassembler.tokens {
+    let bytes = &bytes[token.start..token.end];
+    let mut raw = String::new();
+    for byte in bytes.iter().take(BYTES_PER_LINE) {
+      write!(raw, "{:02x} ", byte).unwrap();
+    }
+    if bytes.len() > BYTES_PER_LINE {
+      raw.push_str(".. ");
+    }
+    let depth = if token.depth > MAX_INDENT_DEPTH {
+      format!("[depth {}] ", token.depth)
+    } else {
+      String::new()
+    };
+    writeln!(
+      output,
+      "{:08x}  {:<width$} {:indent$}{}{}",
+      token.start,
+      raw,
+      "",
+      depth,
+      token.text,
+      width = BYTES_PER_LINE * 3 + 3,
+      indent = token.depth.min(MAX_INDENT_DEPTH) * 2,
+    )
+    .unwrap();
+  }
+  output
+}
+
+/// The tokens of `bytes`, as they are listed by [disassemble], up to the first
+/// one that can not be read or that starts at or after `end`.
+pub(crate) fn tokenize(bytes: &[u8], end: usize) -> Vec<Token> {
+  let mut disassembler = Disassembler::new(bytes, end);
+  let _ = disassembler.disassemble();
+  disassembler.tokens
+}
+
+/// A line of the listing.
+pub(crate) struct Token {
+  /// The offset of the first byte of the token.
+  pub(crate) start: usize,
+  /// The offset after the last byte of the token.
+  end: usize,
+  /// The nesting depth of the token.
+  depth: usize,
+  /// The name of the tag of the token and its decoded operands.
+  pub(crate) text: String,
+}
+
+enum Frame {
+  /// An object, array, map or set that ends with this tag.
+  Container(SerializationTag),
+  /// The fields of an error. The cause of an error is a value in between its
+  /// fields.
+  Error { in_cause: bool },
+}
+
+struct Disassembler<'a> {
+  input: Input<'a>,
+  version: u32,
+  next_id: u32,
+  stack: Vec<Frame>,
+  tokens: Vec<Token>,
+  /// The offset at which disassembling stops.
+  end: usize,
+}
+
+impl<'a> Disassembler<'a> {
+  fn new(bytes: &'a [u8], end: usize) -> Self {
+    Self {
+      input: Input {
+        bytes: Cow::Borrowed(bytes),
+        cursor: 0,
+        offset: 0,
+        message_start: 0,
+        reader: None,
+        strict: false,
+      },
+      version: 0,
+      next_id: 0,
+      stack: vec![],
+      tokens: vec![],
+      end,
+    }
+  }
+
+  fn disassemble(&mut self) -> Result<(), ParseError> {
+    if self.input.peek_byte()? == Some(SerializationTag::Version as u8) {
+      self.input.read_byte()?;
+      self.version = self.input.read_varint()?;
+      self.line(0, 0, &format!("Version {}", self.version));
+    }
+    while self.input.position() < self.end && self.input.peek_byte()?.is_some()
+    {
+      match self.stack.last() {
+        Some(Frame::Error { in_cause: false }) => self.error_field()?,
+        _ => self.token()?,
+      }
+    }
+    if !self.stack.is_empty() {
+      return Err(self.input.err_current(ParseErrorKind::UnexpectedEof));
+    }
+    Ok(())
+  }
+
+  /// Add a line for the token from `start` to the cursor.
+  fn line(&mut self, start: usize, depth: usize, text: &str) {
+    self.tokens.push(Token {
+      start,
+      end: self.input.position().max(start),
+      depth,
+      text: text.to_string(),
+    });
+  }
+
+  /// The next object id, as it is shown in the listing.
+  fn assign_id(&mut self) -> String {
+    let id = self.next_id;
+    self.next_id += 1;
+    format!("#{}", id)
+  }
+
+  /// Called after a value was read, to leave the cause of an error. A view is
+  /// part of the buffer before it, so the value ends after the view.
+  fn value_done(&mut self) -> Result<(), ParseError> {
+    if self.input.peek_byte()? == Some(SerializationTag::ArrayBufferView as u8)
+    {
+      return Ok(());
+    }
+    if let Some(Frame::Error { in_cause }) = self.stack.last_mut() {
+      *in_cause = false;
+    }
+    Ok(())
+  }
+
+  fn token(&mut self) -> Result<(), ParseError> {
+    let start = self.input.position();
+    let depth = self.stack.len();
+    let byte = self.input.read_byte()?;
+    let Some(tag) = SerializationTag::from_u8(byte) else {
+      if self.version < 13 {
+        // Before there was an explicit tag for host objects, all unknown tags
+        // were delegated to the host.
+        let id = self.assign_id();
+        let text = format!("unknown tag 0x{:02x}, a host object {}", byte, id);
+        self.line(start, depth, &text);
+        return self.host_object_data(depth + 1);
+      }
+      self.line(start, depth, &format!("unknown tag 0x{:02x}", byte));
+      return Ok(());
+    };
+    match tag {
+      SerializationTag::Padding => {
+        while self.input.peek_byte()? == Some(SerializationTag::Padding as u8) {
+          self.input.read_byte()?;
+        }
+        self.line(start, depth, "Padding");
+      }
+      SerializationTag::VerifyObjectCount => {
+        let count = self.input.read_varint()?;
+        self.line(start, depth, &format!("VerifyObjectCount {}", count));
+      }
+      SerializationTag::Version => {
+        self.line(start, depth, "Version (unexpected)");
+      }
+      SerializationTag::TheHole
+      | SerializationTag::Undefined
+      | SerializationTag::Null
+      | SerializationTag::True
+      | SerializationTag::False => {
+        self.line(start, depth, &format!("{:?}", tag));
+        self.value_done()?;
+      }
+      SerializationTag::Int32 => {
+        let value = self.input.read_zigzag()?;
+        self.line(start, depth, &format!("Int32 {}", value));
+        self.value_done()?;
+      }
+      SerializationTag::Uint32 => {
+        let value = self.input.read_varint()?;
+        self.line(start, depth, &format!("Uint32 {}", value));
+        self.value_done()?;
+      }
+      SerializationTag::Double => {
+        let value = self.input.read_double()?;
+        self.line(start, depth, &format!("Double {}", value));
+        self.value_done()?;
+      }
+      SerializationTag::BigInt => {
+        let value = self.bigint()?;
+        self.line(start, depth, &format!("BigInt {}", value));
+        self.value_done()?;
+      }
+      SerializationTag::Utf8String
+      | SerializationTag::OneByteString
+      | SerializationTag::TwoByteString => {
+        let value = self.string(tag)?;
+        self.line(start, depth, &format!("{:?} {}", tag, value));
+        self.value_done()?;
+      }
+      SerializationTag::ObjectReference => {
+        let id = self.input.read_varint()?;
+        let note = if id >= self.next_id { " (invalid)" } else { "" };
+        self.line(start, depth, &format!("ObjectReference {}{}", id, note));
+        self.value_done()?;
+      }
+      SerializationTag::BeginJsObject
+      | SerializationTag::BeginJsMap
+      | SerializationTag::BeginJsSet => {
+        let id = self.assign_id();
+        self.line(start, depth, &format!("{:?} {}", tag, id));
+        let end_tag = match tag {
+          SerializationTag::BeginJsObject => SerializationTag::EndJsObject,
+          SerializationTag::BeginJsMap => SerializationTag::EndJsMap,
+          _ => SerializationTag::EndJsSet,
+        };
+        self.stack.push(Frame::Container(end_tag));
+      }
+      SerializationTag::BeginSparseJsArray
+      | SerializationTag::BeginDenseJsArray => {
+        let length = self.input.read_varint()?;
+        let id = self.assign_id();
+        let text = format!("{:?} length={} {}", tag, length, id);
+        self.line(start, depth, &text);
+        let end_tag = match tag {
+          SerializationTag::BeginSparseJsArray => {
+            SerializationTag::EndSparseJsArray
+          }
+          _ => SerializationTag::EndDenseJsArray,
+        };
+        self.stack.push(Frame::Container(end_tag));
+      }
+      SerializationTag::EndJsObject
+      | SerializationTag::EndJsMap
+      | SerializationTag::EndJsSet => {
+        let count = self.input.read_varint()?;
+        let operand = match tag {
+          SerializationTag::EndJsObject => "numProperties",
+          _ => "length",
+        };
+        let note = self.end_container(tag);
+        let text = format!("{:?} {}={}{}", tag, operand, count, note);
+        self.line(start, self.stack.len(), &text);
+        self.value_done()?;
+      }
+      SerializationTag::EndSparseJsArray
+      | SerializationTag::EndDenseJsArray => {
+        let num_properties = self.input.read_varint()?;
+        let length = self.input.read_varint()?;
+        let note = self.end_container(tag);
+        let text = format!(
+          "{:?} numProperties={} length={}{}",
+          tag, num_properties, length, note
+        );
+        self.line(start, self.stack.len(), &text);
+        self.value_done()?;
+      }
+      SerializationTag::Date | SerializationTag::NumberObject => {
+        let value = self.input.read_double()?;
+        let id = self.assign_id();
+        self.line(start, depth, &format!("{:?} {} {}", tag, value, id));
+        self.value_done()?;
+      }
+      SerializationTag::TrueObject | SerializationTag::FalseObject => {
+        let id = self.assign_id();
+        self.line(start, depth, &format!("{:?} {}", tag, id));
+        self.value_done()?;
+      }
+      SerializationTag::BigIntObject => {
+        let value = self.bigint()?;
+        let id = self.assign_id();
+        self.line(start, depth, &format!("BigIntObject {} {}", value, id));
+        self.value_done()?;
+      }
+      SerializationTag::StringObject => {
+        let id = self.assign_id();
+        self.line(start, depth, &format!("StringObject {}", id));
+        self.nested_string(depth + 1)?;
+        self.value_done()?;
+      }
+      SerializationTag::RegExp => {
+        let id = self.assign_id();
+        self.line(start, depth, &format!("RegExp {}", id));
+        self.nested_string(depth + 1)?;
+        let start = self.input.position();
+        let flags = self.input.read_varint()?;
+        let text = format!(
+          "flags=0x{:x} ({})",
+          flags,
+          RegExpFlags::from_bits_truncate(flags)
+        );
+        self.line(start, depth + 1, &text);
+        self.value_done()?;
+      }
+      SerializationTag::ArrayBuffer
+      | SerializationTag::ResizableArrayBuffer => {
+        let byte_length = self.input.read_varint()?;
+        let mut text = format!("{:?} byteLength={}", tag, byte_length);
+        if let SerializationTag::ResizableArrayBuffer = tag {
+          let max_byte_length = self.input.read_varint()?;
+          write!(text, " maxByteLength={}", max_byte_length).unwrap();
+        }
+        self.input.read_bytes(byte_length as usize)?;
+        let id = self.assign_id();
+        self.line(start, depth, &format!("{} {}", text, id));
+        self.value_done()?;
+      }
+      SerializationTag::ArrayBufferTransfer
+      | SerializationTag::SharedArrayBuffer
+      | SerializationTag::WasmModuleTransfer => {
+        let transfer_id = self.input.read_varint()?;
+        let id = self.assign_id();
+        let text = format!("{:?} transferId={} {}", tag, transfer_id, id);
+        self.line(start, depth, &text);
+        self.value_done()?;
+      }
+      SerializationTag::ArrayBufferView => {
+        let subtag = self.input.read_varint_u8()?;
+        let byte_offset = self.input.read_varint()?;
+        let byte_length = self.input.read_varint()?;
+        let mut text = match ArrayBufferViewTag::from_u8(subtag) {
+          Some(subtag) => format!("ArrayBufferView {:?}", subtag),
+          None => format!("ArrayBufferView unknown subtag 0x{:02x}", subtag),
+        };
+        write!(
+          text,
+          " byteOffset={} byteLength={}",
+          byte_offset, byte_length
+        )
+        .unwrap();
+        if let Some(flags) =
+          self.input.read_array_buffer_view_flags(self.version)?
+        {
+          write!(text, " flags=0x{:x}", flags).unwrap();
+        }
+        let id = self.assign_id();
+        self.line(start, depth, &format!("{} {}", text, id));
+        self.value_done()?;
+      }
+      SerializationTag::WasmMemoryTransfer => {
+        // The SharedArrayBuffer of the memory follows, which ends the value.
+        let maximum_pages = self.input.read_zigzag()?;
+        let id = self.assign_id();
+        let text =
+          format!("WasmMemoryTransfer maximumPages={} {}", maximum_pages, id);
+        self.line(start, depth, &text);
+      }
+      SerializationTag::SharedObject => {
+        let shared_value_id = self.input.read_varint()?;
+        let id = self.assign_id();
+        let text =
+          format!("SharedObject sharedValueId={} {}", shared_value_id, id);
+        self.line(start, depth, &text);
+        self.value_done()?;
+      }
+      SerializationTag::HostObject => {
+        let id = self.assign_id();
+        self.line(start, depth, &format!("HostObject {}", id));
+        self.host_object_data(depth + 1)?;
+      }
+      SerializationTag::Error => {
+        let id = self.assign_id();
+        self.line(start, depth, &format!("Error {}", id));
+        self.stack.push(Frame::Error { in_cause: false });
+      }
+    }
+    Ok(())
+  }
+
+  /// Leave the container that ends with `end_tag`. Returns a note for the
+  /// listing if the end tag does not match the innermost container.
+  fn end_container(&mut self, end_tag: SerializationTag) -> &'static str {
+    match self.stack.last() {
+      Some(Frame::Container(expected)) if *expected as u8 == end_tag as u8 => {
+        self.stack.pop();
+        ""
+      }
+      // Versionless data does not have begin tags for objects and sparse
+      // arrays.
+      _ if self.version == 0 => "",
+      _ => " (unmatched)",
+    }
+  }
+
+  fn error_field(&mut self) -> Result<(), ParseError> {
+    let start = self.input.position();
+    let depth = self.stack.len();
+    let subtag = self.input.read_varint_u8()?;
+    let Some(subtag) = ErrorTag::from_u8(subtag) else {
+      self.line(start, depth, &format!("unknown error tag 0x{:02x}", subtag));
+      return Ok(());
+    };
+    match subtag {
+      ErrorTag::Message | ErrorTag::Stack => {
+        self.line(start, depth, &format!("{:?}", subtag));
+        self.nested_string(depth + 1)?;
+      }
+      ErrorTag::Cause => {
+        self.line(start, depth, "Cause");
+        if let Some(Frame::Error { in_cause }) = self.stack.last_mut() {
+          *in_cause = true;
+        }
+      }
+      ErrorTag::End => {
+        self.stack.pop();
+        self.line(start, depth - 1, "End");
+        self.value_done()?;
+      }
+      _ => self.line(start, depth, &format!("{:?}", subtag)),
+    }
+    Ok(())
+  }
+
+  /// A string that is part of another value, like the pattern of a regexp.
+  fn nested_string(&mut self, depth: usize) -> Result<(), ParseError> {
+    // Before version 12, these strings were always written as raw UTF-8,
+    // without a tag.
+    if self.version < 12 {
+      let start = self.input.position();
+      let value = self.string(SerializationTag::Utf8String)?;
+      self.line(start, depth, &format!("untagged Utf8String {}", value));
+      return Ok(());
+    }
+    loop {
+      let start = self.input.position();
+      let byte = self.input.read_byte()?;
+      match SerializationTag::from_u8(byte) {
+        Some(SerializationTag::Padding) => {
+          while self.input.peek_byte()? == Some(byte) {
+            self.input.read_byte()?;
+          }
+          self.line(start, depth, "Padding");
+        }
+        Some(SerializationTag::VerifyObjectCount) => {
+          let count = self.input.read_varint()?;
+          self.line(start, depth, &format!("VerifyObjectCount {}", count));
+        }
+        Some(
+          tag @ (SerializationTag::Utf8String
+          | SerializationTag::OneByteString
+          | SerializationTag::TwoByteString),
+        ) => {
+          let value = self.string(tag)?;
+          self.line(start, depth, &format!("{:?} {}", tag, value));
+          return Ok(());
+        }
+        _ => {
+          let text =
+            format!("unexpected tag 0x{:02x}, expected a string", byte);
+          self.line(start, depth, &text);
+          return Ok(());
+        }
+      }
+    }
+  }
+
+  /// Read the operands of a string with the given tag, and show its length
+  /// and the start of its contents.
+  fn string(&mut self, tag: SerializationTag) -> Result<String, ParseError> {
+    let byte_length = self.input.read_varint()?;
+    let bytes = self.input.read_bytes(byte_length as usize)?;
+    let value: String = match tag {
+      SerializationTag::OneByteString => {
+        bytes.iter().map(|byte| *byte as char).collect()
+      }
+      SerializationTag::TwoByteString => char::decode_utf16(
+        bytes
+          .chunks_exact(2)
+          .map(|pair| u16::from_le_bytes([pair[0], pair[1]])),
+      )
+      .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+      .collect(),
+      _ => String::from_utf8_lossy(bytes).into_owned(),
+    };
+    let preview: String = value.chars().take(STRING_PREVIEW_CHARS).collect();
+    let ellipsis = if preview.len() < value.len() {
+      "..."
+    } else {
+      ""
+    };
+    Ok(format!(
+      "byteLength={} {:?}{}",
+      byte_length, preview, ellipsis
+    ))
+  }
+
+  /// Read the operands of a BigInt, and show its value and bitfield.
+  fn bigint(&mut self) -> Result<String, ParseError> {
+    let bitfield = self.input.read_varint()?;
+    // The least significant bit is the sign, the next 30 bits are the byte
+    // length.
+    let is_negative = bitfield & 1 != 0;
+    let byte_length = (bitfield & 0x7FFFFFFE) >> 1;
+    let bytes = self.input.read_bytes(byte_length as usize)?;
+    let sign = if is_negative {
+      num_bigint::Sign::Minus
+    } else {
+      num_bigint::Sign::Plus
+    };
+    let value = BigInt::from_bytes_le(sign, bytes);
+    Ok(format!(
+      "{} bitfield=0x{:x} ({}, {} bytes)",
+      value,
+      bitfield,
+      if is_negative { "negative" } else { "positive" },
+      byte_length
+    ))
+  }
+
+  /// The rest of the input, which is read by the host and so can not be
+  /// disassembled.
+  fn host_object_data(&mut self, depth: usize) -> Result<(), ParseError> {
+    let start = self.input.position();
+    let len = self.input.buffered();
+    self.input.read_bytes(len)?;
+    let text = format!("{} bytes of host object data", len);
+    self.line(start, depth, &text);
+    // Whatever contains the host object may have ended in its data.
+    self.stack.clear();
+    Ok(())
+  }
+}
@@ -5,22 +5,22 @@ use std::f32;
 use std::fmt::LowerHex;
 use std::fmt::Write;
 
-use crate::value::ArrayBuffer;
 use crate::value::ArrayBufferView;
 use crate::value::ArrayBufferViewKind;
-use crate::value::PropertyKey;
-use crate::Heap;
+use crate::value::BorrowedArrayBuffer;
+use crate::value::BorrowedPropertyKey;
+use crate::BorrowedHeap;
+use crate::BorrowedHeapValue;
+use crate::BorrowedStringValue;
+use crate::BorrowedTwoByteString;
+use crate::BorrowedValue;
 use crate::HeapReference;
-use crate::HeapValue;
-use crate::StringValue;
-use crate::TwoByteString;
-use crate::Value;
 
-enum FollowUpTasks {
+enum FollowUpTasks<'h> {
   PropertyAssignment {
     target: HeapReference,
-    key: PropertyKey,
-    value: Value,
+    key: BorrowedPropertyKey<'h>,
+    value: BorrowedValue<'h>,
     /// To ensure correct property ordering on objects, all properties for a
     /// given target that have this flag, must be set in the order in which they
     /// are in the follow up task list. If this task is ready to render, but
@@ -31,12 +31,12 @@ enum FollowUpTasks {
   },
   MapSet {
     target: HeapReference,
-    key: Value,
-    value: Value,
+    key: BorrowedValue<'h>,
+    value: BorrowedValue<'h>,
   },
   SetAdd {
     target: HeapReference,
-    value: Value,
+    value: BorrowedValue<'h>,
   },
   ArrayBufferSet {
     target: HeapReference,
@@ -44,8 +44,12 @@ enum FollowUpTasks {
   },
 }
 
+/// How deeply objects are nested in each other before they are assigned to
+/// variables.
+const MAX_INLINE_DEPTH: usize = 64;
+
 struct Displayer<'h, W: Write> {
-  heap: &'h Heap,
+  heap: &'h BorrowedHeap<'h>,
   writer: W,
   indent: usize,
   /// A map that keeps track of which heap objects have been seen, and what heap
@@ -58,7 +62,7 @@ struct Displayer<'h, W: Write> {
   idents: HashMap<HeapReference, String>,
   /// Follow up rendering tasks that need to be done after a given object has
   /// been rendered and assigned to a variable.
-  follow_up_tasks: Vec<FollowUpTasks>,
+  follow_up_tasks: Vec<FollowUpTasks<'h>>,
 }
 
 #[derive(Default, Debug)]
@@ -102,25 +106,21 @@ impl HeapObjectInfo {
 
 impl<'h, W: Write> Displayer<'h, W> {
   fn display(
-    heap: &'h Heap,
-    value: &Value,
+    heap: &'h BorrowedHeap<'h>,
+    value: &BorrowedValue,
     opts: DisplayOptions,
     writer: W,
   ) -> std::fmt::Result {
     let mut deps = DependencyInfo::default();
 
-    macro_rules! visit_and_record {
-      ($heap:expr, $deps:expr, $referrer:expr, $referred:expr) => {
-        let referrer = $deps.objects.get_mut(&$referrer).unwrap();
-        referrer.dependencies.insert($referred);
-        visit($heap, $deps, $referred);
-        let referred = $deps.objects.get_mut(&$referred).unwrap();
-        referred.dependants.insert($referrer);
-        referred.dependants_count += 1;
-      };
-    }
-
-    fn visit(heap: &Heap, deps: &mut DependencyInfo, referrer: HeapReference) {
+    /// Start visiting `referrer`, unless it was seen before. The objects it
+    /// references are pushed to `pending`, to be visited after it.
+    fn enter(
+      heap: &BorrowedHeap,
+      deps: &mut DependencyInfo,
+      pending: &mut Vec<std::vec::IntoIter<HeapReference>>,
+      referrer: HeapReference,
+    ) {
       match deps.objects.entry(referrer) {
         Entry::Occupied(_) => {
           if deps.stack.contains(&referrer) {
@@ -138,93 +138,132 @@ impl<'h, W: Write> Displayer<'h, W> {
       };
       deps.stack.push(referrer);
 
+      let mut referred = vec![];
+      let mut requires_binding = false;
       let heap_value = referrer.open(heap);
       match heap_value {
-        HeapValue::BooleanObject(_)
-        | HeapValue::NumberObject(_)
-        | HeapValue::BigIntObject(_)
-        | HeapValue::StringObject(_)
-        | HeapValue::RegExp(_)
-        | HeapValue::Date(_) => {}
-        HeapValue::Object(object) => {
-          for (_, value) in &object.properties {
-            if let Value::HeapReference(referred) = value {
-              visit_and_record!(heap, deps, referrer, *referred);
-            }
-          }
+        BorrowedHeapValue::BooleanObject(_)
+        | BorrowedHeapValue::NumberObject(_)
+        | BorrowedHeapValue::BigIntObject(_)
+        | BorrowedHeapValue::StringObject(_)
+        | BorrowedHeapValue::RegExp(_)
+        | BorrowedHeapValue::Date(_)
+        | BorrowedHeapValue::HostObject(_)
+        | BorrowedHeapValue::WasmModule(_)
+        | BorrowedHeapValue::SharedObject(_)
+        | BorrowedHeapValue::Placeholder => {}
+        BorrowedHeapValue::Object(object) => {
+          referred.extend(object.properties.iter().map(|(_, value)| value));
         }
-        HeapValue::SparseArray(arr) => {
-          if !arr.properties.is_empty() {
-            let referrer = deps.objects.get_mut(&referrer).unwrap();
-            referrer.requires_binding = true;
-          }
-          for (_, value) in &arr.properties {
-            if let Value::HeapReference(referred) = value {
-              visit_and_record!(heap, deps, referrer, *referred);
-            }
-          }
+        BorrowedHeapValue::SparseArray(arr) => {
+          requires_binding = !arr.properties.is_empty();
+          referred.extend(arr.properties.iter().map(|(_, value)| value));
         }
-        HeapValue::DenseArray(arr) => {
-          for value in arr.elements.iter().flatten() {
-            if let Value::HeapReference(referred) = value {
-              visit_and_record!(heap, deps, referrer, *referred);
-            }
-          }
-          if !arr.properties.is_empty() {
-            let referrer = deps.objects.get_mut(&referrer).unwrap();
-            referrer.requires_binding = true;
-          }
-          for (_, value) in &arr.properties {
-            if let Value::HeapReference(referred) = value {
-              visit_and_record!(heap, deps, referrer, *referred);
-            }
-          }
+        BorrowedHeapValue::DenseArray(arr) => {
+          referred.extend(arr.elements.iter().flatten());
+          requires_binding = !arr.properties.is_empty();
+          referred.extend(arr.properties.iter().map(|(_, value)| value));
         }
-        HeapValue::Map(map) => {
+        BorrowedHeapValue::Map(map) => {
           for (key, value) in &map.entries {
-            if let Value::HeapReference(referred) = key {
-              visit_and_record!(heap, deps, referrer, *referred);
-            }
-            if let Value::HeapReference(referred) = value {
-              visit_and_record!(heap, deps, referrer, *referred);
-            }
+            referred.push(key);
+            referred.push(value);
           }
         }
-        HeapValue::Set(set) => {
-          for value in &set.values {
-            if let Value::HeapReference(referred) = value {
-              visit_and_record!(heap, deps, referrer, *referred);
-            }
-          }
+        BorrowedHeapValue::Set(set) => {
+          referred.extend(&set.values);
         }
-        HeapValue::ArrayBuffer(ab) => {
-          if ab.max_byte_length.is_some() {
-            let referrer = deps.objects.get_mut(&referrer).unwrap();
-            referrer.requires_binding = true;
-          }
+        BorrowedHeapValue::ArrayBuffer(ab) => {
+          requires_binding = ab.max_byte_length.is_some();
         }
-        HeapValue::ArrayBufferView(view) => {
-          visit_and_record!(heap, deps, referrer, view.buffer);
+        BorrowedHeapValue::ArrayBufferView(view) => {
+          pending.push(vec![view.buffer].into_iter());
+          return;
         }
-        HeapValue::Error(err) => {
-          if let Some(Value::HeapReference(referred)) = err.cause {
-            visit_and_record!(heap, deps, referrer, referred);
-          }
-          let referrer = deps.objects.get_mut(&referrer).unwrap();
-          referrer.requires_binding = true;
+        BorrowedHeapValue::SharedArrayBuffer(_) => {
+          // Shared array buffers always need a binding, so that views and
+          // follow up tasks can refer to the same buffer.
+          requires_binding = true;
+        }
+        BorrowedHeapValue::WasmMemory(_) => {
+          // The buffer of a WebAssembly.Memory is created by the memory
+          // itself, so it is not rendered as a separate dependency.
+        }
+        BorrowedHeapValue::Error(err) => {
+          referred.extend(&err.cause);
+          requires_binding = true;
         }
       }
+      if requires_binding {
+        let referrer = deps.objects.get_mut(&referrer).unwrap();
+        referrer.requires_binding = true;
+      }
+      let referred = referred
+        .into_iter()
+        .filter_map(|value| match value {
+          BorrowedValue::HeapReference(referred) => Some(*referred),
+          _ => None,
+        })
+        .collect::<Vec<_>>();
+      pending.push(referred.into_iter());
+    }
 
-      deps.order.push(referrer);
-      deps.stack.pop();
+    /// Record the dependencies of `root` and of everything it references.
+    /// This uses a work stack instead of recursing, so that deeply nested
+    /// values can be displayed.
+    fn visit(
+      heap: &BorrowedHeap,
+      deps: &mut DependencyInfo,
+      root: HeapReference,
+    ) {
+      // For each object on `deps.stack`, the references that were not
+      // visited yet.
+      let mut pending = vec![];
+      enter(heap, deps, &mut pending, root);
+      while let Some(references) = pending.last_mut() {
+        let referrer = *deps.stack.last().unwrap();
+        let Some(referred) = references.next() else {
+          pending.pop();
+          deps.stack.pop();
+          deps.order.push(referrer);
+          continue;
+        };
+        let info = deps.objects.get_mut(&referrer).unwrap();
+        info.dependencies.insert(referred);
+        enter(heap, deps, &mut pending, referred);
+        let info = deps.objects.get_mut(&referred).unwrap();
+        info.dependants.insert(referrer);
+        info.dependants_count += 1;
+      }
     }
 
-    if let Value::HeapReference(reference) = value {
+    if let BorrowedValue::HeapReference(reference) = value {
       visit(heap, &mut deps, *reference);
       let info = deps.objects.get_mut(reference).unwrap();
       info.dependants_count += 1; // the root object has one dependant that isn't in the stack (the displayer)
     }
 
+    // Inlined objects are rendered inside of the object that references them,
+    // which recurses. Assign objects that are nested too deeply to variables
+    // instead. Objects are seen after the objects they reference.
+    let mut heights = HashMap::new();
+    for reference in &deps.order {
+      let info = &deps.objects[reference];
+      let height = info
+        .dependencies
+        .iter()
+        .filter(|referred| deps.objects[*referred].inlineable())
+        .map(|referred| heights.get(referred).copied().unwrap_or(0) + 1)
+        .max()
+        .unwrap_or(0);
+      if height > MAX_INLINE_DEPTH {
+        deps.objects.get_mut(reference).unwrap().requires_binding = true;
+        heights.insert(*reference, 0);
+      } else {
+        heights.insert(*reference, height);
+      }
+    }
+
     let multiline = deps.objects.values().any(|info| !info.inlineable());
 
     let mut this = Self {
@@ -246,13 +285,14 @@ impl<'h, W: Write> Displayer<'h, W> {
       }
     }
 
-    if let Value::HeapReference(reference) = value {
+    if let BorrowedValue::HeapReference(reference) = value {
       assert_eq!(this.order.last(), Some(reference));
 
       for (i, mut reference) in this.order.clone().into_iter().enumerate() {
         let info = this.deps.get(&reference).unwrap();
         if !this.idents.contains_key(&reference) && !info.inlineable() {
-          if let HeapValue::ArrayBuffer(ab) = reference.open(this.heap) {
+          if let BorrowedHeapValue::ArrayBuffer(ab) = reference.open(this.heap)
+          {
             if let Some((
               backing_view_reference,
               _backing_view_info,
@@ -348,9 +388,12 @@ impl<'h, W: Write> Displayer<'h, W> {
                   continue;
                 }
                 write!(this.writer, "new {}({}).set(", kind, ident)?;
-                let HeapValue::ArrayBuffer(buffer) = target.open(this.heap)
-                else {
-                  unreachable!()
+                let buffer = match target.open(this.heap) {
+                  BorrowedHeapValue::ArrayBuffer(buffer) => buffer,
+                  BorrowedHeapValue::SharedArrayBuffer(sab) => {
+                    sab.as_array_buffer()
+                  }
+                  _ => unreachable!(),
                 };
                 this.display_array_buffer_data_array(*kind, buffer)?;
                 writeln!(this.writer, ");")?;
@@ -366,11 +409,11 @@ impl<'h, W: Write> Displayer<'h, W> {
     match opts.format {
       DisplayFormat::Expression if multiline => write!(this.writer, "return ")?,
       DisplayFormat::Expression | DisplayFormat::Repl | DisplayFormat::Eval => {
-        if let Value::HeapReference(reference) = value {
+        if let BorrowedValue::HeapReference(reference) = value {
           let info = this.deps.get(reference).unwrap();
           if info.inlineable() {
             let value = reference.open(this.heap);
-            if matches!(value, HeapValue::Object(..)) {
+            if matches!(value, BorrowedHeapValue::Object(..)) {
               return_has_parens = true;
               write!(this.writer, "(")?;
             }
@@ -397,15 +440,15 @@ impl<'h, W: Write> Displayer<'h, W> {
     Ok(())
   }
 
-  fn is_ready_to_render(&self, value: &Value) -> bool {
+  fn is_ready_to_render(&self, value: &BorrowedValue) -> bool {
     match value {
-      Value::HeapReference(reference) => {
+      BorrowedValue::HeapReference(reference) => {
         let info = self.deps.get(reference).unwrap();
         if info.inlineable() || self.idents.get(reference).is_some() {
           return true;
         }
         match reference.open(self.heap) {
-          HeapValue::ArrayBuffer(ab) => {
+          BorrowedHeapValue::ArrayBuffer(ab) => {
             if let Some((reference, info, _view)) =
               self.array_buffer_view_to_render_array_buffer_in(ab, info)
             {
@@ -422,17 +465,17 @@ impl<'h, W: Write> Displayer<'h, W> {
     }
   }
 
-  fn display_value(&mut self, value: &Value) -> std::fmt::Result {
+  fn display_value(&mut self, value: &BorrowedValue) -> std::fmt::Result {
     match value {
-      Value::Undefined => write!(self.writer, "undefined"),
-      Value::Null => write!(self.writer, "null"),
-      Value::Bool(bool) => write!(self.writer, "{}", bool),
-      Value::I32(val) => write!(self.writer, "{}", val),
-      Value::U32(val) => write!(self.writer, "{}", val),
-      Value::Double(val) => self.display_number(*val),
-      Value::BigInt(val) => write!(self.writer, "{}n", val),
-      Value::String(val) => self.display_string(val),
-      Value::HeapReference(reference) => {
+      BorrowedValue::Undefined => write!(self.writer, "undefined"),
+      BorrowedValue::Null => write!(self.writer, "null"),
+      BorrowedValue::Bool(bool) => write!(self.writer, "{}", bool),
+      BorrowedValue::I32(val) => write!(self.writer, "{}", val),
+      BorrowedValue::U32(val) => write!(self.writer, "{}", val),
+      BorrowedValue::Double(val) => self.display_number(*val),
+      BorrowedValue::BigInt(val) => write!(self.writer, "{}n", val),
+      BorrowedValue::String(val) => self.display_string(val),
+      BorrowedValue::HeapReference(reference) => {
         if let Some(ident) = self.idents.get(reference) {
           write!(self.writer, "{}", ident)
         } else {
@@ -443,15 +486,18 @@ impl<'h, W: Write> Displayer<'h, W> {
     }
   }
 
-  fn display_string(&mut self, string: &StringValue) -> std::fmt::Result {
+  fn display_string(
+    &mut self,
+    string: &BorrowedStringValue,
+  ) -> std::fmt::Result {
     match string {
-      StringValue::Wtf8(string) => {
+      BorrowedStringValue::Wtf8(string) => {
         self.display_string_literal(string.as_str().chars())
       }
-      StringValue::OneByte(string) => {
+      BorrowedStringValue::OneByte(string) => {
         self.display_string_literal(string.as_str().chars())
       }
-      StringValue::TwoByte(string) => self.display_two_byte_str(string),
+      BorrowedStringValue::TwoByte(string) => self.display_two_byte_str(string),
     }
   }
 
@@ -472,7 +518,10 @@ impl<'h, W: Write> Displayer<'h, W> {
     write!(self.writer, "\"")
   }
 
-  fn display_two_byte_str(&mut self, str: &TwoByteString) -> std::fmt::Result {
+  fn display_two_byte_str(
+    &mut self,
+    str: &BorrowedTwoByteString,
+  ) -> std::fmt::Result {
     write!(self.writer, "\"")?;
     str.display_escaped(&mut self.writer)?;
     write!(self.writer, "\"")
@@ -508,36 +557,38 @@ impl<'h, W: Write> Displayer<'h, W> {
 
   fn display_heap_value(
     &mut self,
-    value: &HeapValue,
+    value: &BorrowedHeapValue<'h>,
     reference: &HeapReference,
   ) -> std::fmt::Result {
     match value {
-      HeapValue::BooleanObject(bool) => {
+      BorrowedHeapValue::BooleanObject(bool) => {
         write!(self.writer, "new Boolean({})", bool)?;
       }
-      HeapValue::NumberObject(val) => {
+      BorrowedHeapValue::NumberObject(val) => {
         write!(self.writer, "new Number(")?;
         self.display_number(*val)?;
         write!(self.writer, ")")?;
       }
-      HeapValue::BigIntObject(val) => {
+      BorrowedHeapValue::BigIntObject(val) => {
         write!(self.writer, "new Object({}n)", val)?;
       }
-      HeapValue::StringObject(str) => {
+      BorrowedHeapValue::StringObject(str) => {
         write!(self.writer, "new String(")?;
         self.display_string(str)?;
         write!(self.writer, ")")?;
       }
-      HeapValue::RegExp(regexp) => {
+      BorrowedHeapValue::RegExp(regexp) => {
         write!(self.writer, "new RegExp(")?;
         self.display_string(&regexp.pattern)?;
         if !regexp.flags.is_empty() {
           write!(self.writer, ", ")?;
-          self.display_string(&StringValue::new(regexp.flags.to_string()))?;
+          self.display_string(&BorrowedStringValue::new(
+            regexp.flags.to_string(),
+          ))?;
         }
         write!(self.writer, ")")?;
       }
-      HeapValue::Date(date) => {
+      BorrowedHeapValue::Date(date) => {
         write!(self.writer, "new Date(")?;
         if let Some(ms_since_epoch) = date.ms_since_epoch() {
           write!(self.writer, "{}", ms_since_epoch)?;
@@ -546,7 +597,7 @@ impl<'h, W: Write> Displayer<'h, W> {
         }
         write!(self.writer, ")")?;
       }
-      HeapValue::Object(object) => {
+      BorrowedHeapValue::Object(object) => {
         writeln!(self.writer, "{{")?;
         for (key, value) in &object.properties {
           self.display_indent(1)?;
@@ -572,7 +623,7 @@ impl<'h, W: Write> Displayer<'h, W> {
         self.display_indent(0)?;
         write!(self.writer, "}}")?;
       }
-      HeapValue::SparseArray(arr) => {
+      BorrowedHeapValue::SparseArray(arr) => {
         write!(self.writer, "new Array({})", arr.length)?;
         if !arr.properties.is_empty() {
           for (key, value) in &arr.properties {
@@ -587,7 +638,7 @@ impl<'h, W: Write> Displayer<'h, W> {
           }
         }
       }
-      HeapValue::DenseArray(arr) => {
+      BorrowedHeapValue::DenseArray(arr) => {
         writeln!(self.writer, "[")?;
         for (i, value) in arr.elements.iter().enumerate() {
           self.display_indent(1)?;
@@ -601,7 +652,7 @@ impl<'h, W: Write> Displayer<'h, W> {
                 .follow_up_tasks
                 .push(FollowUpTasks::PropertyAssignment {
                   target: *reference,
-                  key: PropertyKey::I32(i as i32),
+                  key: BorrowedPropertyKey::I32(i as i32),
                   value: value.clone(),
                   requires_ordering: false,
                 });
@@ -625,7 +676,7 @@ impl<'h, W: Write> Displayer<'h, W> {
             });
         }
       }
-      HeapValue::Map(map) => {
+      BorrowedHeapValue::Map(map) => {
         if map.entries.is_empty() {
           writeln!(self.writer, "new Map()")?;
         } else {
@@ -652,7 +703,7 @@ impl<'h, W: Write> Displayer<'h, W> {
           write!(self.writer, "])")?;
         }
       }
-      HeapValue::Set(set) => {
+      BorrowedHeapValue::Set(set) => {
         if set.values.is_empty() {
           writeln!(self.writer, "new Set()")?;
         } else {
@@ -675,7 +726,7 @@ impl<'h, W: Write> Displayer<'h, W> {
           write!(self.writer, "])")?;
         }
       }
-      HeapValue::ArrayBuffer(ab) => {
+      BorrowedHeapValue::ArrayBuffer(ab) => {
         let info = self.deps.get(reference).unwrap();
         if let Some((
           backing_view_reference,
@@ -689,7 +740,7 @@ impl<'h, W: Write> Displayer<'h, W> {
         } else {
           let mut kind = ArrayBufferViewKind::Uint8Array;
           for view_reference in &self.order {
-            if let HeapValue::ArrayBufferView(view) =
+            if let BorrowedHeapValue::ArrayBufferView(view) =
               view_reference.open(self.heap)
             {
               if view.buffer == *reference
@@ -727,17 +778,25 @@ impl<'h, W: Write> Displayer<'h, W> {
           }
         }
       }
-      HeapValue::ArrayBufferView(view) => {
+      BorrowedHeapValue::ArrayBufferView(view) => {
         let buffer_info = self.deps.get(&view.buffer).unwrap();
-        let HeapValue::ArrayBuffer(buffer) = view.buffer.open(self.heap) else {
-          unreachable!()
+        let (buffer, is_shared) = match view.buffer.open(self.heap) {
+          BorrowedHeapValue::ArrayBuffer(buffer) => (buffer, false),
+          BorrowedHeapValue::SharedArrayBuffer(sab) => {
+            (sab.as_array_buffer(), true)
+          }
+          _ => unreachable!(),
+        };
+        let backing_view = if is_shared {
+          None
+        } else {
+          self.array_buffer_view_to_render_array_buffer_in(buffer, buffer_info)
         };
         if let Some((
           backing_view_reference,
           _backing_view_info,
           backing_view,
-        )) =
-          self.array_buffer_view_to_render_array_buffer_in(buffer, buffer_info)
+        )) = backing_view
         {
           assert!(!view.is_backed_by_rab);
           if backing_view_reference == *reference {
@@ -837,7 +896,7 @@ impl<'h, W: Write> Displayer<'h, W> {
           write!(self.writer, ")")?;
         }
       }
-      HeapValue::Error(err) => {
+      BorrowedHeapValue::Error(err) => {
         write!(self.writer, "new {}(", err.name)?;
         if let Some(message) = &err.message {
           self.display_string(message)?;
@@ -862,7 +921,9 @@ impl<'h, W: Write> Displayer<'h, W> {
               .follow_up_tasks
               .push(FollowUpTasks::PropertyAssignment {
                 target: *reference,
-                key: PropertyKey::String(StringValue::new("cause".to_owned())),
+                key: BorrowedPropertyKey::String(BorrowedStringValue::new(
+                  "cause".to_owned(),
+                )),
                 value: cause.clone(),
                 requires_ordering: false,
               });
@@ -871,18 +932,64 @@ impl<'h, W: Write> Displayer<'h, W> {
         write!(self.writer, ")")?;
 
         let stack_value = match &err.stack {
-          Some(stack) => Value::String(stack.clone()),
-          None => Value::Undefined,
+          Some(stack) => BorrowedValue::String(stack.clone()),
+          None => BorrowedValue::Undefined,
         };
         self
           .follow_up_tasks
           .push(FollowUpTasks::PropertyAssignment {
             target: *reference,
-            key: PropertyKey::String(StringValue::new("stack".to_owned())),
+            key: BorrowedPropertyKey::String(BorrowedStringValue::new(
+              "stack".to_owned(),
+            )),
             value: stack_value,
             requires_ordering: false,
           });
       }
+      BorrowedHeapValue::HostObject(host_object) => {
+        host_object.payload().display(&mut self.writer)?;
+      }
+      BorrowedHeapValue::SharedArrayBuffer(sab) => {
+        write!(self.writer, "new SharedArrayBuffer({})", sab.byte_length())?;
+        if sab.as_array_buffer().data.iter().any(|b| *b != 0) {
+          self.follow_up_tasks.push(FollowUpTasks::ArrayBufferSet {
+            target: *reference,
+            kind: ArrayBufferViewKind::Uint8Array,
+          });
+        }
+      }
+      BorrowedHeapValue::WasmMemory(memory) => {
+        const WASM_PAGE_SIZE: u32 = 65536;
+        let BorrowedHeapValue::SharedArrayBuffer(buffer) =
+          memory.buffer.open(self.heap)
+        else {
+          unreachable!()
+        };
+        // The contents of the memory are not rendered.
+        write!(
+          self.writer,
+          "new WebAssembly.Memory({{ initial: {}",
+          buffer.byte_length() / WASM_PAGE_SIZE
+        )?;
+        if memory.maximum_pages >= 0 {
+          write!(self.writer, ", maximum: {}", memory.maximum_pages)?;
+        }
+        write!(self.writer, ", shared: true }})")?;
+      }
+      BorrowedHeapValue::WasmModule(module) => {
+        // Compiled modules can not be recreated without their bytes.
+        write!(
+          self.writer,
+          "undefined /* WebAssembly.Module {} */",
+          module.transfer_id
+        )?;
+      }
+      BorrowedHeapValue::SharedObject(id) => {
+        write!(self.writer, "undefined /* shared object {} */", id)?;
+      }
+      BorrowedHeapValue::Placeholder => {
+        write!(self.writer, "undefined /* unreadable */")?;
+      }
     }
     Ok(())
   }
@@ -896,13 +1003,13 @@ impl<'h, W: Write> Displayer<'h, W> {
 
   fn display_property_key(
     &mut self,
-    key: &crate::value::PropertyKey,
+    key: &BorrowedPropertyKey,
   ) -> std::fmt::Result {
     match key {
-      crate::value::PropertyKey::String(string) => self.display_string(string),
-      crate::value::PropertyKey::I32(num) => write!(self.writer, "[{}]", num),
-      crate::value::PropertyKey::U32(num) => write!(self.writer, "[{}]", num),
-      crate::value::PropertyKey::Double(num) => {
+      BorrowedPropertyKey::String(string) => self.display_string(string),
+      BorrowedPropertyKey::I32(num) => write!(self.writer, "[{}]", num),
+      BorrowedPropertyKey::U32(num) => write!(self.writer, "[{}]", num),
+      BorrowedPropertyKey::Double(num) => {
         write!(self.writer, "[")?;
         self.display_number(*num)?;
         write!(self.writer, "]")
@@ -912,17 +1019,17 @@ impl<'h, W: Write> Displayer<'h, W> {
 
   fn display_property_access(
     &mut self,
-    key: &crate::value::PropertyKey,
+    key: &BorrowedPropertyKey,
   ) -> std::fmt::Result {
     match key {
-      crate::value::PropertyKey::String(string) => {
+      BorrowedPropertyKey::String(string) => {
         write!(self.writer, "[")?;
         self.display_string(string)?;
         write!(self.writer, "]")
       }
-      crate::value::PropertyKey::I32(num) => write!(self.writer, "[{}]", num),
-      crate::value::PropertyKey::U32(num) => write!(self.writer, "[{}]", num),
-      crate::value::PropertyKey::Double(num) => {
+      BorrowedPropertyKey::I32(num) => write!(self.writer, "[{}]", num),
+      BorrowedPropertyKey::U32(num) => write!(self.writer, "[{}]", num),
+      BorrowedPropertyKey::Double(num) => {
         write!(self.writer, "[")?;
         self.display_number(*num)?;
         write!(self.writer, "]")
@@ -941,7 +1048,7 @@ impl<'h, W: Write> Displayer<'h, W> {
   /// This method is only relevant for array buffers.
   fn array_buffer_view_to_render_array_buffer_in<'d>(
     &'d self,
-    array_buffer: &ArrayBuffer,
+    array_buffer: &BorrowedArrayBuffer,
     info: &HeapObjectInfo,
   ) -> Option<(HeapReference, &'d HeapObjectInfo, &'d ArrayBufferView)> {
     if array_buffer.max_byte_length.is_some() {
@@ -950,7 +1057,9 @@ impl<'h, W: Write> Displayer<'h, W> {
     for reference in &self.order {
       if info.dependants.contains(reference) {
         let info = self.deps.get(reference).unwrap();
-        if let HeapValue::ArrayBufferView(view) = reference.open(self.heap) {
+        if let BorrowedHeapValue::ArrayBufferView(view) =
+          reference.open(self.heap)
+        {
           assert!(!view.is_backed_by_rab);
           assert!(!view.is_length_tracking);
           if view.kind != ArrayBufferViewKind::DataView
@@ -969,7 +1078,7 @@ impl<'h, W: Write> Displayer<'h, W> {
   fn display_array_buffer_data_array(
     &mut self,
     kind: ArrayBufferViewKind,
-    buffer: &ArrayBuffer,
+    buffer: &BorrowedArrayBuffer,
   ) -> std::fmt::Result {
     writeln!(self.writer, "[")?;
 
@@ -1068,6 +1177,9 @@ impl<'h, W: Write> Displayer<'h, W> {
       ArrayBufferViewKind::Uint32Array => {
         write_array!(u32, as_u32_slice, display_hex_u)
       }
+      ArrayBufferViewKind::Float16Array => {
+        write_array!(f32, to_f16_vec, display_f32)
+      }
       ArrayBufferViewKind::Float32Array => {
         write_array!(f32, as_f32_slice, display_f32)
       }
@@ -1089,7 +1201,11 @@ impl<'h, W: Write> Displayer<'h, W> {
   }
 }
 
-pub fn display(heap: &Heap, value: &Value, opts: DisplayOptions) -> String {
+pub fn display(
+  heap: &BorrowedHeap,
+  value: &BorrowedValue,
+  opts: DisplayOptions,
+) -> String {
   let mut result = String::new();
   Displayer::display(heap, value, opts, &mut result).unwrap();
   result
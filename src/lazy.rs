@@ -0,0 +1,371 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use crate::de::read_object;
+use crate::de::read_object_by_id;
+use crate::de::read_object_internal;
+use crate::de::visit_object;
+use crate::de::visit_object_internal;
+use crate::de::Input;
+use crate::tags::SerializationTag;
+use crate::value::BorrowedHeapBuilder;
+use crate::visit::ObjectKind;
+use crate::visit::Visit;
+use crate::BorrowedHeap;
+use crate::BorrowedValue;
+use crate::ParseError;
+use crate::ParseErrorKind;
+use crate::ValueDeserializer;
+use crate::ValueVisitor;
+
+/// The position of an object in the input of a [LazyReader].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ObjectPosition {
+  /// The offset of the tag of the object.
+  pub(crate) offset: usize,
+  /// For an ArrayBufferView, the object id of its buffer. Views directly
+  /// follow their buffer in the input, and `offset` is that of the view tag.
+  pub(crate) view_of: Option<u32>,
+  pub(crate) kind: ObjectKind,
+}
+
+/// An indexed input whose value is decoded on demand. Created by
+/// [ValueDeserializer::read_lazy].
+pub struct LazyReader<'a> {
+  de: RefCell<ValueDeserializer>,
+  bytes: &'a [u8],
+  /// The offset of the root value, after the header.
+  root: usize,
+}
+
+impl<'a> LazyReader<'a> {
+  pub(crate) fn new(
+    mut de: ValueDeserializer,
+    bytes: &'a [u8],
+  ) -> Result<Self, ParseError> {
+    let mut input = input_at(bytes, 0, de.options.strict);
+    de.read_header(&mut input)?;
+    if de.version == 0 {
+      return Err(
+        input.err_current(ParseErrorKind::UnsupportedLazyReadVersion(0)),
+      );
+    }
+    let root = input.cursor;
+    index(&mut de, &mut input)?;
+    Ok(Self {
+      de: RefCell::new(de),
+      bytes,
+      root,
+    })
+  }
+
+  pub fn root(&self) -> LazyValue<'_, 'a> {
+    LazyValue {
+      reader: self,
+      offset: self.root,
+      id: 0,
+      by_id: false,
+    }
+  }
+}
+
+fn input_at(bytes: &[u8], cursor: usize, strict: bool) -> Input<'_> {
+  Input {
+    bytes: Cow::Borrowed(bytes),
+    cursor,
+    offset: 0,
+    message_start: 0,
+    reader: None,
+    strict,
+  }
+}
+
+/// Record the positions of all objects in the input.
+fn index(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'_>,
+) -> Result<(), ParseError> {
+  de.object_positions = Some(vec![]);
+  skip_object(de, input, &mut 0)?;
+  input.expect_eof()
+}
+
+/// A value in the input of a [LazyReader] that has not been decoded yet.
+#[derive(Clone, Copy)]
+pub struct LazyValue<'r, 'a> {
+  reader: &'r LazyReader<'a>,
+  /// The offset of the value in the input.
+  offset: usize,
+  /// The object id of the first object in the value.
+  id: u32,
+  /// Whether this is the object with object id `id`, which was found through
+  /// an object reference. Such objects are decoded from their recorded
+  /// position, which matters for ArrayBufferViews.
+  by_id: bool,
+}
+
+impl<'r, 'a> LazyValue<'r, 'a> {
+  /// Decode the value. Objects outside of the value that it references are
+  /// decoded as well.
+  pub fn read(
+    &self,
+  ) -> Result<(BorrowedValue<'a>, BorrowedHeap<'a>), ParseError> {
+    let mut de = self.reader.de.borrow_mut();
+    let de = &mut *de;
+    de.allocated = 0;
+    de.heap_objects = 0;
+    let mut input = input_at(self.reader.bytes, self.offset, de.options.strict);
+    let mut heap = BorrowedHeapBuilder::with_sparse_ids(self.id);
+    let value = if self.by_id {
+      let reference = read_object_by_id(de, &mut input, &mut heap, self.id)?
+        .ok_or_else(|| {
+          input.err_current(ParseErrorKind::InvalidObjectReference(self.id))
+        })?;
+      BorrowedValue::HeapReference(reference)
+    } else {
+      read_object(de, &mut input, &mut heap)?
+    };
+    let heap = heap.build().map_err(|err| input.err_current(err.into()))?;
+    Ok((value, heap))
+  }
+
+  /// Look up a property of an object, an element or property of an array, or
+  /// an entry of a map with a string or number key. Returns None if there is
+  /// no such property, or if the value is not an object, array or map. Values
+  /// before the property are skipped over without being decoded.
+  pub fn get(&self, key: &str) -> Result<Option<Self>, ParseError> {
+    let mut de = self.reader.de.borrow_mut();
+    let de = &mut *de;
+    let (mut input, mut next_id, tag) = self.open(de)?;
+    let input = &mut input;
+    let next_id = &mut next_id;
+    let end_tag = if tag == SerializationTag::BeginJsObject as u8 {
+      *next_id += 1;
+      SerializationTag::EndJsObject
+    } else if tag == SerializationTag::BeginSparseJsArray as u8 {
+      *next_id += 1;
+      input.read_varint()?;
+      SerializationTag::EndSparseJsArray
+    } else if tag == SerializationTag::BeginDenseJsArray as u8 {
+      *next_id += 1;
+      let length = input.read_varint()?;
+      let key_index = key.parse::<u32>().ok().filter(|i| i.to_string() == key);
+      for index in 0..length {
+        let is_key = key_index == Some(index);
+        if input.maybe_read_tag(SerializationTag::TheHole)? {
+          if is_key {
+            return Ok(None);
+          }
+        } else if is_key {
+          return Ok(Some(self.at(input, *next_id)));
+        } else {
+          skip_object(de, input, next_id)?;
+        }
+      }
+      SerializationTag::EndDenseJsArray
+    } else if tag == SerializationTag::BeginJsMap as u8 {
+      *next_id += 1;
+      SerializationTag::EndJsMap
+    } else {
+      return Ok(None);
+    };
+    loop {
+      if input.maybe_read_tag(end_tag)? {
+        return Ok(None);
+      }
+      if key_matches(de, input, next_id, key)? {
+        return Ok(Some(self.at(input, *next_id)));
+      }
+      skip_object(de, input, next_id)?;
+    }
+  }
+
+  /// Look up an element of an array. See [LazyValue::get].
+  pub fn index(&self, index: u32) -> Result<Option<Self>, ParseError> {
+    self.get(&index.to_string())
+  }
+
+  /// Position the input after the tag of this value, following object
+  /// references. Returns the input, the id of the next object, and the tag.
+  fn open(
+    &self,
+    de: &ValueDeserializer,
+  ) -> Result<(Input<'a>, u32, u8), ParseError> {
+    let mut input = input_at(self.reader.bytes, self.offset, de.options.strict);
+    let mut next_id = self.id;
+    loop {
+      input.skip_padding()?;
+      let tag = input.read_byte()?;
+      if tag == SerializationTag::VerifyObjectCount as u8 {
+        input.read_varint()?;
+      } else if tag == SerializationTag::ObjectReference as u8 {
+        let id = input.read_varint()?;
+        let position = de
+          .object_positions
+          .as_ref()
+          .and_then(|positions| positions.get(id as usize))
+          .ok_or_else(|| {
+            input.err(ParseErrorKind::InvalidObjectReference(id))
+          })?;
+        input.cursor = position.offset;
+        next_id = id;
+      } else {
+        return Ok((input, next_id, tag));
+      }
+    }
+  }
+
+  fn at(&self, input: &Input<'_>, next_id: u32) -> Self {
+    Self {
+      reader: self.reader,
+      offset: input.cursor,
+      id: next_id,
+      by_id: false,
+    }
+  }
+}
+
+/// Whether the property key at the cursor is equal to `key`. The key is
+/// consumed either way.
+fn key_matches(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'_>,
+  next_id: &mut u32,
+  key: &str,
+) -> Result<bool, ParseError> {
+  const KEY_TAGS: [SerializationTag; 6] = [
+    SerializationTag::Int32,
+    SerializationTag::Uint32,
+    SerializationTag::Double,
+    SerializationTag::Utf8String,
+    SerializationTag::OneByteString,
+    SerializationTag::TwoByteString,
+  ];
+  input.skip_padding()?;
+  let tag = input.peek_byte()?;
+  if !KEY_TAGS.iter().any(|key_tag| tag == Some(*key_tag as u8)) {
+    skip_object(de, input, next_id)?;
+    return Ok(false);
+  }
+  let value =
+    read_object_internal(de, input, &mut BorrowedHeapBuilder::default())?;
+  Ok(match value {
+    BorrowedValue::I32(int) => int.to_string() == key,
+    BorrowedValue::U32(uint) => uint.to_string() == key,
+    BorrowedValue::Double(double) => double.to_string() == key,
+    BorrowedValue::String(str) => str.to_string() == key,
+    _ => false,
+  })
+}
+
+/// Skips over values without decoding them: the values are read like they
+/// are for [ValueDeserializer::validate], but reported to no one. Objects are
+/// assigned object ids, and their positions are recorded when indexing.
+struct Skipper {
+  /// The object id of the first object in the skipped value.
+  first_id: u32,
+  /// The position of every object in the skipped value.
+  objects: Vec<ObjectPosition>,
+  /// The object id of the object that was read last, which is the buffer of
+  /// an ArrayBufferView that follows.
+  last_id: Option<u32>,
+}
+
+impl ValueVisitor<'_> for Skipper {}
+
+impl<'a> Visit<'a> for Skipper {
+  fn visitor(&mut self) -> &mut dyn ValueVisitor<'a> {
+    self
+  }
+
+  fn assign_id(&mut self, kind: ObjectKind, offset: usize) {
+    let view_of = match kind {
+      ObjectKind::ArrayBufferView => self.last_id,
+      _ => None,
+    };
+    let id = self.first_id + self.objects.len() as u32;
+    self.objects.push(ObjectPosition {
+      offset,
+      view_of,
+      kind,
+    });
+    self.last_id = Some(id);
+  }
+
+  fn object_reference(
+    &mut self,
+    de: &mut ValueDeserializer,
+    input: &mut Input<'a>,
+    id: u32,
+  ) -> Result<ObjectKind, ParseError> {
+    // Objects before the skipped value were indexed already.
+    let position = match id.checked_sub(self.first_id) {
+      Some(index) => self.objects.get(index as usize),
+      None => de
+        .object_positions
+        .as_ref()
+        .and_then(|positions| positions.get(id as usize)),
+    };
+    let Some(position) = position else {
+      return Err(input.err(ParseErrorKind::InvalidObjectReference(id)));
+    };
+    self.last_id = Some(id);
+    Ok(position.kind)
+  }
+}
+
+/// Skip over the value at the cursor, like [read_object] reads it, without
+/// decoding it. Objects in the value are assigned object ids starting at
+/// `next_id`.
+pub(crate) fn skip_object(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'_>,
+  next_id: &mut u32,
+) -> Result<(), ParseError> {
+  skip_with(de, next_id, |de, skipper| visit_object(de, input, skipper))
+}
+
+/// Skip over the value at the cursor, like [read_object_internal] reads it.
+pub(crate) fn skip_object_internal(
+  de: &mut ValueDeserializer,
+  input: &mut Input<'_>,
+  next_id: &mut u32,
+) -> Result<(), ParseError> {
+  skip_with(de, next_id, |de, skipper| {
+    visit_object_internal(de, input, skipper).map(|_| ())
+  })
+}
+
+fn skip_with(
+  de: &mut ValueDeserializer,
+  next_id: &mut u32,
+  visit: impl FnOnce(&mut ValueDeserializer, &mut Skipper) -> Result<(), ParseError>,
+) -> Result<(), ParseError> {
+  let mut skipper = Skipper {
+    first_id: *next_id,
+    objects: vec![],
+    last_id: None,
+  };
+  // Nothing is decoded, so the limits on the memory use of a read do not
+  // apply to skipped values.
+  let options = de.options.clone();
+  let (allocated, heap_objects) = (de.allocated, de.heap_objects);
+  let skip_payloads = de.skip_payloads;
+  de.options.max_allocation = usize::MAX;
+  de.options.max_heap_objects = usize::MAX;
+  de.skip_payloads = true;
+  let result = visit(de, &mut skipper);
+  de.options = options;
+  (de.allocated, de.heap_objects) = (allocated, heap_objects);
+  de.skip_payloads = skip_payloads;
+  result?;
+  if let Some(positions) = &mut de.object_positions {
+    for (id, position) in (*next_id..).zip(&skipper.objects) {
+      if positions.len() == id as usize {
+        positions.push(*position);
+      }
+    }
+  }
+  *next_id += skipper.objects.len() as u32;
+  Ok(())
+}
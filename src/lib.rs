@@ -1,20 +1,66 @@
+mod blink;
+mod concat;
 mod de;
+mod diagnostics;
+mod disasm;
 mod display;
+mod lazy;
+mod node;
 mod ser;
 mod tags;
 mod value;
+mod visit;
 
+pub use crate::blink::BlinkCryptoKeyAlgorithm;
+pub use crate::blink::BlinkDeserializer;
+pub use crate::blink::BlinkEnvelope;
+pub use crate::blink::BlinkFile;
+pub use crate::blink::BlinkHostObject;
+pub use crate::blink::BlinkImage;
+pub use crate::concat::ConcatenatedValues;
+pub use crate::de::DeserializerOptions;
+pub use crate::de::HostObjectReader;
 pub use crate::de::ParseError;
 pub use crate::de::ParseErrorKind;
 pub use crate::de::ValueDeserializer;
+pub use crate::de::ValueDeserializerDelegate;
+pub use crate::diagnostics::ErrorContext;
+pub use crate::diagnostics::PathSegment;
+pub use crate::disasm::disassemble;
 pub use crate::display::display;
 pub use crate::display::DisplayFormat;
 pub use crate::display::DisplayOptions;
+pub use crate::lazy::LazyReader;
+pub use crate::lazy::LazyValue;
+pub use crate::node::NodeIpcError;
+pub use crate::node::NodeIpcReader;
+pub use crate::node::NodeIpcWriter;
+pub use crate::ser::HostObjectWriter;
+pub use crate::ser::SerializationError;
 pub use crate::ser::ValueSerializer;
+pub use crate::ser::ValueSerializerDelegate;
 pub use crate::value::value_eq;
 pub use crate::value::ArrayBuffer;
+pub use crate::value::ArrayBufferError;
 pub use crate::value::ArrayBufferView;
 pub use crate::value::ArrayBufferViewKind;
+pub use crate::value::BorrowedArrayBuffer;
+pub use crate::value::BorrowedDenseArray;
+pub use crate::value::BorrowedError;
+pub use crate::value::BorrowedHeap;
+pub use crate::value::BorrowedHeapBuilder;
+pub use crate::value::BorrowedHeapValue;
+pub use crate::value::BorrowedMap;
+pub use crate::value::BorrowedObject;
+pub use crate::value::BorrowedOneByteString;
+pub use crate::value::BorrowedPropertyKey;
+pub use crate::value::BorrowedRegExp;
+pub use crate::value::BorrowedSet;
+pub use crate::value::BorrowedSparseArray;
+pub use crate::value::BorrowedStringValue;
+pub use crate::value::BorrowedTwoByteString;
+pub use crate::value::BorrowedValue;
+pub use crate::value::BorrowedWtf8String;
 pub use crate::value::Date;
 pub use crate::value::DenseArray;
 pub use crate::value::Error;
@@ -23,6 +69,8 @@ pub use crate::value::Heap;
 pub use crate::value::HeapBuilder;
 pub use crate::value::HeapReference;
 pub use crate::value::HeapValue;
+pub use crate::value::HostObject;
+pub use crate::value::HostObjectPayload;
 pub use crate::value::Map;
 pub use crate::value::Object;
 pub use crate::value::OneByteString;
@@ -30,8 +78,12 @@ pub use crate::value::PropertyKey;
 pub use crate::value::RegExp;
 pub use crate::value::RegExpFlags;
 pub use crate::value::Set;
+pub use crate::value::SharedArrayBuffer;
 pub use crate::value::SparseArray;
 pub use crate::value::StringValue;
 pub use crate::value::TwoByteString;
 pub use crate::value::Value;
+pub use crate::value::WasmMemory;
+pub use crate::value::WasmModule;
 pub use crate::value::Wtf8String;
+pub use crate::visit::ValueVisitor;
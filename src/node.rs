@@ -0,0 +1,200 @@
+use std::io::Read;
+use std::io::Write;
+
+use thiserror::Error;
+
+use crate::value::ArrayBufferViewKind;
+use crate::BorrowedHeap;
+use crate::BorrowedValue;
+use crate::DeserializerOptions;
+use crate::Heap;
+use crate::ParseError;
+use crate::SerializationError;
+use crate::Value;
+use crate::ValueDeserializer;
+use crate::ValueSerializer;
+
+/// The size of the big endian length prefix of a message.
+const LENGTH_PREFIX_SIZE: usize = 4;
+
+/// The first field of a host object written by Node's `ChildProcessSerializer`
+/// for array buffer views, which are followed by their `DefaultSerializer`
+/// encoding. Other host objects are followed by a nested value instead.
+pub(crate) const HOST_OBJECT_ARRAY_BUFFER_VIEW: u32 = 0;
+
+/// The type index of `Buffer` in Node's `DefaultSerializer` encoding.
+const BUFFER_TYPE_INDEX: u32 = 10;
+
+/// How Node.js writes array buffer views as host objects.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum NodeFormat {
+  /// `v8.serialize()`, which uses `DefaultSerializer`.
+  Default,
+  /// The child process IPC channel, which prefixes every host object with
+  /// whether it is an array buffer view.
+  ChildProcess,
+}
+
+#[derive(Debug, Error)]
+pub enum NodeIpcError {
+  #[error("failed to read or write a message: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("failed to read a message: {0}")]
+  Parse(#[from] ParseError),
+  #[error("failed to serialize a message: {0}")]
+  Serialization(#[from] SerializationError),
+  #[error("a message of {0} bytes is too large to send")]
+  MessageTooLarge(usize),
+}
+
+/// Reads the messages of a Node.js child process IPC channel that uses
+/// `serialization: 'advanced'`. Each message is a value in the V8 wire format,
+/// prefixed with its length as a big endian u32.
+///
+/// Node writes Buffers and typed arrays as host objects. They are read as
+/// [crate::HeapValue::ArrayBufferView]s of their own buffer, and
+/// Buffers become `Uint8Array`s. Other host objects, such as those of native
+/// addons, are written as an object with a copy of their own properties, and
+/// are read as that object.
+pub struct NodeIpcReader<R> {
+  reader: R,
+  options: DeserializerOptions,
+}
+
+impl<R: Read> NodeIpcReader<R> {
+  pub fn new(reader: R) -> Self {
+    Self::with_options(reader, DeserializerOptions::default())
+  }
+
+  /// Create a reader that reads every message with the given options.
+  pub fn with_options(reader: R, options: DeserializerOptions) -> Self {
+    Self { reader, options }
+  }
+
+  /// Read the next message. Returns `None` if the input ends before the next
+  /// message.
+  pub fn read_message(
+    &mut self,
+  ) -> Result<Option<(Value, Heap)>, NodeIpcError> {
+    let mut prefix = [0; LENGTH_PREFIX_SIZE];
+    let mut filled = 0;
+    while filled < prefix.len() {
+      match self.reader.read(&mut prefix[filled..]) {
+        Ok(0) if filled == 0 => return Ok(None),
+        Ok(0) => {
+          return Err(NodeIpcError::Io(
+            std::io::ErrorKind::UnexpectedEof.into(),
+          ))
+        }
+        Ok(n) => filled += n,
+        Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+        Err(err) => return Err(err.into()),
+      }
+    }
+    let length = u32::from_be_bytes(prefix) as u64;
+    // The message is not allocated upfront, so a bogus length only costs as
+    // much memory as there is input.
+    let mut message = vec![];
+    (&mut self.reader).take(length).read_to_end(&mut message)?;
+    if message.len() as u64 != length {
+      return Err(NodeIpcError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+    }
+
+    let mut de = ValueDeserializer::with_options(self.options.clone());
+    de.node_format = Some(NodeFormat::ChildProcess);
+    Ok(Some(de.read(&message)?))
+  }
+
+  pub fn into_inner(self) -> R {
+    self.reader
+  }
+}
+
+/// Writes messages to a Node.js child process IPC channel that uses
+/// `serialization: 'advanced'`, see [NodeIpcReader].
+///
+/// Array buffer views are written as host objects the way Node writes them,
+/// with a copy of the bytes that they cover. Buffers can not be told apart
+/// from `Uint8Array`s, so they arrive in Node as `Uint8Array`s. Other host
+/// objects can not be written.
+pub struct NodeIpcWriter<W> {
+  writer: W,
+}
+
+impl<W: Write> NodeIpcWriter<W> {
+  pub fn new(writer: W) -> Self {
+    Self { writer }
+  }
+
+  /// Write a message. Messages are written with a single call to
+  /// [Write::write_all], so a message is not interleaved with other writes to
+  /// the same channel.
+  pub fn write_message(
+    &mut self,
+    heap: &BorrowedHeap,
+    value: &BorrowedValue,
+  ) -> Result<(), NodeIpcError> {
+    let mut ser = ValueSerializer::default();
+    ser.set_node_compat(true);
+    ser.node_format = Some(NodeFormat::ChildProcess);
+    let data = ser.finish(heap, value)?;
+    let length = u32::try_from(data.len())
+      .map_err(|_| NodeIpcError::MessageTooLarge(data.len()))?;
+    let mut message = Vec::with_capacity(LENGTH_PREFIX_SIZE + data.len());
+    message.extend_from_slice(&length.to_be_bytes());
+    message.extend_from_slice(&data);
+    self.writer.write_all(&message)?;
+    Ok(())
+  }
+
+  pub fn flush(&mut self) -> Result<(), NodeIpcError> {
+    self.writer.flush()?;
+    Ok(())
+  }
+
+  pub fn into_inner(self) -> W {
+    self.writer
+  }
+}
+
+/// The kind of view for a type index of Node's `DefaultSerializer`.
+pub(crate) fn array_buffer_view_kind(
+  type_index: u32,
+) -> Option<ArrayBufferViewKind> {
+  let kind = match type_index {
+    0 => ArrayBufferViewKind::Int8Array,
+    1 | BUFFER_TYPE_INDEX => ArrayBufferViewKind::Uint8Array,
+    2 => ArrayBufferViewKind::Uint8ClampedArray,
+    3 => ArrayBufferViewKind::Int16Array,
+    4 => ArrayBufferViewKind::Uint16Array,
+    5 => ArrayBufferViewKind::Int32Array,
+    6 => ArrayBufferViewKind::Uint32Array,
+    7 => ArrayBufferViewKind::Float32Array,
+    8 => ArrayBufferViewKind::Float64Array,
+    9 => ArrayBufferViewKind::DataView,
+    11 => ArrayBufferViewKind::BigInt64Array,
+    12 => ArrayBufferViewKind::BigUint64Array,
+    13 => ArrayBufferViewKind::Float16Array,
+    _ => return None,
+  };
+  Some(kind)
+}
+
+/// The type index of Node's `DefaultSerializer` for a kind of view.
+pub(crate) fn array_buffer_view_type_index(kind: ArrayBufferViewKind) -> u32 {
+  match kind {
+    ArrayBufferViewKind::Int8Array => 0,
+    ArrayBufferViewKind::Uint8Array => 1,
+    ArrayBufferViewKind::Uint8ClampedArray => 2,
+    ArrayBufferViewKind::Int16Array => 3,
+    ArrayBufferViewKind::Uint16Array => 4,
+    ArrayBufferViewKind::Int32Array => 5,
+    ArrayBufferViewKind::Uint32Array => 6,
+    ArrayBufferViewKind::Float32Array => 7,
+    ArrayBufferViewKind::Float64Array => 8,
+    ArrayBufferViewKind::DataView => 9,
+    ArrayBufferViewKind::BigInt64Array => 11,
+    ArrayBufferViewKind::BigUint64Array => 12,
+    ArrayBufferViewKind::Float16Array => 13,
+  }
+}
@@ -3,32 +3,37 @@ use std::collections::HashMap;
 use num_bigint::BigInt;
 use thiserror::Error;
 
+use crate::node::array_buffer_view_type_index;
+use crate::node::NodeFormat;
+use crate::node::HOST_OBJECT_ARRAY_BUFFER_VIEW;
 use crate::tags::ArrayBufferViewTag;
 use crate::tags::ErrorTag;
 use crate::tags::SerializationTag;
-use crate::value::ArrayBuffer;
 use crate::value::ArrayBufferView;
 use crate::value::ArrayBufferViewKind;
+use crate::value::BorrowedArrayBuffer;
+use crate::value::BorrowedDenseArray;
+use crate::value::BorrowedError;
+use crate::value::BorrowedMap;
+use crate::value::BorrowedObject;
+use crate::value::BorrowedPropertyKey;
+use crate::value::BorrowedRegExp;
+use crate::value::BorrowedSet;
+use crate::value::BorrowedSparseArray;
 use crate::value::Date;
-use crate::value::DenseArray;
-use crate::value::Error;
 use crate::value::ErrorName;
-use crate::value::Map;
-use crate::value::Object;
-use crate::value::PropertyKey;
-use crate::value::RegExp;
-use crate::value::Set;
-use crate::value::SparseArray;
-use crate::Heap;
+use crate::value::HostObject;
+use crate::value::SharedArrayBuffer;
+use crate::value::WasmMemory;
+use crate::value::WasmModule;
+use crate::BorrowedHeap;
+use crate::BorrowedHeapValue;
+use crate::BorrowedStringValue;
+use crate::BorrowedValue;
 use crate::HeapReference;
-use crate::HeapValue;
-use crate::StringValue;
-use crate::Value;
 
 #[derive(Debug, Error)]
 pub enum SerializationError {
-  #[error("recursion depth limit exceeded")]
-  RecursionDepthLimitExceeded,
   #[error("a dangling heap reference was encountered")]
   DanglingHeapReference,
   #[error("a string was too long to serialize")]
@@ -43,55 +48,287 @@ pub enum SerializationError {
   MapTooLarge,
   #[error("a set has too many entries to serialize")]
   SetTooLarge,
+  #[error("host objects can not be serialized without a delegate")]
+  HostObjectNotSupported,
+  #[error("invalid host object: {0}")]
+  InvalidHostObject(String),
+  #[error("shared array buffers can not be serialized without a delegate")]
+  SharedArrayBufferNotSupported,
+  #[error("a transferred value is not an ArrayBuffer")]
+  TransferNotArrayBuffer,
+  #[error("an array buffer view is out of bounds of its buffer")]
+  ArrayBufferViewOutOfBounds,
+  #[error("a placeholder for an unreadable value can not be serialized")]
+  Placeholder,
+  #[error("wire format version {0} can not be written")]
+  UnsupportedVersion(u32),
+  #[error("{value} can not be represented in wire format version {version}")]
+  UnsupportedInVersion { value: &'static str, version: u32 },
+}
+
+/// A delegate that encodes embedder specific data in the wire format. This
+/// mirrors `v8::ValueSerializer::Delegate`.
+pub trait ValueSerializerDelegate {
+  /// Write the payload of a host object. The host object tag has already been
+  /// written when this is called.
+  fn write_host_object(
+    &mut self,
+    writer: &mut HostObjectWriter<'_>,
+    host_object: &HostObject,
+  ) -> Result<(), SerializationError> {
+    let _ = (writer, host_object);
+    Err(SerializationError::HostObjectNotSupported)
+  }
+
+  /// Write the payload of an array buffer view as a host object, when views
+  /// are written as host objects (see
+  /// [ValueSerializer::set_treat_array_buffer_views_as_host_objects]). The
+  /// host object tag has already been written, and `contents` are the bytes
+  /// that the view covers.
+  fn write_array_buffer_view(
+    &mut self,
+    writer: &mut HostObjectWriter<'_>,
+    view: &ArrayBufferView,
+    contents: &[u8],
+  ) -> Result<(), SerializationError> {
+    let _ = (writer, view, contents);
+    Err(SerializationError::HostObjectNotSupported)
+  }
+
+  /// Get the id that the given SharedArrayBuffer is written as. This is called
+  /// once per SharedArrayBuffer in the serialized value. The receiving side
+  /// must register the buffer under the same id, for example with
+  /// [crate::ValueDeserializer::transfer_shared_array_buffer].
+  fn get_shared_array_buffer_id(
+    &mut self,
+    sab: &SharedArrayBuffer,
+  ) -> Result<u32, SerializationError> {
+    let _ = sab;
+    Err(SerializationError::SharedArrayBufferNotSupported)
+  }
+}
+
+/// A handle to the serializer output, handed to a [ValueSerializerDelegate] to
+/// write the payload of a host object.
+pub struct HostObjectWriter<'s> {
+  ser: &'s mut ValueSerializer,
+}
+
+impl<'s> HostObjectWriter<'s> {
+  /// Write a u32 as a varint.
+  pub fn write_uint32(&mut self, value: u32) {
+    self.ser.write_varint(value);
+  }
+
+  /// Write a u64 as a varint.
+  pub fn write_uint64(&mut self, value: u64) {
+    self.ser.write_varint_u64(value);
+  }
+
+  /// Write a f64 in little endian byte order.
+  pub fn write_double(&mut self, value: f64) {
+    self.ser.write_double(value);
+  }
+
+  /// Write raw bytes, without a length prefix.
+  pub fn write_raw_bytes(&mut self, bytes: &[u8]) {
+    self.ser.data.extend_from_slice(bytes);
+  }
 }
 
 #[derive(Default)]
 pub struct ValueSerializer {
   data: Vec<u8>,
   id_map: HashMap<HeapReference, u32>,
-  recursion_depth: usize,
+  transfer_map: HashMap<HeapReference, u32>,
+  delegate: Option<Box<dyn ValueSerializerDelegate>>,
+  version: Option<u32>,
+  treat_array_buffer_views_as_host_objects: bool,
+  /// How Node.js writes array buffer views as host objects, if they are
+  /// written natively instead of through the delegate.
+  pub(crate) node_format: Option<NodeFormat>,
 }
 
-const RECURSION_DEPTH_LIMIT: usize = 256;
 const WIRE_FORMAT_VERSION: u32 = 15;
+const MINIMUM_WIRE_FORMAT_VERSION: u32 = 13;
+
+/// A step of writing a value. Nested values are written by pushing steps onto
+/// a work stack instead of recursing, so that the nesting depth of a value is
+/// not limited by the native stack.
+enum Step<'v> {
+  Value(&'v BorrowedValue<'v>),
+  HeapReference(HeapReference),
+  /// A heap value whose dependencies have been written.
+  HeapValue(HeapReference, &'v BorrowedHeapValue<'v>),
+  Property(&'v BorrowedPropertyKey<'v>, &'v BorrowedValue<'v>),
+  Hole,
+  /// An end tag followed by a count.
+  End(SerializationTag, u32),
+  Varint(u32),
+  /// The fields of an error that follow its cause.
+  ErrorEnd(&'v BorrowedError<'v>),
+}
 
 impl ValueSerializer {
+  /// Set the delegate that is used to write host objects. Without a delegate,
+  /// host objects fail with [SerializationError::HostObjectNotSupported].
+  pub fn set_delegate(&mut self, delegate: Box<dyn ValueSerializerDelegate>) {
+    self.delegate = Some(delegate);
+  }
+
+  /// Set the wire format version to write, for consumers that use an older
+  /// V8. Values that the version can not represent are downleveled where
+  /// possible: resizable array buffers are written as fixed length buffers,
+  /// and array buffer views lose their length tracking. Otherwise
+  /// serialization fails with [SerializationError::UnsupportedInVersion].
+  ///
+  /// Versions 13 to 15 are supported. The default is 15.
+  pub fn set_version(
+    &mut self,
+    version: u32,
+  ) -> Result<(), SerializationError> {
+    if !(MINIMUM_WIRE_FORMAT_VERSION..=WIRE_FORMAT_VERSION).contains(&version) {
+      return Err(SerializationError::UnsupportedVersion(version));
+    }
+    self.version = Some(version);
+    Ok(())
+  }
+
+  /// Write array buffer views as host objects, through
+  /// [ValueSerializerDelegate::write_array_buffer_view], instead of as views
+  /// of their buffer. Their buffer is then only written if it is referenced
+  /// elsewhere. This mirrors
+  /// `v8::ValueSerializer::SetTreatArrayBufferViewsAsHostObjects`.
+  pub fn set_treat_array_buffer_views_as_host_objects(&mut self, mode: bool) {
+    self.treat_array_buffer_views_as_host_objects = mode;
+  }
+
+  /// Write array buffer views as host objects the way Node.js does in
+  /// `v8.serialize()`, so that Node reads them as typed arrays. Only the bytes
+  /// that a view covers are written, and `Uint8Array`s are written as such
+  /// rather than as Buffers. Float16Arrays can only be read by Node releases
+  /// that have them. Other host objects are still written through the
+  /// delegate.
+  pub fn set_node_compat(&mut self, node_compat: bool) {
+    self.node_format = node_compat.then_some(NodeFormat::Default);
+    self.treat_array_buffer_views_as_host_objects = node_compat;
+  }
+
+  fn version(&self) -> u32 {
+    self.version.unwrap_or(WIRE_FORMAT_VERSION)
+  }
+
+  /// Transfer the ArrayBuffer at `reference` instead of copying its contents
+  /// into the output. It is written as `id`, and the receiving side must
+  /// provide the buffer under the same id, for example with
+  /// [crate::ValueDeserializer::transfer_array_buffer].
+  pub fn transfer_array_buffer(&mut self, id: u32, reference: HeapReference) {
+    self.transfer_map.insert(reference, id);
+  }
+
   pub fn finish(
     mut self,
-    heap: &Heap,
-    value: &Value,
+    heap: &BorrowedHeap,
+    value: &BorrowedValue,
   ) -> Result<Vec<u8>, SerializationError> {
+    for reference in self.transfer_map.keys() {
+      let Some(BorrowedHeapValue::ArrayBuffer(_)) = reference.try_open(heap)
+      else {
+        return Err(SerializationError::TransferNotArrayBuffer);
+      };
+    }
     self.write_header();
     self.write_value(heap, value)?;
     Ok(self.data)
   }
 
+  /// Like [ValueSerializer::finish], but additionally detaches the transferred
+  /// ArrayBuffers the way V8 does: they are moved out of the heap, which is
+  /// left with empty buffers in their place, and returned keyed by their
+  /// transfer id. The empty buffers stay resizable up to the same maximum
+  /// length, and views of them become empty as well.
+  pub fn finish_transferring<'a>(
+    self,
+    heap: &mut BorrowedHeap<'a>,
+    value: &BorrowedValue,
+  ) -> Result<
+    (Vec<u8>, HashMap<u32, BorrowedArrayBuffer<'a>>),
+    SerializationError,
+  > {
+    let transfer_map = self.transfer_map.clone();
+    let data = self.finish(heap, value)?;
+    let mut transferred = HashMap::with_capacity(transfer_map.len());
+    for (reference, id) in &transfer_map {
+      let BorrowedHeapValue::ArrayBuffer(ab) = reference.open_mut(heap) else {
+        unreachable!();
+      };
+      let detached = match ab.max_byte_length {
+        Some(max_byte_length) => {
+          BorrowedArrayBuffer::new_resizable(0, max_byte_length).unwrap()
+        }
+        None => BorrowedArrayBuffer::new(0),
+      };
+      transferred.insert(*id, std::mem::replace(ab, detached));
+    }
+    for heap_value in heap.values_mut() {
+      if let BorrowedHeapValue::ArrayBufferView(view) = heap_value {
+        if transfer_map.contains_key(&view.buffer) {
+          view.byte_offset = 0;
+          view.length = 0;
+        }
+      }
+    }
+    Ok((data, transferred))
+  }
+
   fn write_header(&mut self) {
     self.write_tag(SerializationTag::Version);
-    self.write_varint(WIRE_FORMAT_VERSION);
+    self.write_varint(self.version());
   }
 
-  fn write_value(
+  fn write_value<'v>(
     &mut self,
-    heap: &Heap,
-    value: &Value,
+    heap: &'v BorrowedHeap,
+    value: &'v BorrowedValue,
   ) -> Result<(), SerializationError> {
-    match value {
-      Value::Undefined => self.write_tag(SerializationTag::Undefined),
-      Value::Null => self.write_tag(SerializationTag::Null),
-      Value::Bool(true) => self.write_tag(SerializationTag::True),
-      Value::Bool(false) => self.write_tag(SerializationTag::False),
-      Value::I32(smi) => self.write_smi(*smi),
-      Value::U32(int) => self.write_u32(int),
-      Value::Double(double) => self.write_number(*double),
-      Value::BigInt(bigint) => self.write_bigint(bigint)?,
-      Value::String(str) => self.write_string(str)?,
-      Value::HeapReference(reference) => {
-        self.recursion_depth += 1;
-        self.write_heap_reference(heap, *reference)?;
-        self.recursion_depth -= 1;
+    let mut steps = vec![Step::Value(value)];
+    while let Some(step) = steps.pop() {
+      match step {
+        Step::Value(value) => match value {
+          BorrowedValue::Undefined => {
+            self.write_tag(SerializationTag::Undefined)
+          }
+          BorrowedValue::Null => self.write_tag(SerializationTag::Null),
+          BorrowedValue::Bool(true) => self.write_tag(SerializationTag::True),
+          BorrowedValue::Bool(false) => self.write_tag(SerializationTag::False),
+          BorrowedValue::I32(smi) => self.write_smi(*smi),
+          BorrowedValue::U32(int) => self.write_u32(int),
+          BorrowedValue::Double(double) => self.write_number(*double),
+          BorrowedValue::BigInt(bigint) => self.write_bigint(bigint)?,
+          BorrowedValue::String(str) => self.write_string(str)?,
+          BorrowedValue::HeapReference(reference) => {
+            self.write_heap_reference(heap, *reference, &mut steps)?
+          }
+        },
+        Step::HeapReference(reference) => {
+          self.write_heap_reference(heap, reference, &mut steps)?
+        }
+        Step::HeapValue(reference, value) => {
+          self.write_heap_value_inner(heap, reference, value, &mut steps)?
+        }
+        Step::Property(key, value) => {
+          self.write_property_key(key)?;
+          steps.push(Step::Value(value));
+        }
+        Step::Hole => self.write_tag(SerializationTag::TheHole),
+        Step::End(tag, count) => {
+          self.write_tag(tag);
+          self.write_varint(count);
+        }
+        Step::Varint(value) => self.write_varint(value),
+        Step::ErrorEnd(err) => self.write_error_end(err)?,
       }
-    };
+    }
     Ok(())
   }
 
@@ -112,6 +349,15 @@ impl ValueSerializer {
     self.data.push(value as u8);
   }
 
+  fn write_varint_u64(&mut self, value: u64) {
+    let mut value = value;
+    while value >= 0x80 {
+      self.data.push(((value & 0x7f) | 0x80) as u8);
+      value >>= 7;
+    }
+    self.data.push(value as u8);
+  }
+
   fn write_varint_u8(&mut self, value: u8) {
     self.write_varint(value as u32);
   }
@@ -174,10 +420,10 @@ impl ValueSerializer {
 
   fn write_string(
     &mut self,
-    str: &StringValue,
+    str: &BorrowedStringValue,
   ) -> Result<(), SerializationError> {
     match str {
-      StringValue::Wtf8(wtf8) => {
+      BorrowedStringValue::Wtf8(wtf8) => {
         self.write_tag(SerializationTag::Utf8String);
         let bytes = wtf8.as_bytes();
         let length: u32 = bytes
@@ -187,7 +433,7 @@ impl ValueSerializer {
         self.write_varint(length);
         self.data.extend_from_slice(bytes);
       }
-      StringValue::OneByte(str) => {
+      BorrowedStringValue::OneByte(str) => {
         self.write_tag(SerializationTag::OneByteString);
         let bytes = str.as_bytes();
         let length: u32 = str
@@ -198,7 +444,7 @@ impl ValueSerializer {
         self.write_varint(length);
         self.data.extend_from_slice(bytes);
       }
-      StringValue::TwoByte(str) => {
+      BorrowedStringValue::TwoByte(str) => {
         let bytes = str.as_u8_bytes();
         let length: u32 = bytes
           .len()
@@ -215,32 +461,35 @@ impl ValueSerializer {
     Ok(())
   }
 
-  fn write_heap_reference(
+  fn write_heap_reference<'v>(
     &mut self,
-    heap: &Heap,
+    heap: &'v BorrowedHeap,
     reference: HeapReference,
+    steps: &mut Vec<Step<'v>>,
   ) -> Result<(), SerializationError> {
     let Some(value) = reference.try_open(heap) else {
       return Err(SerializationError::DanglingHeapReference);
     };
     match value {
-      HeapValue::ArrayBufferView(abv)
-        if !self.id_map.contains_key(&reference) =>
+      BorrowedHeapValue::ArrayBufferView(abv)
+        if !self.id_map.contains_key(&reference)
+          && !self.treat_array_buffer_views_as_host_objects =>
       {
-        self.recursion_depth += 1;
-        self.write_heap_reference(heap, abv.buffer)?;
-        self.recursion_depth -= 1;
-        self.write_heap_value_inner(heap, reference, value)
+        // The buffer is written first, and the view follows it.
+        steps.push(Step::HeapValue(reference, value));
+        steps.push(Step::HeapReference(abv.buffer));
+        Ok(())
       }
-      _ => self.write_heap_value_inner(heap, reference, value),
+      _ => self.write_heap_value_inner(heap, reference, value, steps),
     }
   }
 
-  fn write_heap_value_inner(
+  fn write_heap_value_inner<'v>(
     &mut self,
-    heap: &Heap,
+    heap: &'v BorrowedHeap,
     reference: HeapReference,
-    value: &HeapValue,
+    value: &'v BorrowedHeapValue,
+    steps: &mut Vec<Step<'v>>,
   ) -> Result<(), SerializationError> {
     let next_id: u32 = self.id_map.len() as u32;
     match self.id_map.entry(reference) {
@@ -255,48 +504,86 @@ impl ValueSerializer {
       }
     };
 
-    if self.recursion_depth > RECURSION_DEPTH_LIMIT {
-      return Err(SerializationError::RecursionDepthLimitExceeded);
-    }
-
     match value {
-      HeapValue::BooleanObject(true) => {
+      BorrowedHeapValue::BooleanObject(true) => {
         self.write_tag(SerializationTag::TrueObject);
       }
-      HeapValue::BooleanObject(false) => {
+      BorrowedHeapValue::BooleanObject(false) => {
         self.write_tag(SerializationTag::FalseObject);
       }
-      HeapValue::NumberObject(double) => {
+      BorrowedHeapValue::NumberObject(double) => {
         self.write_tag(SerializationTag::NumberObject);
         self.write_double(*double);
       }
-      HeapValue::BigIntObject(bigint) => {
+      BorrowedHeapValue::BigIntObject(bigint) => {
         self.write_tag(SerializationTag::BigIntObject);
         self.write_bigint_contents(bigint)?;
       }
-      HeapValue::StringObject(str) => {
+      BorrowedHeapValue::StringObject(str) => {
         self.write_tag(SerializationTag::StringObject);
         self.write_string(str)?;
       }
-      HeapValue::RegExp(regexp) => self.write_regexp(regexp)?,
-      HeapValue::Date(date) => {
+      BorrowedHeapValue::RegExp(regexp) => self.write_regexp(regexp)?,
+      BorrowedHeapValue::Date(date) => {
         self.write_date(date);
       }
-      HeapValue::Object(obj) => self.write_object(heap, obj)?,
-      HeapValue::SparseArray(arr) => self.write_sparse_array(heap, arr)?,
-      HeapValue::DenseArray(arr) => self.write_dense_array(heap, arr)?,
-      HeapValue::Map(map) => self.write_map(heap, map)?,
-      HeapValue::Set(set) => self.write_set(heap, set)?,
-      HeapValue::ArrayBuffer(ab) => self.write_array_buffer(ab),
-      HeapValue::ArrayBufferView(abv) => self.write_array_buffer_view(abv),
-      HeapValue::Error(err) => self.write_error(heap, err)?,
+      BorrowedHeapValue::Object(obj) => self.write_object(obj, steps)?,
+      BorrowedHeapValue::SparseArray(arr) => {
+        self.write_sparse_array(arr, steps)?
+      }
+      BorrowedHeapValue::DenseArray(arr) => {
+        self.write_dense_array(arr, steps)?
+      }
+      BorrowedHeapValue::Map(map) => self.write_map(map, steps)?,
+      BorrowedHeapValue::Set(set) => self.write_set(set, steps)?,
+      BorrowedHeapValue::ArrayBuffer(ab) => {
+        match self.transfer_map.get(&reference) {
+          Some(&id) => {
+            self.write_tag(SerializationTag::ArrayBufferTransfer);
+            self.write_varint(id);
+          }
+          None => self.write_array_buffer(ab),
+        }
+      }
+      BorrowedHeapValue::ArrayBufferView(abv)
+        if self.treat_array_buffer_views_as_host_objects =>
+      {
+        self.write_array_buffer_view_host_object(heap, abv)?
+      }
+      BorrowedHeapValue::ArrayBufferView(abv) => {
+        self.write_array_buffer_view(abv)?
+      }
+      BorrowedHeapValue::Error(err) => self.write_error(err, steps)?,
+      BorrowedHeapValue::HostObject(host_object) => {
+        self.write_host_object(host_object)?
+      }
+      BorrowedHeapValue::SharedArrayBuffer(sab) => {
+        self.write_shared_array_buffer(sab)?
+      }
+      BorrowedHeapValue::WasmMemory(memory) => {
+        self.write_wasm_memory(memory, steps)
+      }
+      BorrowedHeapValue::WasmModule(module) => self.write_wasm_module(module),
+      BorrowedHeapValue::SharedObject(id) => {
+        if self.version() < 15 {
+          return Err(SerializationError::UnsupportedInVersion {
+            value: "shared objects",
+            version: self.version(),
+          });
+        }
+        self.write_tag(SerializationTag::SharedObject);
+        self.write_varint(*id);
+      }
+      BorrowedHeapValue::Placeholder => {
+        return Err(SerializationError::Placeholder)
+      }
     };
     Ok(())
   }
 
   fn write_regexp(
     &mut self,
-    regexp: &RegExp,
+    regexp: &BorrowedRegExp,
   ) -> Result<(), SerializationError> {
     self.write_tag(SerializationTag::RegExp);
     self.write_string(&regexp.pattern)?;
@@ -309,64 +596,70 @@ impl ValueSerializer {
     self.write_double(date.time_since_epoch);
   }
 
-  fn write_object(
+  fn write_object<'v>(
     &mut self,
-    heap: &Heap,
-    obj: &Object,
+    obj: &'v BorrowedObject,
+    steps: &mut Vec<Step<'v>>,
   ) -> Result<(), SerializationError> {
     self.write_tag(SerializationTag::BeginJsObject);
     self.write_object_properties(
-      heap,
       &obj.properties,
       SerializationTag::EndJsObject,
+      steps,
     )?;
     Ok(())
   }
 
-  fn write_object_properties(
+  fn write_object_properties<'v>(
     &mut self,
-    heap: &Heap,
-    properties: &[(PropertyKey, Value)],
+    properties: &'v [(BorrowedPropertyKey, BorrowedValue)],
     end_tag: SerializationTag,
+    steps: &mut Vec<Step<'v>>,
   ) -> Result<(), SerializationError> {
     let property_count: u32 = properties
       .len()
       .try_into()
       .map_err(|_| SerializationError::TooManyObjectProperties)?;
-    for (key, value) in properties {
-      match key {
-        PropertyKey::I32(smi) => self.write_smi(*smi),
-        PropertyKey::U32(num) => self.write_u32(num),
-        PropertyKey::Double(double) => self.write_number(*double),
-        PropertyKey::String(str) => self.write_string(str)?,
-      }
-      self.write_value(heap, value)?;
+    steps.push(Step::End(end_tag, property_count));
+    for (key, value) in properties.iter().rev() {
+      steps.push(Step::Property(key, value));
     }
-    self.write_tag(end_tag);
-    self.write_varint(property_count);
     Ok(())
   }
 
-  fn write_sparse_array(
+  fn write_property_key(
     &mut self,
-    heap: &Heap,
-    arr: &SparseArray,
+    key: &BorrowedPropertyKey,
+  ) -> Result<(), SerializationError> {
+    match key {
+      BorrowedPropertyKey::I32(smi) => self.write_smi(*smi),
+      BorrowedPropertyKey::U32(num) => self.write_u32(num),
+      BorrowedPropertyKey::Double(double) => self.write_number(*double),
+      BorrowedPropertyKey::String(str) => self.write_string(str)?,
+    }
+    Ok(())
+  }
+
+  fn write_sparse_array<'v>(
+    &mut self,
+    arr: &'v BorrowedSparseArray,
+    steps: &mut Vec<Step<'v>>,
   ) -> Result<(), SerializationError> {
     self.write_tag(SerializationTag::BeginSparseJsArray);
     self.write_varint(arr.length);
+    steps.push(Step::Varint(arr.length));
     self.write_object_properties(
-      heap,
       &arr.properties,
       SerializationTag::EndSparseJsArray,
+      steps,
     )?;
-    self.write_varint(arr.length);
     Ok(())
   }
 
-  fn write_dense_array(
+  fn write_dense_array<'v>(
     &mut self,
-    heap: &Heap,
-    arr: &DenseArray,
+    arr: &'v BorrowedDenseArray,
+    steps: &mut Vec<Step<'v>>,
   ) -> Result<(), SerializationError> {
     self.write_tag(SerializationTag::BeginDenseJsArray);
     let length: u32 = arr
@@ -375,26 +668,25 @@ impl ValueSerializer {
       .try_into()
       .map_err(|_| SerializationError::ArrayTooLong)?;
     self.write_varint(length);
-    for value in &arr.elements {
-      if let Some(value) = value {
-        self.write_value(heap, value)?;
-      } else {
-        self.write_tag(SerializationTag::TheHole);
-      }
-    }
+    steps.push(Step::Varint(length));
     self.write_object_properties(
-      heap,
       &arr.properties,
       SerializationTag::EndDenseJsArray,
+      steps,
     )?;
-    self.write_varint(length);
+    for value in arr.elements.iter().rev() {
+      steps.push(match value {
+        Some(value) => Step::Value(value),
+        None => Step::Hole,
+      });
+    }
     Ok(())
   }
 
-  fn write_map(
+  fn write_map<'v>(
     &mut self,
-    heap: &Heap,
-    map: &Map,
+    map: &'v BorrowedMap,
+    steps: &mut Vec<Step<'v>>,
   ) -> Result<(), SerializationError> {
     let size: u32 = map
       .entries
@@ -403,19 +695,18 @@ impl ValueSerializer {
       .map_err(|_| SerializationError::MapTooLarge)?;
     let length = size.checked_mul(2).ok_or(SerializationError::MapTooLarge)?;
     self.write_tag(SerializationTag::BeginJsMap);
-    for (key, value) in &map.entries {
-      self.write_value(heap, key)?;
-      self.write_value(heap, value)?;
+    steps.push(Step::End(SerializationTag::EndJsMap, length));
+    for (key, value) in map.entries.iter().rev() {
+      steps.push(Step::Value(value));
+      steps.push(Step::Value(key));
     }
-    self.write_tag(SerializationTag::EndJsMap);
-    self.write_varint(length);
     Ok(())
   }
 
-  fn write_set(
+  fn write_set<'v>(
     &mut self,
-    heap: &Heap,
-    set: &Set,
+    set: &'v BorrowedSet,
+    steps: &mut Vec<Step<'v>>,
   ) -> Result<(), SerializationError> {
     let size: u32 = set
       .values
@@ -423,16 +714,17 @@ impl ValueSerializer {
       .try_into()
       .map_err(|_| SerializationError::SetTooLarge)?;
     self.write_tag(SerializationTag::BeginJsSet);
-    for value in &set.values {
-      self.write_value(heap, value)?;
+    steps.push(Step::End(SerializationTag::EndJsSet, size));
+    for value in set.values.iter().rev() {
+      steps.push(Step::Value(value));
     }
-    self.write_tag(SerializationTag::EndJsSet);
-    self.write_varint(size);
     Ok(())
   }
 
-  fn write_array_buffer(&mut self, ab: &ArrayBuffer) {
-    if let Some(max_byte_length) = ab.max_byte_length {
+  fn write_array_buffer(&mut self, ab: &BorrowedArrayBuffer) {
+    // Resizable array buffers were added in version 14.
+    let max_byte_length = ab.max_byte_length.filter(|_| self.version() >= 14);
+    if let Some(max_byte_length) = max_byte_length {
       self.write_tag(SerializationTag::ResizableArrayBuffer);
       self.write_varint(ab.byte_length());
       self.write_varint(max_byte_length);
@@ -443,7 +735,18 @@ impl ValueSerializer {
     self.data.extend_from_slice(ab.as_u8_slice());
   }
 
-  fn write_array_buffer_view(&mut self, abv: &ArrayBufferView) {
+  fn write_array_buffer_view(
+    &mut self,
+    abv: &ArrayBufferView,
+  ) -> Result<(), SerializationError> {
+    // The Float16Array subtag was added without a version bump, while V8 was
+    // already writing version 15.
+    if abv.kind == ArrayBufferViewKind::Float16Array && self.version() < 15 {
+      return Err(SerializationError::UnsupportedInVersion {
+        value: "Float16Array",
+        version: self.version(),
+      });
+    }
     self.write_tag(SerializationTag::ArrayBufferView);
     let tag = match abv.kind {
       ArrayBufferViewKind::Int8Array => ArrayBufferViewTag::Int8Array,
@@ -455,6 +758,7 @@ impl ValueSerializer {
       ArrayBufferViewKind::Uint16Array => ArrayBufferViewTag::Uint16Array,
       ArrayBufferViewKind::Int32Array => ArrayBufferViewTag::Int32Array,
       ArrayBufferViewKind::Uint32Array => ArrayBufferViewTag::Uint32Array,
+      ArrayBufferViewKind::Float16Array => ArrayBufferViewTag::Float16Array,
       ArrayBufferViewKind::Float32Array => ArrayBufferViewTag::Float32Array,
       ArrayBufferViewKind::Float64Array => ArrayBufferViewTag::Float64Array,
       ArrayBufferViewKind::BigInt64Array => ArrayBufferViewTag::BigInt64Array,
@@ -464,6 +768,10 @@ impl ValueSerializer {
     self.write_varint_u8(tag as u8);
     self.write_varint(abv.byte_offset);
     self.write_varint(abv.length * abv.kind.byte_width());
+    // Array buffer view flags were added in version 14.
+    if self.version() < 14 {
+      return Ok(());
+    }
     let mut flags = 0u32;
     if abv.is_length_tracking {
       flags |= 0b1;
@@ -472,12 +780,110 @@ impl ValueSerializer {
       flags |= 0b10;
     }
     self.write_varint(flags);
+    Ok(())
   }
 
-  fn write_error(
+  fn write_host_object(
     &mut self,
-    heap: &Heap,
-    err: &Error,
+    host_object: &HostObject,
+  ) -> Result<(), SerializationError> {
+    let Some(mut delegate) = self.delegate.take() else {
+      return Err(SerializationError::HostObjectNotSupported);
+    };
+    self.write_tag(SerializationTag::HostObject);
+    let res = delegate
+      .write_host_object(&mut HostObjectWriter { ser: self }, host_object);
+    self.delegate = Some(delegate);
+    res
+  }
+
+  fn write_array_buffer_view_host_object(
+    &mut self,
+    heap: &BorrowedHeap,
+    abv: &ArrayBufferView,
+  ) -> Result<(), SerializationError> {
+    let buffer = match abv.buffer.try_open(heap) {
+      Some(BorrowedHeapValue::ArrayBuffer(ab)) => ab,
+      Some(BorrowedHeapValue::SharedArrayBuffer(sab)) => sab.as_array_buffer(),
+      _ => return Err(SerializationError::DanglingHeapReference),
+    };
+    let bytes = buffer.as_u8_slice();
+    let start = abv.byte_offset as usize;
+    let byte_width = abv.kind.byte_width() as usize;
+    let end = if abv.is_length_tracking {
+      start + bytes.len().saturating_sub(start) / byte_width * byte_width
+    } else {
+      start + abv.length as usize * byte_width
+    };
+    let Some(contents) = bytes.get(start..end) else {
+      return Err(SerializationError::ArrayBufferViewOutOfBounds);
+    };
+    if let Some(format) = self.node_format {
+      self.write_tag(SerializationTag::HostObject);
+      self.write_node_host_object(format, abv.kind, contents);
+      return Ok(());
+    }
+    let Some(mut delegate) = self.delegate.take() else {
+      return Err(SerializationError::HostObjectNotSupported);
+    };
+    self.write_tag(SerializationTag::HostObject);
+    let res = delegate.write_array_buffer_view(
+      &mut HostObjectWriter { ser: self },
+      abv,
+      contents,
+    );
+    self.delegate = Some(delegate);
+    res
+  }
+
+  /// Write an array buffer view the way Node.js writes it as a host object.
+  fn write_node_host_object(
+    &mut self,
+    format: NodeFormat,
+    kind: ArrayBufferViewKind,
+    contents: &[u8],
+  ) {
+    if let NodeFormat::ChildProcess = format {
+      self.write_varint(HOST_OBJECT_ARRAY_BUFFER_VIEW);
+    }
+    self.write_varint(array_buffer_view_type_index(kind));
+    self.write_varint(contents.len() as u32);
+    self.data.extend_from_slice(contents);
+  }
+
+  fn write_shared_array_buffer(
+    &mut self,
+    sab: &SharedArrayBuffer,
+  ) -> Result<(), SerializationError> {
+    let Some(mut delegate) = self.delegate.take() else {
+      return Err(SerializationError::SharedArrayBufferNotSupported);
+    };
+    let res = delegate.get_shared_array_buffer_id(sab);
+    self.delegate = Some(delegate);
+    self.write_tag(SerializationTag::SharedArrayBuffer);
+    self.write_varint(res?);
+    Ok(())
+  }
+
+  fn write_wasm_memory<'v>(
+    &mut self,
+    memory: &'v WasmMemory,
+    steps: &mut Vec<Step<'v>>,
+  ) {
+    self.write_tag(SerializationTag::WasmMemoryTransfer);
+    self.write_zigzag(memory.maximum_pages);
+    steps.push(Step::HeapReference(memory.buffer));
+  }
+
+  fn write_wasm_module(&mut self, module: &WasmModule) {
+    self.write_tag(SerializationTag::WasmModuleTransfer);
+    self.write_varint(module.transfer_id);
+  }
+
+  fn write_error<'v>(
+    &mut self,
+    err: &'v BorrowedError,
+    steps: &mut Vec<Step<'v>>,
   ) -> Result<(), SerializationError> {
     self.write_tag(SerializationTag::Error);
     let name_tag = match err.name {
@@ -498,8 +904,18 @@ impl ValueSerializer {
     }
     if let Some(cause) = &err.cause {
       self.write_varint(ErrorTag::Cause as u32);
-      self.write_value(heap, cause)?;
+      steps.push(Step::ErrorEnd(err));
+      steps.push(Step::Value(cause));
+      return Ok(());
     }
+    self.write_error_end(err)
+  }
+
+  /// Write the fields of an error that follow its cause.
+  fn write_error_end(
+    &mut self,
+    err: &BorrowedError,
+  ) -> Result<(), SerializationError> {
     if let Some(stack) = &err.stack {
       self.write_varint(ErrorTag::Stack as u32);
       self.write_string(stack)?;
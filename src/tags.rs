@@ -98,8 +98,61 @@ pub enum SerializationTag {
   Error = b'r',
 }
 
+impl SerializationTag {
+  /// The tag with the given value, if any.
+  pub(crate) fn from_u8(byte: u8) -> Option<Self> {
+    Some(match byte {
+      x if x == Self::Version as u8 => Self::Version,
+      x if x == Self::Padding as u8 => Self::Padding,
+      x if x == Self::VerifyObjectCount as u8 => Self::VerifyObjectCount,
+      x if x == Self::TheHole as u8 => Self::TheHole,
+      x if x == Self::Undefined as u8 => Self::Undefined,
+      x if x == Self::Null as u8 => Self::Null,
+      x if x == Self::True as u8 => Self::True,
+      x if x == Self::False as u8 => Self::False,
+      x if x == Self::Int32 as u8 => Self::Int32,
+      x if x == Self::Uint32 as u8 => Self::Uint32,
+      x if x == Self::Double as u8 => Self::Double,
+      x if x == Self::BigInt as u8 => Self::BigInt,
+      x if x == Self::Utf8String as u8 => Self::Utf8String,
+      x if x == Self::OneByteString as u8 => Self::OneByteString,
+      x if x == Self::TwoByteString as u8 => Self::TwoByteString,
+      x if x == Self::ObjectReference as u8 => Self::ObjectReference,
+      x if x == Self::BeginJsObject as u8 => Self::BeginJsObject,
+      x if x == Self::EndJsObject as u8 => Self::EndJsObject,
+      x if x == Self::BeginSparseJsArray as u8 => Self::BeginSparseJsArray,
+      x if x == Self::EndSparseJsArray as u8 => Self::EndSparseJsArray,
+      x if x == Self::BeginDenseJsArray as u8 => Self::BeginDenseJsArray,
+      x if x == Self::EndDenseJsArray as u8 => Self::EndDenseJsArray,
+      x if x == Self::Date as u8 => Self::Date,
+      x if x == Self::TrueObject as u8 => Self::TrueObject,
+      x if x == Self::FalseObject as u8 => Self::FalseObject,
+      x if x == Self::NumberObject as u8 => Self::NumberObject,
+      x if x == Self::BigIntObject as u8 => Self::BigIntObject,
+      x if x == Self::StringObject as u8 => Self::StringObject,
+      x if x == Self::RegExp as u8 => Self::RegExp,
+      x if x == Self::BeginJsMap as u8 => Self::BeginJsMap,
+      x if x == Self::EndJsMap as u8 => Self::EndJsMap,
+      x if x == Self::BeginJsSet as u8 => Self::BeginJsSet,
+      x if x == Self::EndJsSet as u8 => Self::EndJsSet,
+      x if x == Self::ArrayBuffer as u8 => Self::ArrayBuffer,
+      x if x == Self::ResizableArrayBuffer as u8 => Self::ResizableArrayBuffer,
+      x if x == Self::ArrayBufferTransfer as u8 => Self::ArrayBufferTransfer,
+      x if x == Self::ArrayBufferView as u8 => Self::ArrayBufferView,
+      x if x == Self::SharedArrayBuffer as u8 => Self::SharedArrayBuffer,
+      x if x == Self::SharedObject as u8 => Self::SharedObject,
+      x if x == Self::WasmModuleTransfer as u8 => Self::WasmModuleTransfer,
+      x if x == Self::HostObject as u8 => Self::HostObject,
+      x if x == Self::WasmMemoryTransfer as u8 => Self::WasmMemoryTransfer,
+      x if x == Self::Error as u8 => Self::Error,
+      _ => return None,
+    })
+  }
+}
+
 /// https://source.chromium.org/chromium/chromium/src/+/main:v8/src/objects/value-serializer.cc;l=93;drc=f5bdc89c7395ed24f1b8d196a3bdd6232d5bf771;bpv=1;bpt=1
 
+#[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum ArrayBufferViewTag {
   Int8Array = b'b',
@@ -109,6 +162,7 @@ pub enum ArrayBufferViewTag {
   Uint16Array = b'W',
   Int32Array = b'd',
   Uint32Array = b'D',
+  Float16Array = b'h',
   Float32Array = b'f',
   Float64Array = b'F',
   BigInt64Array = b'q',
@@ -116,7 +170,30 @@ pub enum ArrayBufferViewTag {
   DataView = b'?',
 }
 
+impl ArrayBufferViewTag {
+  /// The tag with the given value, if any.
+  pub(crate) fn from_u8(byte: u8) -> Option<Self> {
+    Some(match byte {
+      x if x == Self::Int8Array as u8 => Self::Int8Array,
+      x if x == Self::Uint8Array as u8 => Self::Uint8Array,
+      x if x == Self::Uint8ClampedArray as u8 => Self::Uint8ClampedArray,
+      x if x == Self::Int16Array as u8 => Self::Int16Array,
+      x if x == Self::Uint16Array as u8 => Self::Uint16Array,
+      x if x == Self::Int32Array as u8 => Self::Int32Array,
+      x if x == Self::Uint32Array as u8 => Self::Uint32Array,
+      x if x == Self::Float16Array as u8 => Self::Float16Array,
+      x if x == Self::Float32Array as u8 => Self::Float32Array,
+      x if x == Self::Float64Array as u8 => Self::Float64Array,
+      x if x == Self::BigInt64Array as u8 => Self::BigInt64Array,
+      x if x == Self::BigUint64Array as u8 => Self::BigUint64Array,
+      x if x == Self::DataView as u8 => Self::DataView,
+      _ => return None,
+    })
+  }
+}
+
 /// https://source.chromium.org/chromium/chromium/src/+/main:v8/src/objects/value-serializer.cc;l=93;drc=f5bdc89c7395ed24f1b8d196a3bdd6232d5bf771;bpv=1;bpt=1#[repr(u8)]
+#[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum ErrorTag {
   /// The error is a EvalError. No accompanying data.
@@ -140,3 +217,24 @@ pub enum ErrorTag {
   /// The end of this error information.
   End = b'.',
 }
+
+impl ErrorTag {
+  /// The tag with the given value, if any.
+  pub(crate) fn from_u8(byte: u8) -> Option<Self> {
+    Some(match byte {
+      x if x == Self::EvalErrorPrototype as u8 => Self::EvalErrorPrototype,
+      x if x == Self::RangeErrorPrototype as u8 => Self::RangeErrorPrototype,
+      x if x == Self::ReferenceErrorPrototype as u8 => {
+        Self::ReferenceErrorPrototype
+      }
+      x if x == Self::SyntaxErrorPrototype as u8 => Self::SyntaxErrorPrototype,
+      x if x == Self::TypeErrorPrototype as u8 => Self::TypeErrorPrototype,
+      x if x == Self::UriErrorPrototype as u8 => Self::UriErrorPrototype,
+      x if x == Self::Message as u8 => Self::Message,
+      x if x == Self::Cause as u8 => Self::Cause,
+      x if x == Self::Stack as u8 => Self::Stack,
+      x if x == Self::End as u8 => Self::End,
+      _ => return None,
+    })
+  }
+}
@@ -1,12 +1,18 @@
+use std::alloc::Layout;
+use std::any::Any;
 use std::borrow::Cow;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Write;
+use std::mem::align_of;
+use std::ptr::NonNull;
 use std::rc::Rc;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
 use num_bigint::BigInt;
 use thiserror::Error;
@@ -14,17 +20,22 @@ use thiserror::Error;
 static NEXT_HEAP_ID: AtomicU64 = AtomicU64::new(1);
 
 struct HeapEqContext<'a, 'b, T> {
-  heap: &'a Heap,
+  heap: &'a BorrowedHeap<'a>,
   value: &'b T,
   visited: Rc<RefCell<HashSet<HeapReference>>>,
+  /// Pairs of heap objects that still need to be compared. They are compared
+  /// one after another instead of recursively, so that deeply nested values
+  /// can be compared.
+  pending: Rc<RefCell<Vec<(HeapReference, HeapReference)>>>,
 }
 
 impl<'a, 'b, T> HeapEqContext<'a, 'b, T> {
-  fn next<N>(&self, value: &'b N) -> HeapEqContext<'a, 'b, N> {
+  fn next<'c, N>(&self, value: &'c N) -> HeapEqContext<'a, 'c, N> {
     HeapEqContext {
       heap: self.heap,
       value,
       visited: self.visited.clone(),
+      pending: self.pending.clone(),
     }
   }
 }
@@ -41,22 +52,40 @@ impl<'a, 'b, T: HeapEq<Right>, Right> PartialEq<HeapEqContext<'a, 'b, Right>>
   }
 }
 
-pub fn value_eq(left: (&Value, &Heap), right: (&Value, &Heap)) -> bool {
+pub fn value_eq(
+  left: (&BorrowedValue, &BorrowedHeap),
+  right: (&BorrowedValue, &BorrowedHeap),
+) -> bool {
+  let pending = Rc::new(RefCell::new(vec![]));
   let left = HeapEqContext {
     heap: left.1,
     value: left.0,
     visited: Rc::new(RefCell::new(HashSet::new())),
+    pending: pending.clone(),
   };
   let right = HeapEqContext {
     heap: right.1,
     value: right.0,
     visited: Rc::new(RefCell::new(HashSet::new())),
+    pending: pending.clone(),
   };
-  left == right
+  if left != right {
+    return false;
+  }
+  loop {
+    let Some((a, b)) = pending.borrow_mut().pop() else {
+      return true;
+    };
+    if left.next(a.open(left.heap)) != right.next(b.open(right.heap)) {
+      return false;
+    }
+  }
 }
 
+/// A value whose strings may borrow from the deserializer input, as read by
+/// [crate::ValueDeserializer::read_borrowed].
 #[derive(Debug, Clone)]
-pub enum Value {
+pub enum BorrowedValue<'a> {
   Undefined,
   Null,
   Bool(bool),
@@ -64,23 +93,30 @@ pub enum Value {
   U32(u32),
   Double(f64),
   BigInt(BigInt),
-  String(StringValue),
+  String(BorrowedStringValue<'a>),
   HeapReference(HeapReference),
 }
 
-impl HeapEq for Value {
+/// A [BorrowedValue] that owns all of its data.
+pub type Value = BorrowedValue<'static>;
+
+impl HeapEq for BorrowedValue<'_> {
   fn eq(left: &HeapEqContext<Self>, right: &HeapEqContext<Self>) -> bool {
     match (left.value, right.value) {
-      (Value::Undefined, Value::Undefined) => true,
-      (Value::Null, Value::Null) => true,
-      (Value::Bool(a), Value::Bool(b)) => a == b,
-      (Value::I32(a), Value::I32(b)) => a == b,
-      (Value::U32(a), Value::U32(b)) => a == b,
-      (Value::Double(a), Value::Double(b)) if a.is_nan() && b.is_nan() => true,
-      (Value::Double(a), Value::Double(b)) => a == b,
-      (Value::BigInt(a), Value::BigInt(b)) => a == b,
-      (Value::String(a), Value::String(b)) => a == b,
-      (Value::HeapReference(a), Value::HeapReference(b)) => {
+      (BorrowedValue::Undefined, BorrowedValue::Undefined) => true,
+      (BorrowedValue::Null, BorrowedValue::Null) => true,
+      (BorrowedValue::Bool(a), BorrowedValue::Bool(b)) => a == b,
+      (BorrowedValue::I32(a), BorrowedValue::I32(b)) => a == b,
+      (BorrowedValue::U32(a), BorrowedValue::U32(b)) => a == b,
+      (BorrowedValue::Double(a), BorrowedValue::Double(b))
+        if a.is_nan() && b.is_nan() =>
+      {
+        true
+      }
+      (BorrowedValue::Double(a), BorrowedValue::Double(b)) => a == b,
+      (BorrowedValue::BigInt(a), BorrowedValue::BigInt(b)) => a == b,
+      (BorrowedValue::String(a), BorrowedValue::String(b)) => a == b,
+      (BorrowedValue::HeapReference(a), BorrowedValue::HeapReference(b)) => {
         let a = left.next(a);
         let b = right.next(b);
         a == b
@@ -90,38 +126,62 @@ impl HeapEq for Value {
   }
 }
 
+impl BorrowedValue<'_> {
+  /// Copy any data borrowed from the deserializer input.
+  pub fn into_owned(self) -> Value {
+    match self {
+      Self::Undefined => Value::Undefined,
+      Self::Null => Value::Null,
+      Self::Bool(b) => Value::Bool(b),
+      Self::I32(i) => Value::I32(i),
+      Self::U32(u) => Value::U32(u),
+      Self::Double(d) => Value::Double(d),
+      Self::BigInt(b) => Value::BigInt(b),
+      Self::String(s) => Value::String(s.into_owned()),
+      Self::HeapReference(r) => Value::HeapReference(r),
+    }
+  }
+}
+
 #[derive(Clone)]
-pub enum StringValue {
-  Wtf8(Wtf8String),
-  OneByte(OneByteString),
-  TwoByte(TwoByteString),
+pub enum BorrowedStringValue<'a> {
+  Wtf8(BorrowedWtf8String<'a>),
+  OneByte(BorrowedOneByteString<'a>),
+  TwoByte(BorrowedTwoByteString<'a>),
 }
 
-impl PartialEq for StringValue {
+/// A [BorrowedStringValue] that owns all of its data.
+pub type StringValue = BorrowedStringValue<'static>;
+
+impl PartialEq for BorrowedStringValue<'_> {
   fn eq(&self, other: &Self) -> bool {
     let left = match self {
-      StringValue::Wtf8(str) => {
+      BorrowedStringValue::Wtf8(str) => {
         // Safety: The memory layout of Wtf8 and [u8] is the same.
         let wtf8: &wtf8::Wtf8 = unsafe { std::mem::transmute(str.as_bytes()) };
         wtf8.to_ill_formed_utf16().collect()
       }
-      StringValue::OneByte(str) => str.as_str().encode_utf16().collect(),
-      StringValue::TwoByte(str) => str.0.clone(),
+      BorrowedStringValue::OneByte(str) => {
+        str.as_str().encode_utf16().collect()
+      }
+      BorrowedStringValue::TwoByte(str) => str.0.to_vec(),
     };
     let right = match other {
-      StringValue::Wtf8(wtf8) => {
+      BorrowedStringValue::Wtf8(wtf8) => {
         // Safety: The memory layout of Wtf8 and [u8] is the same.
         let wtf8: &wtf8::Wtf8 = unsafe { std::mem::transmute(wtf8.as_bytes()) };
         wtf8.to_ill_formed_utf16().collect()
       }
-      StringValue::OneByte(str) => str.as_str().encode_utf16().collect(),
-      StringValue::TwoByte(bytes) => bytes.0.clone(),
+      BorrowedStringValue::OneByte(str) => {
+        str.as_str().encode_utf16().collect()
+      }
+      BorrowedStringValue::TwoByte(bytes) => bytes.0.to_vec(),
     };
     left == right
   }
 }
 
-impl std::fmt::Debug for StringValue {
+impl std::fmt::Debug for BorrowedStringValue<'_> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     match self {
       Self::Wtf8(s) => {
@@ -143,19 +203,19 @@ impl std::fmt::Debug for StringValue {
   }
 }
 
-impl StringValue {
+impl<'a> BorrowedStringValue<'a> {
   /// Create a new StringValue from a String. If the string is valid Latin-1 it
   /// will be stored as a OneByte string, otherwise it will be stored as a Utf8
   /// string.
   pub fn new(s: String) -> Self {
     if s.is_ascii() {
-      Self::OneByte(OneByteString {
-        bytes: s.into_bytes(),
+      Self::OneByte(BorrowedOneByteString {
+        bytes: Cow::Owned(s.into_bytes()),
         is_ascii: true, // We just checked that the string is ASCII.
       })
     } else {
-      Self::Wtf8(Wtf8String {
-        bytes: s.into_bytes(),
+      Self::Wtf8(BorrowedWtf8String {
+        bytes: Cow::Owned(s.into_bytes()),
         is_utf8: true, // This string is valid UTF-8, because it was a String.
       })
     }
@@ -178,17 +238,30 @@ impl StringValue {
       Self::TwoByte(chars) => Cow::Owned(chars.to_string()),
     }
   }
+
+  /// Copy any data borrowed from the deserializer input.
+  pub fn into_owned(self) -> StringValue {
+    match self {
+      Self::Wtf8(s) => StringValue::Wtf8(s.into_owned()),
+      Self::OneByte(s) => StringValue::OneByte(s.into_owned()),
+      Self::TwoByte(s) => StringValue::TwoByte(s.into_owned()),
+    }
+  }
 }
 
 #[derive(Debug, Clone)]
-pub struct Wtf8String {
-  bytes: Vec<u8>,
+pub struct BorrowedWtf8String<'a> {
+  bytes: Cow<'a, [u8]>,
   is_utf8: bool,
 }
 
-impl Wtf8String {
-  /// Create a new Wtf8String from a Vec<u8>.
-  pub fn new(bytes: Vec<u8>) -> Self {
+/// A [BorrowedWtf8String] that owns all of its data.
+pub type Wtf8String = BorrowedWtf8String<'static>;
+
+impl<'a> BorrowedWtf8String<'a> {
+  /// Create a new Wtf8String from a Vec<u8> or a borrowed byte slice.
+  pub fn new(bytes: impl Into<Cow<'a, [u8]>>) -> Self {
+    let bytes = bytes.into();
     let is_utf8 = std::str::from_utf8(&bytes).is_ok();
     Self { bytes, is_utf8 }
   }
@@ -200,14 +273,22 @@ impl Wtf8String {
 
   /// Turn the Wtf8String into the underlying Vec<u8>.
   pub fn into_bytes(self) -> Vec<u8> {
-    self.bytes
+    self.bytes.into_owned()
+  }
+
+  /// Copy the bytes if they are borrowed from the deserializer input.
+  pub fn into_owned(self) -> Wtf8String {
+    Wtf8String {
+      bytes: Cow::Owned(self.bytes.into_owned()),
+      is_utf8: self.is_utf8,
+    }
   }
 
   /// Turn this Wtf8String into a String.
   pub fn into_string(self) -> String {
     if self.is_utf8 {
       // SAFETY: The bytes are valid UTF-8.
-      unsafe { String::from_utf8_unchecked(self.bytes) }
+      unsafe { String::from_utf8_unchecked(self.bytes.into_owned()) }
     } else {
       String::from_utf8_lossy(&self.bytes).into_owned()
     }
@@ -227,14 +308,18 @@ impl Wtf8String {
 }
 
 #[derive(Debug, Clone)]
-pub struct OneByteString {
-  bytes: Vec<u8>,
+pub struct BorrowedOneByteString<'a> {
+  bytes: Cow<'a, [u8]>,
   is_ascii: bool,
 }
 
-impl OneByteString {
-  /// Create a new OneByteString from a Vec<u8>.
-  pub fn new(bytes: Vec<u8>) -> Self {
+/// A [BorrowedOneByteString] that owns all of its data.
+pub type OneByteString = BorrowedOneByteString<'static>;
+
+impl<'a> BorrowedOneByteString<'a> {
+  /// Create a new OneByteString from a Vec<u8> or a borrowed byte slice.
+  pub fn new(bytes: impl Into<Cow<'a, [u8]>>) -> Self {
+    let bytes = bytes.into();
     let is_ascii = bytes.is_ascii();
     Self { bytes, is_ascii }
   }
@@ -246,21 +331,29 @@ impl OneByteString {
 
   /// Turn the OneByteString into the underlying Vec<u8>.
   pub fn into_bytes(self) -> Vec<u8> {
-    self.bytes
+    self.bytes.into_owned()
+  }
+
+  /// Copy the bytes if they are borrowed from the deserializer input.
+  pub fn into_owned(self) -> OneByteString {
+    OneByteString {
+      bytes: Cow::Owned(self.bytes.into_owned()),
+      is_ascii: self.is_ascii,
+    }
   }
 
   /// Turn this OneByteString into a String.
   pub fn into_string(self) -> String {
     if self.is_ascii {
       // SAFETY: The bytes are valid ASCII, which is a subset of UTF-8.
-      unsafe { String::from_utf8_unchecked(self.bytes) }
+      unsafe { String::from_utf8_unchecked(self.bytes.into_owned()) }
     } else {
       // The string is Latin1, so we have to convert it to UTF-8.
       let str = encoding_rs::mem::decode_latin1(&self.bytes);
       match str {
         Cow::Borrowed(_) => {
           // SAFETY: The bytes are valid ASCII, which is a subset of UTF-8.
-          unsafe { String::from_utf8_unchecked(self.bytes) }
+          unsafe { String::from_utf8_unchecked(self.bytes.into_owned()) }
         }
         Cow::Owned(string) => string,
       }
@@ -281,9 +374,12 @@ impl OneByteString {
 }
 
 #[derive(Debug, Clone)]
-pub struct TwoByteString(Vec<u16>);
+pub struct BorrowedTwoByteString<'a>(Cow<'a, [u16]>);
 
-impl std::fmt::Display for TwoByteString {
+/// A [BorrowedTwoByteString] that owns all of its data.
+pub type TwoByteString = BorrowedTwoByteString<'static>;
+
+impl std::fmt::Display for BorrowedTwoByteString<'_> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     for res in std::char::decode_utf16(self.0.iter().copied()) {
       let char = res.unwrap_or(char::REPLACEMENT_CHARACTER);
@@ -293,11 +389,11 @@ impl std::fmt::Display for TwoByteString {
   }
 }
 
-impl TwoByteString {
-  /// Create a new TwoByteString from a Vec<u16>. This operation is infallible,
-  /// as the bytes are not checked for validity.
-  pub fn new(chars: Vec<u16>) -> Self {
-    Self(chars)
+impl<'a> BorrowedTwoByteString<'a> {
+  /// Create a new TwoByteString from a Vec<u16> or a borrowed slice. This
+  /// operation is infallible, as the bytes are not checked for validity.
+  pub fn new(chars: impl Into<Cow<'a, [u16]>>) -> Self {
+    Self(chars.into())
   }
 
   /// Get the underlying bytes of this TwoByteString.
@@ -316,7 +412,12 @@ impl TwoByteString {
 
   /// Turn the TwoByteString into the underlying Vec<u8>.
   pub fn into_bytes(self) -> Vec<u16> {
-    self.0
+    self.0.into_owned()
+  }
+
+  /// Copy the characters if they are borrowed from the deserializer input.
+  pub fn into_owned(self) -> TwoByteString {
+    BorrowedTwoByteString(Cow::Owned(self.0.into_owned()))
   }
 
   /// Display the contents of the string in UTF-8, escaping any bytes that are
@@ -342,7 +443,7 @@ impl TwoByteString {
   }
 }
 
-pub enum HeapValue {
+pub enum BorrowedHeapValue<'a> {
   /// new Boolean(bool)
   BooleanObject(bool),
   /// new Number(double)
@@ -350,27 +451,27 @@ pub enum HeapValue {
   /// Object(bigint)
   BigIntObject(BigInt),
   /// new String(string)
-  StringObject(StringValue),
+  StringObject(BorrowedStringValue<'a>),
   /// new RegExp(pattern, flags)
-  RegExp(RegExp),
+  RegExp(BorrowedRegExp<'a>),
   /// new Date(timeSinceEpoch)
   Date(Date),
   // { [key]: value }
-  Object(Object),
+  Object(BorrowedObject<'a>),
   /// new Array(0)
   ///   .length = length
   ///   [properties.key] = properties.value
   /// and additional properties of the array object.
-  SparseArray(SparseArray),
+  SparseArray(BorrowedSparseArray<'a>),
   /// new Array(...elements)
   ///   [properties.key] = properties.value
-  DenseArray(DenseArray),
+  DenseArray(BorrowedDenseArray<'a>),
   /// new Map(...entries)
-  Map(Map),
+  Map(BorrowedMap<'a>),
   /// new Set(...values)
-  Set(Set),
+  Set(BorrowedSet<'a>),
   /// new ArrayBuffer(byteLength)
-  ArrayBuffer(ArrayBuffer),
+  ArrayBuffer(BorrowedArrayBuffer<'a>),
   /// new Uint8Array(buffer, byteOffset, length)
   /// new Uint8ClampedArray(buffer, byteOffset, length)
   /// new Int8Array(buffer, byteOffset, length)
@@ -378,6 +479,7 @@ pub enum HeapValue {
   /// new Int16Array(buffer, byteOffset, length)
   /// new Uint32Array(buffer, byteOffset, length)
   /// new Int32Array(buffer, byteOffset, length)
+  /// new Float16Array(buffer, byteOffset, length)
   /// new Float32Array(buffer, byteOffset, length)
   /// new Float64Array(buffer, byteOffset, length)
   /// new BigInt64Array(buffer, byteOffset, length)
@@ -385,56 +487,180 @@ pub enum HeapValue {
   /// new DataView(buffer, byteOffset, byteLength)
   ArrayBufferView(ArrayBufferView),
   /// new Error(message, { cause: "foo" })
-  Error(Error),
-}
-
-impl HeapEq for HeapValue {
+  Error(BorrowedError<'a>),
+  /// new SharedArrayBuffer(byteLength)
+  SharedArrayBuffer(SharedArrayBuffer),
+  /// new WebAssembly.Memory({ initial, maximum, shared: true })
+  WasmMemory(WasmMemory),
+  /// A transferred WebAssembly.Module, identified by its transfer id
+  WasmModule(WasmModule),
+  /// A value shared between isolates (for example a shared struct), identified
+  /// by its index in the embedder's shared value conveyor.
+  SharedObject(u32),
+  /// An embedder specific object, decoded by a host object delegate.
+  HostObject(HostObject),
+  /// A value that could not be read, in the result of
+  /// [crate::ValueDeserializer::read_salvaged].
+  Placeholder,
+}
+
+/// A [BorrowedHeapValue] that owns all of its data.
+pub type HeapValue = BorrowedHeapValue<'static>;
+
+impl HeapEq for BorrowedHeapValue<'_> {
   fn eq(left: &HeapEqContext<Self>, right: &HeapEqContext<Self>) -> bool {
     match (left.value, right.value) {
-      (HeapValue::BooleanObject(a), HeapValue::BooleanObject(b)) => {
+      (
+        BorrowedHeapValue::BooleanObject(a),
+        BorrowedHeapValue::BooleanObject(b),
+      ) => {
         println!("BooleanObject {}", a == b);
         a == b
       }
-      (HeapValue::NumberObject(a), HeapValue::NumberObject(b))
-        if a.is_nan() && b.is_nan() =>
-      {
-        true
-      }
-      (HeapValue::NumberObject(a), HeapValue::NumberObject(b)) => a == b,
-      (HeapValue::BigIntObject(a), HeapValue::BigIntObject(b)) => a == b,
-      (HeapValue::StringObject(a), HeapValue::StringObject(b)) => a == b,
-      (HeapValue::RegExp(a), HeapValue::RegExp(b)) => a == b,
-      (HeapValue::Date(a), HeapValue::Date(b)) => a == b,
-      (HeapValue::Object(a), HeapValue::Object(b)) => {
+      (
+        BorrowedHeapValue::NumberObject(a),
+        BorrowedHeapValue::NumberObject(b),
+      ) if a.is_nan() && b.is_nan() => true,
+      (
+        BorrowedHeapValue::NumberObject(a),
+        BorrowedHeapValue::NumberObject(b),
+      ) => a == b,
+      (
+        BorrowedHeapValue::BigIntObject(a),
+        BorrowedHeapValue::BigIntObject(b),
+      ) => a == b,
+      (
+        BorrowedHeapValue::StringObject(a),
+        BorrowedHeapValue::StringObject(b),
+      ) => a == b,
+      (BorrowedHeapValue::RegExp(a), BorrowedHeapValue::RegExp(b)) => a == b,
+      (BorrowedHeapValue::Date(a), BorrowedHeapValue::Date(b)) => a == b,
+      (BorrowedHeapValue::Object(a), BorrowedHeapValue::Object(b)) => {
         left.next(a) == right.next(b)
       }
-      (HeapValue::SparseArray(a), HeapValue::SparseArray(b)) => {
+      (
+        BorrowedHeapValue::SparseArray(a),
+        BorrowedHeapValue::SparseArray(b),
+      ) => left.next(a) == right.next(b),
+      (BorrowedHeapValue::DenseArray(a), BorrowedHeapValue::DenseArray(b)) => {
         left.next(a) == right.next(b)
       }
-      (HeapValue::DenseArray(a), HeapValue::DenseArray(b)) => {
+      (BorrowedHeapValue::SparseArray(a), BorrowedHeapValue::DenseArray(b)) => {
         left.next(a) == right.next(b)
       }
-      (HeapValue::SparseArray(a), HeapValue::DenseArray(b)) => {
+      (BorrowedHeapValue::DenseArray(a), BorrowedHeapValue::SparseArray(b)) => {
+        right.next(b) == left.next(a)
+      }
+      (BorrowedHeapValue::Map(a), BorrowedHeapValue::Map(b)) => {
         left.next(a) == right.next(b)
       }
-      (HeapValue::DenseArray(a), HeapValue::SparseArray(b)) => {
-        right.next(b) == left.next(a)
+      (BorrowedHeapValue::Set(a), BorrowedHeapValue::Set(b)) => {
+        left.next(a) == right.next(b)
       }
-      (HeapValue::Map(a), HeapValue::Map(b)) => left.next(a) == right.next(b),
-      (HeapValue::Set(a), HeapValue::Set(b)) => left.next(a) == right.next(b),
-      (HeapValue::ArrayBuffer(a), HeapValue::ArrayBuffer(b)) => a == b,
-      (HeapValue::ArrayBufferView(a), HeapValue::ArrayBufferView(b)) => {
+      (
+        BorrowedHeapValue::ArrayBuffer(a),
+        BorrowedHeapValue::ArrayBuffer(b),
+      ) => a == b,
+      (
+        BorrowedHeapValue::ArrayBufferView(a),
+        BorrowedHeapValue::ArrayBufferView(b),
+      ) => left.next(a) == right.next(b),
+      (BorrowedHeapValue::Error(a), BorrowedHeapValue::Error(b)) => {
         left.next(a) == right.next(b)
       }
-      (HeapValue::Error(a), HeapValue::Error(b)) => {
+      (BorrowedHeapValue::HostObject(a), BorrowedHeapValue::HostObject(b)) => {
+        a == b
+      }
+      (
+        BorrowedHeapValue::SharedArrayBuffer(a),
+        BorrowedHeapValue::SharedArrayBuffer(b),
+      ) => a == b,
+      (BorrowedHeapValue::WasmMemory(a), BorrowedHeapValue::WasmMemory(b)) => {
         left.next(a) == right.next(b)
       }
+      (BorrowedHeapValue::WasmModule(a), BorrowedHeapValue::WasmModule(b)) => {
+        a == b
+      }
+      (
+        BorrowedHeapValue::SharedObject(a),
+        BorrowedHeapValue::SharedObject(b),
+      ) => a == b,
+      (BorrowedHeapValue::Placeholder, BorrowedHeapValue::Placeholder) => true,
       _ => false,
     }
   }
 }
 
-impl std::fmt::Debug for HeapValue {
+impl BorrowedHeapValue<'_> {
+  /// Copy any data borrowed from the deserializer input.
+  pub fn into_owned(self) -> HeapValue {
+    match self {
+      Self::BooleanObject(b) => HeapValue::BooleanObject(b),
+      Self::NumberObject(n) => HeapValue::NumberObject(n),
+      Self::BigIntObject(b) => HeapValue::BigIntObject(b),
+      Self::StringObject(s) => HeapValue::StringObject(s.into_owned()),
+      Self::RegExp(regexp) => HeapValue::RegExp(RegExp {
+        pattern: regexp.pattern.into_owned(),
+        flags: regexp.flags,
+      }),
+      Self::Date(date) => HeapValue::Date(date),
+      Self::Object(obj) => HeapValue::Object(Object {
+        properties: properties_into_owned(obj.properties),
+      }),
+      Self::SparseArray(arr) => HeapValue::SparseArray(SparseArray {
+        length: arr.length,
+        properties: properties_into_owned(arr.properties),
+      }),
+      Self::DenseArray(arr) => HeapValue::DenseArray(DenseArray {
+        elements: arr
+          .elements
+          .into_iter()
+          .map(|value| value.map(BorrowedValue::into_owned))
+          .collect(),
+        properties: properties_into_owned(arr.properties),
+      }),
+      Self::Map(map) => HeapValue::Map(Map {
+        entries: map
+          .entries
+          .into_iter()
+          .map(|(key, value)| (key.into_owned(), value.into_owned()))
+          .collect(),
+      }),
+      Self::Set(set) => HeapValue::Set(Set {
+        values: set
+          .values
+          .into_iter()
+          .map(BorrowedValue::into_owned)
+          .collect(),
+      }),
+      Self::ArrayBuffer(ab) => HeapValue::ArrayBuffer(ab.into_owned()),
+      Self::ArrayBufferView(view) => HeapValue::ArrayBufferView(view),
+      Self::Error(err) => HeapValue::Error(Error {
+        name: err.name,
+        message: err.message.map(BorrowedStringValue::into_owned),
+        stack: err.stack.map(BorrowedStringValue::into_owned),
+        cause: err.cause.map(BorrowedValue::into_owned),
+      }),
+      Self::SharedArrayBuffer(sab) => HeapValue::SharedArrayBuffer(sab),
+      Self::WasmMemory(memory) => HeapValue::WasmMemory(memory),
+      Self::WasmModule(module) => HeapValue::WasmModule(module),
+      Self::SharedObject(id) => HeapValue::SharedObject(id),
+      Self::HostObject(obj) => HeapValue::HostObject(obj),
+      Self::Placeholder => HeapValue::Placeholder,
+    }
+  }
+}
+
+fn properties_into_owned(
+  properties: Vec<(BorrowedPropertyKey<'_>, BorrowedValue<'_>)>,
+) -> Vec<(PropertyKey, Value)> {
+  properties
+    .into_iter()
+    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+    .collect()
+}
+
+impl std::fmt::Debug for BorrowedHeapValue<'_> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     match self {
       Self::BooleanObject(val) => {
@@ -459,19 +685,30 @@ impl std::fmt::Debug for HeapValue {
       Self::ArrayBuffer(ab) => std::fmt::Debug::fmt(ab, f),
       Self::ArrayBufferView(view) => std::fmt::Debug::fmt(view, f),
       Self::Error(err) => std::fmt::Debug::fmt(err, f),
+      Self::HostObject(obj) => std::fmt::Debug::fmt(obj, f),
+      Self::SharedArrayBuffer(sab) => std::fmt::Debug::fmt(sab, f),
+      Self::WasmMemory(memory) => std::fmt::Debug::fmt(memory, f),
+      Self::WasmModule(module) => std::fmt::Debug::fmt(module, f),
+      Self::SharedObject(id) => {
+        f.debug_tuple("SharedObject").field(id).finish()
+      }
+      Self::Placeholder => f.write_str("Placeholder"),
     }
   }
 }
 
 #[derive(Clone)]
-pub enum PropertyKey {
+pub enum BorrowedPropertyKey<'a> {
   I32(i32),
   U32(u32),
   Double(f64),
-  String(StringValue),
+  String(BorrowedStringValue<'a>),
 }
 
-impl PartialEq for PropertyKey {
+/// A [BorrowedPropertyKey] that owns all of its data.
+pub type PropertyKey = BorrowedPropertyKey<'static>;
+
+impl PartialEq for BorrowedPropertyKey<'_> {
   fn eq(&self, other: &Self) -> bool {
     let left = match self {
       Self::I32(i) => Cow::Owned(i.to_string()),
@@ -489,7 +726,19 @@ impl PartialEq for PropertyKey {
   }
 }
 
-impl std::fmt::Debug for PropertyKey {
+impl BorrowedPropertyKey<'_> {
+  /// Copy any data borrowed from the deserializer input.
+  pub fn into_owned(self) -> PropertyKey {
+    match self {
+      Self::I32(index) => PropertyKey::I32(index),
+      Self::U32(index) => PropertyKey::U32(index),
+      Self::Double(key) => PropertyKey::Double(key),
+      Self::String(key) => PropertyKey::String(key.into_owned()),
+    }
+  }
+}
+
+impl std::fmt::Debug for BorrowedPropertyKey<'_> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     match self {
       Self::I32(index) => std::fmt::Debug::fmt(index, f),
@@ -500,17 +749,20 @@ impl std::fmt::Debug for PropertyKey {
   }
 }
 
-pub struct Object {
-  pub properties: Vec<(PropertyKey, Value)>,
+pub struct BorrowedObject<'a> {
+  pub properties: Vec<(BorrowedPropertyKey<'a>, BorrowedValue<'a>)>,
 }
 
-impl HeapEq for Object {
+/// A [BorrowedObject] that owns all of its data.
+pub type Object = BorrowedObject<'static>;
+
+impl HeapEq for BorrowedObject<'_> {
   fn eq(left: &HeapEqContext<Self>, right: &HeapEqContext<Self>) -> bool {
     left.next(&left.value.properties) == right.next(&right.value.properties)
   }
 }
 
-impl HeapEq for Vec<(PropertyKey, Value)> {
+impl HeapEq for Vec<(BorrowedPropertyKey<'_>, BorrowedValue<'_>)> {
   fn eq(left: &HeapEqContext<Self>, right: &HeapEqContext<Self>) -> bool {
     if left.value.len() != right.value.len() {
       return false;
@@ -535,7 +787,7 @@ impl HeapEq for Vec<(PropertyKey, Value)> {
   }
 }
 
-impl std::fmt::Debug for Object {
+impl std::fmt::Debug for BorrowedObject<'_> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     write!(f, "Object ")?;
     let mut map = f.debug_map();
@@ -546,16 +798,19 @@ impl std::fmt::Debug for Object {
   }
 }
 
-pub struct DenseArray {
+pub struct BorrowedDenseArray<'a> {
   /// The elements of the array. The length of this vector is the length of the
   /// array. If an element is None, it is the same as if the array had a hole
   /// there.
-  pub elements: Vec<Option<Value>>,
+  pub elements: Vec<Option<BorrowedValue<'a>>>,
   /// Additional properties of the array object.
-  pub properties: Vec<(PropertyKey, Value)>,
+  pub properties: Vec<(BorrowedPropertyKey<'a>, BorrowedValue<'a>)>,
 }
 
-impl HeapEq for DenseArray {
+/// A [BorrowedDenseArray] that owns all of its data.
+pub type DenseArray = BorrowedDenseArray<'static>;
+
+impl HeapEq for BorrowedDenseArray<'_> {
   fn eq(left: &HeapEqContext<Self>, right: &HeapEqContext<Self>) -> bool {
     if left.value.elements.len() != right.value.elements.len() {
       return false;
@@ -586,7 +841,7 @@ impl Debug for Hole {
   }
 }
 
-impl std::fmt::Debug for DenseArray {
+impl std::fmt::Debug for BorrowedDenseArray<'_> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     write!(f, "DenseArray ")?;
     let mut list = f.debug_list();
@@ -608,12 +863,15 @@ impl std::fmt::Debug for DenseArray {
   }
 }
 
-pub struct SparseArray {
+pub struct BorrowedSparseArray<'a> {
   pub length: u32,
-  pub properties: Vec<(PropertyKey, Value)>,
+  pub properties: Vec<(BorrowedPropertyKey<'a>, BorrowedValue<'a>)>,
 }
 
-impl HeapEq for SparseArray {
+/// A [BorrowedSparseArray] that owns all of its data.
+pub type SparseArray = BorrowedSparseArray<'static>;
+
+impl HeapEq for BorrowedSparseArray<'_> {
   fn eq(left: &HeapEqContext<Self>, right: &HeapEqContext<Self>) -> bool {
     left.value.length == right.value.length
       && left.next(&left.value.properties)
@@ -621,8 +879,11 @@ impl HeapEq for SparseArray {
   }
 }
 
-impl HeapEq<DenseArray> for SparseArray {
-  fn eq(left: &HeapEqContext<Self>, right: &HeapEqContext<DenseArray>) -> bool {
+impl<'a> HeapEq<BorrowedDenseArray<'a>> for BorrowedSparseArray<'a> {
+  fn eq(
+    left: &HeapEqContext<Self>,
+    right: &HeapEqContext<BorrowedDenseArray<'a>>,
+  ) -> bool {
     if left.value.length as usize != right.value.elements.len()
       || left.value.properties.len()
         != (right.value.elements.len() + right.value.properties.len())
@@ -639,10 +900,10 @@ impl HeapEq<DenseArray> for SparseArray {
         }
       } else {
         let key = match left_key {
-          PropertyKey::I32(key) => Cow::Owned(key.to_string()),
-          PropertyKey::U32(key) => Cow::Owned(key.to_string()),
-          PropertyKey::Double(key) => Cow::Owned(key.to_string()),
-          PropertyKey::String(str) => str.to_string(),
+          BorrowedPropertyKey::I32(key) => Cow::Owned(key.to_string()),
+          BorrowedPropertyKey::U32(key) => Cow::Owned(key.to_string()),
+          BorrowedPropertyKey::Double(key) => Cow::Owned(key.to_string()),
+          BorrowedPropertyKey::String(str) => str.to_string(),
         };
         let key = match key.parse::<u32>() {
           Ok(key) => key,
@@ -663,7 +924,7 @@ impl HeapEq<DenseArray> for SparseArray {
   }
 }
 
-impl std::fmt::Debug for SparseArray {
+impl std::fmt::Debug for BorrowedSparseArray<'_> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     write!(f, "SparseArray({}) ", self.length)?;
     let mut map = f.debug_map();
@@ -674,12 +935,24 @@ impl std::fmt::Debug for SparseArray {
   }
 }
 
-#[derive(Debug, PartialEq)]
-pub struct RegExp {
-  pub pattern: StringValue,
+#[derive(PartialEq)]
+pub struct BorrowedRegExp<'a> {
+  pub pattern: BorrowedStringValue<'a>,
   pub flags: RegExpFlags,
 }
 
+/// A [BorrowedRegExp] that owns all of its data.
+pub type RegExp = BorrowedRegExp<'static>;
+
+impl std::fmt::Debug for BorrowedRegExp<'_> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("RegExp")
+      .field("pattern", &self.pattern)
+      .field("flags", &self.flags)
+      .finish()
+  }
+}
+
 bitflags::bitflags! {
   #[derive(Debug, Clone, Copy, PartialEq, Eq)]
   #[repr(transparent)]
@@ -775,11 +1048,14 @@ impl Date {
   }
 }
 
-pub struct Map {
-  pub entries: Vec<(Value, Value)>,
+pub struct BorrowedMap<'a> {
+  pub entries: Vec<(BorrowedValue<'a>, BorrowedValue<'a>)>,
 }
 
-impl HeapEq for Map {
+/// A [BorrowedMap] that owns all of its data.
+pub type Map = BorrowedMap<'static>;
+
+impl HeapEq for BorrowedMap<'_> {
   fn eq(left: &HeapEqContext<Self>, right: &HeapEqContext<Self>) -> bool {
     if left.value.entries.len() != right.value.entries.len() {
       return false;
@@ -804,7 +1080,7 @@ impl HeapEq for Map {
   }
 }
 
-impl std::fmt::Debug for Map {
+impl std::fmt::Debug for BorrowedMap<'_> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     write!(f, "Map ")?;
     let mut map = f.debug_map();
@@ -815,11 +1091,14 @@ impl std::fmt::Debug for Map {
   }
 }
 
-pub struct Set {
-  pub values: Vec<Value>,
+pub struct BorrowedSet<'a> {
+  pub values: Vec<BorrowedValue<'a>>,
 }
 
-impl HeapEq for Set {
+/// A [BorrowedSet] that owns all of its data.
+pub type Set = BorrowedSet<'static>;
+
+impl HeapEq for BorrowedSet<'_> {
   fn eq(left: &HeapEqContext<Self>, right: &HeapEqContext<Self>) -> bool {
     if left.value.values.len() != right.value.values.len() {
       return false;
@@ -841,7 +1120,7 @@ impl HeapEq for Set {
   }
 }
 
-impl std::fmt::Debug for Set {
+impl std::fmt::Debug for BorrowedSet<'_> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     write!(f, "Set ")?;
     let mut list = f.debug_set();
@@ -852,24 +1131,248 @@ impl std::fmt::Debug for Set {
   }
 }
 
-#[derive(Debug, PartialEq)]
-pub struct ArrayBuffer {
+#[derive(PartialEq)]
+pub struct BorrowedArrayBuffer<'a> {
   /// The raw bytes of the buffer. We ensure that this is always aligned to 8
   /// bytes, so that we can cast it to a [[u64] / [i64]] when appropriate.
   /// Additionally we ensure that the length never exceeds 2^32 bytes, so that
   /// we can cast the length to a u32 when appropriate.
-  pub(crate) data: Box<[u8]>,
+  pub(crate) data: AlignedBytes<'a>,
   /// The maximum byte length of the buffer. If this is None, resizability is
   /// disabled. If this is Some(n), then the buffer can be resized to a maximum
   /// of n bytes.
   pub max_byte_length: Option<u32>,
 }
 
-impl ArrayBuffer {
+/// A [BorrowedArrayBuffer] that owns all of its data.
+pub type ArrayBuffer = BorrowedArrayBuffer<'static>;
+
+impl std::fmt::Debug for BorrowedArrayBuffer<'_> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ArrayBuffer")
+      .field("data", &self.data)
+      .field("max_byte_length", &self.max_byte_length)
+      .finish()
+  }
+}
+
+/// The bytes of an [ArrayBuffer], either owned or borrowed from the input of
+/// the deserializer. Both variants are always aligned to 8 bytes.
+pub(crate) enum AlignedBytes<'a> {
+  Owned(AlignedBox),
+  Borrowed(&'a [u8]),
+}
+
+impl<'a> AlignedBytes<'a> {
+  /// Borrow the bytes if they are suitably aligned, or copy them otherwise.
+  pub(crate) fn new(bytes: &'a [u8]) -> Self {
+    if bytes.as_ptr() as usize % align_of::<u64>() == 0 {
+      Self::Borrowed(bytes)
+    } else {
+      Self::copied(bytes)
+    }
+  }
+
+  fn copied(bytes: &[u8]) -> Self {
+    let mut data = AlignedBox::zeroed(bytes.len());
+    data.copy_from_slice(bytes);
+    Self::Owned(data)
+  }
+
+  fn to_mut(&mut self) -> &mut [u8] {
+    if let Self::Borrowed(bytes) = self {
+      *self = Self::copied(bytes);
+    }
+    match self {
+      Self::Owned(data) => data,
+      Self::Borrowed(_) => unreachable!(),
+    }
+  }
+}
+
+impl std::ops::Deref for AlignedBytes<'_> {
+  type Target = [u8];
+
+  fn deref(&self) -> &[u8] {
+    match self {
+      Self::Owned(data) => data,
+      Self::Borrowed(bytes) => bytes,
+    }
+  }
+}
+
+impl Debug for AlignedBytes<'_> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Debug::fmt(&**self, f)
+  }
+}
+
+impl PartialEq for AlignedBytes<'_> {
+  fn eq(&self, other: &Self) -> bool {
+    **self == **other
+  }
+}
+
+impl Clone for BorrowedArrayBuffer<'_> {
+  fn clone(&self) -> Self {
+    // Cloning the boxed slice directly would not preserve the alignment.
+    let data = match &self.data {
+      AlignedBytes::Owned(data) => AlignedBytes::copied(data),
+      AlignedBytes::Borrowed(bytes) => AlignedBytes::Borrowed(bytes),
+    };
+    Self {
+      data,
+      max_byte_length: self.max_byte_length,
+    }
+  }
+}
+
+impl<'a> BorrowedArrayBuffer<'a> {
+  /// Create a new zero filled ArrayBuffer.
+  pub fn new(byte_length: u32) -> Self {
+    Self {
+      data: AlignedBytes::Owned(AlignedBox::zeroed(byte_length as usize)),
+      max_byte_length: None,
+    }
+  }
+
+  /// Create a new zero filled ArrayBuffer that can be resized up to
+  /// `max_byte_length` bytes.
+  pub fn new_resizable(
+    byte_length: u32,
+    max_byte_length: u32,
+  ) -> Result<Self, ArrayBufferError> {
+    if byte_length > max_byte_length {
+      return Err(ArrayBufferError::ExceedsMaxByteLength {
+        byte_length,
+        max_byte_length,
+      });
+    }
+    Ok(Self {
+      data: AlignedBytes::Owned(AlignedBox::zeroed(byte_length as usize)),
+      max_byte_length: Some(max_byte_length),
+    })
+  }
+
+  /// Create a new ArrayBuffer with a copy of the given bytes.
+  pub fn from_u8_slice(bytes: &[u8]) -> Result<Self, ArrayBufferError> {
+    let mut ab = Self::new(checked_byte_length(bytes.len())?);
+    ab.as_u8_slice_mut().copy_from_slice(bytes);
+    Ok(ab)
+  }
+
+  /// Create a new ArrayBuffer with a copy of the given i8 values, in native
+  /// byte order.
+  pub fn from_i8_slice(values: &[i8]) -> Result<Self, ArrayBufferError> {
+    let mut ab = Self::new(checked_byte_length(std::mem::size_of_val(values))?);
+    ab.as_i8_slice_mut().copy_from_slice(values);
+    Ok(ab)
+  }
+
+  /// Create a new ArrayBuffer with a copy of the given u16 values, in native
+  /// byte order.
+  pub fn from_u16_slice(values: &[u16]) -> Result<Self, ArrayBufferError> {
+    let mut ab = Self::new(checked_byte_length(std::mem::size_of_val(values))?);
+    ab.as_u16_slice_mut().copy_from_slice(values);
+    Ok(ab)
+  }
+
+  /// Create a new ArrayBuffer with a copy of the given i16 values, in native
+  /// byte order.
+  pub fn from_i16_slice(values: &[i16]) -> Result<Self, ArrayBufferError> {
+    let mut ab = Self::new(checked_byte_length(std::mem::size_of_val(values))?);
+    ab.as_i16_slice_mut().copy_from_slice(values);
+    Ok(ab)
+  }
+
+  /// Create a new ArrayBuffer with a copy of the given u32 values, in native
+  /// byte order.
+  pub fn from_u32_slice(values: &[u32]) -> Result<Self, ArrayBufferError> {
+    let mut ab = Self::new(checked_byte_length(std::mem::size_of_val(values))?);
+    ab.as_u32_slice_mut().copy_from_slice(values);
+    Ok(ab)
+  }
+
+  /// Create a new ArrayBuffer with a copy of the given i32 values, in native
+  /// byte order.
+  pub fn from_i32_slice(values: &[i32]) -> Result<Self, ArrayBufferError> {
+    let mut ab = Self::new(checked_byte_length(std::mem::size_of_val(values))?);
+    ab.as_i32_slice_mut().copy_from_slice(values);
+    Ok(ab)
+  }
+
+  /// Create a new ArrayBuffer with a copy of the given u64 values, in native
+  /// byte order.
+  pub fn from_u64_slice(values: &[u64]) -> Result<Self, ArrayBufferError> {
+    let mut ab = Self::new(checked_byte_length(std::mem::size_of_val(values))?);
+    ab.as_u64_slice_mut().copy_from_slice(values);
+    Ok(ab)
+  }
+
+  /// Create a new ArrayBuffer with a copy of the given i64 values, in native
+  /// byte order.
+  pub fn from_i64_slice(values: &[i64]) -> Result<Self, ArrayBufferError> {
+    let mut ab = Self::new(checked_byte_length(std::mem::size_of_val(values))?);
+    ab.as_i64_slice_mut().copy_from_slice(values);
+    Ok(ab)
+  }
+
+  /// Create a new ArrayBuffer with a copy of the given f32 values, in native
+  /// byte order.
+  pub fn from_f32_slice(values: &[f32]) -> Result<Self, ArrayBufferError> {
+    let mut ab = Self::new(checked_byte_length(std::mem::size_of_val(values))?);
+    ab.as_f32_slice_mut().copy_from_slice(values);
+    Ok(ab)
+  }
+
+  /// Create a new ArrayBuffer with a copy of the given f64 values, in native
+  /// byte order.
+  pub fn from_f64_slice(values: &[f64]) -> Result<Self, ArrayBufferError> {
+    let mut ab = Self::new(checked_byte_length(std::mem::size_of_val(values))?);
+    ab.as_f64_slice_mut().copy_from_slice(values);
+    Ok(ab)
+  }
+
   pub fn byte_length(&self) -> u32 {
     self.data.len() as u32
   }
 
+  /// Resize a resizable ArrayBuffer. New bytes are zero filled.
+  pub fn resize(&mut self, byte_length: u32) -> Result<(), ArrayBufferError> {
+    let Some(max_byte_length) = self.max_byte_length else {
+      return Err(ArrayBufferError::NotResizable);
+    };
+    if byte_length > max_byte_length {
+      return Err(ArrayBufferError::ExceedsMaxByteLength {
+        byte_length,
+        max_byte_length,
+      });
+    }
+    let mut data = AlignedBox::zeroed(byte_length as usize);
+    let len = self.data.len().min(data.len());
+    data[..len].copy_from_slice(&self.data[..len]);
+    self.data = AlignedBytes::Owned(data);
+    Ok(())
+  }
+
+  /// Whether the bytes of this buffer are borrowed from the deserializer
+  /// input rather than owned.
+  pub fn is_borrowed(&self) -> bool {
+    matches!(self.data, AlignedBytes::Borrowed(_))
+  }
+
+  /// Copy the bytes if they are borrowed from the deserializer input.
+  pub fn into_owned(self) -> ArrayBuffer {
+    let data = match self.data {
+      AlignedBytes::Owned(data) => AlignedBytes::Owned(data),
+      AlignedBytes::Borrowed(bytes) => AlignedBytes::copied(bytes),
+    };
+    ArrayBuffer {
+      data,
+      max_byte_length: self.max_byte_length,
+    }
+  }
+
   pub fn as_u8_slice(&self) -> &[u8] {
     &self.data
   }
@@ -956,6 +1459,16 @@ impl ArrayBuffer {
     }
   }
 
+  /// Decode the buffer as half precision floats. Rust has no stable f16 type,
+  /// so the values are widened to f32, which represents all of them exactly.
+  pub fn to_f16_vec(&self) -> Vec<f32> {
+    self
+      .as_u16_slice()
+      .iter()
+      .map(|bits| f16_to_f32(*bits))
+      .collect()
+  }
+
   pub fn as_f32_slice(&self) -> &[f32] {
     assert!(self.byte_length() % 4 == 0);
     // SAFETY: data is always aligned to 8 bytes, and we checked that the length
@@ -979,6 +1492,300 @@ impl ArrayBuffer {
       )
     }
   }
+
+  pub fn as_u8_slice_mut(&mut self) -> &mut [u8] {
+    self.data.to_mut()
+  }
+
+  pub fn as_i8_slice_mut(&mut self) -> &mut [i8] {
+    // SAFETY: i8 and u8 have the same size and alignment.
+    unsafe {
+      std::slice::from_raw_parts_mut(
+        self.data.to_mut().as_mut_ptr() as *mut i8,
+        self.data.len(),
+      )
+    }
+  }
+
+  pub fn as_u16_slice_mut(&mut self) -> &mut [u16] {
+    assert!(self.byte_length() % 2 == 0);
+    // SAFETY: data is always aligned to 8 bytes, and we checked that the length
+    // is a multiple of 2 (because one u16 == two u8).
+    unsafe {
+      std::slice::from_raw_parts_mut(
+        self.data.to_mut().as_mut_ptr() as *mut u16,
+        self.data.len() / 2,
+      )
+    }
+  }
+
+  pub fn as_i16_slice_mut(&mut self) -> &mut [i16] {
+    assert!(self.byte_length() % 2 == 0);
+    // SAFETY: data is always aligned to 8 bytes, and we checked that the length
+    // is a multiple of 2 (because one i16 == two u8).
+    unsafe {
+      std::slice::from_raw_parts_mut(
+        self.data.to_mut().as_mut_ptr() as *mut i16,
+        self.data.len() / 2,
+      )
+    }
+  }
+
+  pub fn as_u32_slice_mut(&mut self) -> &mut [u32] {
+    assert!(self.byte_length() % 4 == 0);
+    // SAFETY: data is always aligned to 8 bytes, and we checked that the length
+    // is a multiple of 4 (because one u32 == four u8).
+    unsafe {
+      std::slice::from_raw_parts_mut(
+        self.data.to_mut().as_mut_ptr() as *mut u32,
+        self.data.len() / 4,
+      )
+    }
+  }
+
+  pub fn as_i32_slice_mut(&mut self) -> &mut [i32] {
+    assert!(self.byte_length() % 4 == 0);
+    // SAFETY: data is always aligned to 8 bytes, and we checked that the length
+    // is a multiple of 4 (because one i32 == four u8).
+    unsafe {
+      std::slice::from_raw_parts_mut(
+        self.data.to_mut().as_mut_ptr() as *mut i32,
+        self.data.len() / 4,
+      )
+    }
+  }
+
+  pub fn as_u64_slice_mut(&mut self) -> &mut [u64] {
+    assert!(self.byte_length() % 8 == 0);
+    // SAFETY: data is always aligned to 8 bytes, and we checked that the length
+    // is a multiple of 8 (because one u64 == eight u8).
+    unsafe {
+      std::slice::from_raw_parts_mut(
+        self.data.to_mut().as_mut_ptr() as *mut u64,
+        self.data.len() / 8,
+      )
+    }
+  }
+
+  pub fn as_i64_slice_mut(&mut self) -> &mut [i64] {
+    assert!(self.byte_length() % 8 == 0);
+    // SAFETY: data is always aligned to 8 bytes, and we checked that the length
+    // is a multiple of 8 (because one i64 == eight u8).
+    unsafe {
+      std::slice::from_raw_parts_mut(
+        self.data.to_mut().as_mut_ptr() as *mut i64,
+        self.data.len() / 8,
+      )
+    }
+  }
+
+  pub fn as_f32_slice_mut(&mut self) -> &mut [f32] {
+    assert!(self.byte_length() % 4 == 0);
+    // SAFETY: data is always aligned to 8 bytes, and we checked that the length
+    // is a multiple of 4 (because one f32 == four u8).
+    unsafe {
+      std::slice::from_raw_parts_mut(
+        self.data.to_mut().as_mut_ptr() as *mut f32,
+        self.data.len() / 4,
+      )
+    }
+  }
+
+  pub fn as_f64_slice_mut(&mut self) -> &mut [f64] {
+    assert!(self.byte_length() % 8 == 0);
+    // SAFETY: data is always aligned to 8 bytes, and we checked that the length
+    // is a multiple of 8 (because one f64 == eight u8).
+    unsafe {
+      std::slice::from_raw_parts_mut(
+        self.data.to_mut().as_mut_ptr() as *mut f64,
+        self.data.len() / 8,
+      )
+    }
+  }
+}
+
+impl TryFrom<Vec<u8>> for BorrowedArrayBuffer<'_> {
+  type Error = ArrayBufferError;
+
+  fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+    // The bytes are copied, because a Vec<u8> is not guaranteed to be aligned
+    // to 8 bytes.
+    Self::from_u8_slice(&bytes)
+  }
+}
+
+#[derive(Debug, Error)]
+pub enum ArrayBufferError {
+  #[error("array buffers can not be larger than 2^32 - 1 bytes, got {0}")]
+  TooLarge(usize),
+  #[error(
+    "byte length {byte_length} exceeds max byte length {max_byte_length}"
+  )]
+  ExceedsMaxByteLength {
+    byte_length: u32,
+    max_byte_length: u32,
+  },
+  #[error("array buffer is not resizable")]
+  NotResizable,
+}
+
+fn checked_byte_length(byte_length: usize) -> Result<u32, ArrayBufferError> {
+  u32::try_from(byte_length)
+    .map_err(|_| ArrayBufferError::TooLarge(byte_length))
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+  let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+  let exponent = ((bits >> 10) & 0x1f) as i32;
+  let mantissa = (bits & 0x3ff) as f32;
+  sign
+    * match exponent {
+      0 => mantissa * 2f32.powi(-24),
+      0x1f if mantissa == 0.0 => f32::INFINITY,
+      0x1f => f32::NAN,
+      _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// A buffer whose memory is shared between isolates. Shared array buffers are
+/// never serialized inline: the wire format only contains an id, which is
+/// resolved through [crate::ValueDeserializer::transfer_shared_array_buffer].
+///
+/// Cloning a SharedArrayBuffer is cheap, and the clone refers to the same
+/// memory. Two SharedArrayBuffers are equal if they refer to the same memory,
+/// not if they have the same contents. The contents are read-only: they are a
+/// snapshot of the memory at the time the buffer was created.
+#[derive(Debug, Clone)]
+pub struct SharedArrayBuffer {
+  backing_store: Arc<ArrayBuffer>,
+}
+
+impl PartialEq for SharedArrayBuffer {
+  fn eq(&self, other: &Self) -> bool {
+    self.ptr_eq(other)
+  }
+}
+
+impl Eq for SharedArrayBuffer {}
+
+impl SharedArrayBuffer {
+  /// Create a new SharedArrayBuffer with a copy of the given bytes.
+  pub fn new(bytes: &[u8]) -> Self {
+    Self {
+      backing_store: Arc::new(BorrowedArrayBuffer {
+        data: AlignedBytes::copied(bytes),
+        max_byte_length: None,
+      }),
+    }
+  }
+
+  pub fn byte_length(&self) -> u32 {
+    self.backing_store.byte_length()
+  }
+
+  /// Get the memory of this buffer, which can be read through the typed slice
+  /// accessors of [ArrayBuffer].
+  pub fn as_array_buffer(&self) -> &ArrayBuffer {
+    &self.backing_store
+  }
+
+  /// Whether this buffer and `other` refer to the same memory.
+  pub fn ptr_eq(&self, other: &SharedArrayBuffer) -> bool {
+    Arc::ptr_eq(&self.backing_store, &other.backing_store)
+  }
+}
+
+#[derive(Debug)]
+pub struct WasmMemory {
+  /// The maximum size of the memory in pages, or -1 if the memory has no
+  /// maximum.
+  pub maximum_pages: i32,
+  /// The SharedArrayBuffer that backs this memory.
+  pub buffer: HeapReference,
+}
+
+impl HeapEq for WasmMemory {
+  fn eq(left: &HeapEqContext<Self>, right: &HeapEqContext<Self>) -> bool {
+    left.value.maximum_pages == right.value.maximum_pages
+      && left.next(&left.value.buffer) == right.next(&right.value.buffer)
+  }
+}
+
+/// A WebAssembly.Module. Compiled modules are never serialized inline: the
+/// wire format only contains a transfer id, which is resolved through
+/// [crate::ValueDeserializerDelegate::get_wasm_module_from_id].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmModule {
+  pub transfer_id: u32,
+}
+
+/// Zero initialized bytes on the heap that are aligned to 8 bytes. Unlike a
+/// `Box<[u8]>`, this is deallocated with the alignment it was allocated with,
+/// and it does not allocate at all when it is empty.
+pub(crate) struct AlignedBox {
+  ptr: NonNull<u8>,
+  len: usize,
+}
+
+// Safety: an AlignedBox owns its bytes, like a Box<[u8]> does.
+unsafe impl Send for AlignedBox {}
+unsafe impl Sync for AlignedBox {}
+
+impl AlignedBox {
+  pub(crate) fn zeroed(len: usize) -> Self {
+    if len == 0 {
+      // A dangling pointer is valid for a slice of length 0, as long as it is
+      // aligned.
+      let ptr = NonNull::<u64>::dangling().cast::<u8>();
+      return Self { ptr, len };
+    }
+    let layout = Self::layout(len);
+    // Safety: the layout has a non-zero size.
+    let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+    let Some(ptr) = NonNull::new(ptr) else {
+      std::alloc::handle_alloc_error(layout);
+    };
+    Self { ptr, len }
+  }
+
+  /// Changes the length, keeping the bytes that fit. New bytes are zero
+  /// filled.
+  pub(crate) fn resize(&mut self, len: usize) {
+    let mut resized = Self::zeroed(len);
+    let kept = self.len.min(len);
+    resized[..kept].copy_from_slice(&self[..kept]);
+    *self = resized;
+  }
+
+  fn layout(len: usize) -> Layout {
+    Layout::from_size_align(len, align_of::<u64>()).unwrap()
+  }
+}
+
+impl std::ops::Deref for AlignedBox {
+  type Target = [u8];
+
+  fn deref(&self) -> &[u8] {
+    // Safety: ptr is valid for len bytes, which were initialized when they
+    // were allocated.
+    unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+  }
+}
+
+impl std::ops::DerefMut for AlignedBox {
+  fn deref_mut(&mut self) -> &mut [u8] {
+    // Safety: see Deref. The bytes are owned exclusively.
+    unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+  }
+}
+
+impl Drop for AlignedBox {
+  fn drop(&mut self) {
+    if self.len != 0 {
+      // Safety: ptr was allocated with this layout in AlignedBox::zeroed.
+      unsafe { std::alloc::dealloc(self.ptr.as_ptr(), Self::layout(self.len)) }
+    }
+  }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -990,6 +1797,7 @@ pub enum ArrayBufferViewKind {
   Uint16Array,
   Int32Array,
   Uint32Array,
+  Float16Array,
   Float32Array,
   Float64Array,
   BigInt64Array,
@@ -1007,6 +1815,7 @@ impl Display for ArrayBufferViewKind {
       Self::Uint16Array => write!(f, "Uint16Array"),
       Self::Int32Array => write!(f, "Int32Array"),
       Self::Uint32Array => write!(f, "Uint32Array"),
+      Self::Float16Array => write!(f, "Float16Array"),
       Self::Float32Array => write!(f, "Float32Array"),
       Self::Float64Array => write!(f, "Float64Array"),
       Self::BigInt64Array => write!(f, "BigInt64Array"),
@@ -1026,6 +1835,7 @@ impl ArrayBufferViewKind {
       Self::Uint16Array => 2,
       Self::Int32Array => 4,
       Self::Uint32Array => 4,
+      Self::Float16Array => 2,
       Self::Float32Array => 4,
       Self::Float64Array => 8,
       Self::BigInt64Array => 8,
@@ -1081,15 +1891,28 @@ impl Display for ErrorName {
   }
 }
 
-#[derive(Debug)]
-pub struct Error {
+pub struct BorrowedError<'a> {
   pub name: ErrorName,
-  pub message: Option<StringValue>,
-  pub stack: Option<StringValue>,
-  pub cause: Option<Value>,
+  pub message: Option<BorrowedStringValue<'a>>,
+  pub stack: Option<BorrowedStringValue<'a>>,
+  pub cause: Option<BorrowedValue<'a>>,
 }
 
-impl HeapEq for Error {
+/// A [BorrowedError] that owns all of its data.
+pub type Error = BorrowedError<'static>;
+
+impl std::fmt::Debug for BorrowedError<'_> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Error")
+      .field("name", &self.name)
+      .field("message", &self.message)
+      .field("stack", &self.stack)
+      .field("cause", &self.cause)
+      .finish()
+  }
+}
+
+impl HeapEq for BorrowedError<'_> {
   fn eq(left: &HeapEqContext<Self>, right: &HeapEqContext<Self>) -> bool {
     left.value.name == right.value.name
       && left.value.message == right.value.message
@@ -1099,16 +1922,94 @@ impl HeapEq for Error {
   }
 }
 
-pub struct HeapBuilder {
-  heap_id: u64,
-  values: Vec<Option<HeapValue>>,
+/// The payload of a host object. Host objects are written by the embedder (for
+/// example Node.js, Deno, or Blink) in a wire format of its own choosing. A
+/// [crate::ValueDeserializerDelegate] decodes that wire format into a type
+/// implementing this trait.
+pub trait HostObjectPayload: Debug + 'static {
+  /// Get the payload as [Any], so that it can be downcast to its concrete type.
+  fn as_any(&self) -> &dyn Any;
+
+  /// Compare this payload to the payload of another host object.
+  fn payload_eq(&self, other: &dyn HostObjectPayload) -> bool;
+
+  /// Write a JavaScript expression that represents this host object. Host
+  /// objects can generally not be recreated from JavaScript, so by default
+  /// this renders as `undefined` with an explanatory comment.
+  fn display(&self, writer: &mut dyn Write) -> std::fmt::Result {
+    write!(writer, "undefined /* host object */")
+  }
+}
+
+pub struct HostObject {
+  payload: Box<dyn HostObjectPayload>,
+}
+
+impl HostObject {
+  pub fn new(payload: impl HostObjectPayload) -> Self {
+    Self {
+      payload: Box::new(payload),
+    }
+  }
+
+  /// Get the payload of this host object.
+  pub fn payload(&self) -> &dyn HostObjectPayload {
+    &*self.payload
+  }
+
+  /// Get the payload of this host object as a `T`, if it is one.
+  pub fn downcast_ref<T: HostObjectPayload>(&self) -> Option<&T> {
+    self.payload.as_any().downcast_ref()
+  }
+}
+
+impl PartialEq for HostObject {
+  fn eq(&self, other: &Self) -> bool {
+    self.payload.payload_eq(other.payload())
+  }
 }
 
-impl Default for HeapBuilder {
+impl std::fmt::Debug for HostObject {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("HostObject").field(&self.payload).finish()
+  }
+}
+
+pub struct BorrowedHeapBuilder<'a> {
+  heap_id: u64,
+  values: Vec<Option<BorrowedHeapValue<'a>>>,
+  /// Indices of values that were not assigned an object id, in ascending
+  /// order. Only legacy data contains such values.
+  without_id: Vec<usize>,
+  /// Object ids that refer to the same value as the next id, in ascending
+  /// order. Only host objects that Node.js wrote as a nested value have such
+  /// ids.
+  aliases: Vec<u32>,
+  /// Object ids of the values, if they are not inserted in object id order.
+  sparse_ids: Option<SparseIds>,
+}
+
+/// A [BorrowedHeapBuilder] that owns all of its data.
+pub type HeapBuilder = BorrowedHeapBuilder<'static>;
+
+/// Object ids for a heap that is decoded out of order, because parts of the
+/// input are skipped or decoded on demand.
+struct SparseIds {
+  /// The object id of the next reserved value.
+  next: u32,
+  references: HashMap<u32, HeapReference>,
+  /// Object ids that refer to the next reserved value.
+  pending_aliases: Vec<u32>,
+}
+
+impl Default for BorrowedHeapBuilder<'_> {
   fn default() -> Self {
     Self {
       heap_id: NEXT_HEAP_ID.fetch_add(1, Ordering::Relaxed),
       values: vec![],
+      without_id: vec![],
+      aliases: vec![],
+      sparse_ids: None,
     }
   }
 }
@@ -1128,20 +2029,28 @@ impl std::fmt::Display for HeapBuildError {
   }
 }
 
-impl HeapBuilder {
+impl<'a> BorrowedHeapBuilder<'a> {
   pub fn reserve(&mut self) -> HeapReference {
     let index = self.values.len();
     self.values.push(None);
-    HeapReference {
+    let reference = HeapReference {
       heap_id: self.heap_id,
       index,
+    };
+    if let Some(ids) = &mut self.sparse_ids {
+      ids.references.entry(ids.next).or_insert(reference);
+      ids.next += 1;
+      for alias in ids.pending_aliases.drain(..) {
+        ids.references.entry(alias).or_insert(reference);
+      }
     }
+    reference
   }
 
   pub fn insert_reserved(
     &mut self,
     reference: HeapReference,
-    value: HeapValue,
+    value: BorrowedHeapValue<'a>,
   ) {
     assert!(reference.heap_id == self.heap_id);
     assert!(
@@ -1151,19 +2060,109 @@ impl HeapBuilder {
     );
   }
 
-  pub fn insert(&mut self, value: HeapValue) -> HeapReference {
+  pub fn insert(&mut self, value: BorrowedHeapValue<'a>) -> HeapReference {
     let reference = self.reserve();
     self.insert_reserved(reference, value);
     reference
   }
 
-  pub fn try_open(&self, reference: HeapReference) -> Option<&HeapValue> {
+  pub fn try_open(
+    &self,
+    reference: HeapReference,
+  ) -> Option<&BorrowedHeapValue<'a>> {
     assert!(reference.heap_id == self.heap_id);
     self.values[reference.index].as_ref()
   }
 
+  /// Insert a value that can not be referred to by an object id.
+  pub(crate) fn insert_without_id(
+    &mut self,
+    value: BorrowedHeapValue<'a>,
+  ) -> HeapReference {
+    let index = self.values.len();
+    self.values.push(Some(value));
+    self.without_id.push(index);
+    HeapReference {
+      heap_id: self.heap_id,
+      index,
+    }
+  }
+
+  /// Populate the reserved references that have not been populated yet with
+  /// placeholders.
+  pub(crate) fn fill_with_placeholders(&mut self) {
+    for value in &mut self.values {
+      value.get_or_insert(BorrowedHeapValue::Placeholder);
+    }
+  }
+
+  /// Create a builder whose values are assigned object ids starting at
+  /// `next_id`, in any order.
+  pub(crate) fn with_sparse_ids(next_id: u32) -> Self {
+    Self {
+      sparse_ids: Some(SparseIds {
+        next: next_id,
+        references: HashMap::new(),
+        pending_aliases: vec![],
+      }),
+      ..Self::default()
+    }
+  }
+
+  /// The object id that the next reserved value is assigned.
+  pub(crate) fn next_id(&self) -> u32 {
+    match &self.sparse_ids {
+      Some(ids) => ids.next,
+      None => {
+        (self.values.len() - self.without_id.len() + self.aliases.len()) as u32
+      }
+    }
+  }
+
+  /// Make the next object id refer to the same value as the id after it,
+  /// which is assigned to the next reserved value.
+  pub(crate) fn alias_next_id(&mut self) {
+    match &mut self.sparse_ids {
+      Some(ids) => {
+        ids.pending_aliases.push(ids.next);
+        ids.next += 1;
+      }
+      None => {
+        let id = self.next_id();
+        self.aliases.push(id);
+      }
+    }
+  }
+
+  /// Change the object id that the next reserved value is assigned. Only
+  /// builders created with [HeapBuilder::with_sparse_ids] support this.
+  pub(crate) fn set_next_id(&mut self, id: u32) {
+    self.sparse_ids.as_mut().unwrap().next = id;
+  }
+
+  /// The value that was already inserted for the next object id, if values
+  /// are inserted out of order and it was.
+  pub(crate) fn existing_next(&self) -> Option<HeapReference> {
+    let ids = self.sparse_ids.as_ref()?;
+    ids.references.get(&ids.next).copied()
+  }
+
   pub(crate) fn reference_by_id(&mut self, id: u32) -> Option<HeapReference> {
-    let index = id as usize;
+    if let Some(ids) = &self.sparse_ids {
+      return ids.references.get(&id).copied();
+    }
+    let mut id = id;
+    while self.aliases.binary_search(&id).is_ok() {
+      id += 1;
+    }
+    id -= self.aliases.partition_point(|&alias| alias < id) as u32;
+    let mut index = id as usize;
+    for &skipped in &self.without_id {
+      if skipped > index {
+        break;
+      }
+      index += 1;
+    }
     if self.values.len() <= index {
       return None;
     }
@@ -1173,30 +2172,35 @@ impl HeapBuilder {
     })
   }
 
-  pub fn build(self) -> Result<Heap, HeapBuildError> {
+  pub fn build(self) -> Result<BorrowedHeap<'a>, HeapBuildError> {
     let mut map = Vec::with_capacity(self.values.len());
     for value in self.values {
       map.push(value.ok_or(HeapBuildError { index: map.len() })?);
     }
-    Ok(Heap {
+    Ok(BorrowedHeap {
       heap_id: self.heap_id,
       values: map,
     })
   }
 }
 
-pub struct Heap {
+/// The heap of a [BorrowedValue]. Its strings and ArrayBuffer contents may
+/// borrow from the deserializer input.
+pub struct BorrowedHeap<'a> {
   heap_id: u64,
-  values: Vec<HeapValue>,
+  values: Vec<BorrowedHeapValue<'a>>,
 }
 
-impl Default for Heap {
+/// A [BorrowedHeap] that owns all of its data.
+pub type Heap = BorrowedHeap<'static>;
+
+impl Default for BorrowedHeap<'_> {
   fn default() -> Self {
-    HeapBuilder::default().build().unwrap()
+    BorrowedHeapBuilder::default().build().unwrap()
   }
 }
 
-impl std::fmt::Debug for Heap {
+impl std::fmt::Debug for BorrowedHeap<'_> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     write!(f, "Heap ")?;
     let mut map = f.debug_map();
@@ -1207,12 +2211,12 @@ impl std::fmt::Debug for Heap {
   }
 }
 
-impl Heap {
+impl<'a> BorrowedHeap<'a> {
   pub fn is_empty(&self) -> bool {
     self.values.is_empty()
   }
 
-  pub fn insert(&mut self, value: HeapValue) -> HeapReference {
+  pub fn insert(&mut self, value: BorrowedHeapValue<'a>) -> HeapReference {
     let index = self.values.len();
     assert!(
       index <= u32::MAX as usize,
@@ -1224,6 +2228,25 @@ impl Heap {
       index,
     }
   }
+
+  pub(crate) fn values_mut(
+    &mut self,
+  ) -> impl Iterator<Item = &mut BorrowedHeapValue<'a>> {
+    self.values.iter_mut()
+  }
+
+  /// Copy any data borrowed from the deserializer input. References into this
+  /// heap remain valid for the returned heap.
+  pub fn into_owned(self) -> Heap {
+    Heap {
+      heap_id: self.heap_id,
+      values: self
+        .values
+        .into_iter()
+        .map(BorrowedHeapValue::into_owned)
+        .collect(),
+    }
+  }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
@@ -1243,11 +2266,10 @@ impl HeapEq for HeapReference {
       return false;
     }
     if left_inserted && right_inserted {
-      left.next(left.value.open(left.heap))
-        == right.next(right.value.open(right.heap))
-    } else {
-      true
+      // Compared by value_eq once the current object has been compared.
+      left.pending.borrow_mut().push((*left.value, *right.value));
     }
+    true
   }
 }
 
@@ -1258,13 +2280,27 @@ impl std::fmt::Debug for HeapReference {
 }
 
 impl HeapReference {
-  pub fn open<'a>(&self, heap: &'a Heap) -> &'a HeapValue {
+  pub fn open<'a, 'h>(
+    &self,
+    heap: &'a BorrowedHeap<'h>,
+  ) -> &'a BorrowedHeapValue<'h> {
     assert!(self.heap_id == heap.heap_id);
     &heap.values[self.index]
   }
 
-  pub fn try_open<'a>(&self, heap: &'a Heap) -> Option<&'a HeapValue> {
+  pub fn try_open<'a, 'h>(
+    &self,
+    heap: &'a BorrowedHeap<'h>,
+  ) -> Option<&'a BorrowedHeapValue<'h>> {
     assert!(self.heap_id == heap.heap_id);
     heap.values.get(self.index)
   }
+
+  pub fn open_mut<'a, 'h>(
+    &self,
+    heap: &'a mut BorrowedHeap<'h>,
+  ) -> &'a mut BorrowedHeapValue<'h> {
+    assert!(self.heap_id == heap.heap_id);
+    &mut heap.values[self.index]
+  }
 }
@@ -0,0 +1,991 @@
+use num_bigint::BigInt;
+
+use crate::de::emit;
+use crate::de::read_object_by_id;
+use crate::de::Input;
+use crate::de::ViewFields;
+use crate::lazy::skip_object_internal;
+use crate::value::ArrayBufferViewKind;
+use crate::value::BorrowedArrayBuffer;
+use crate::value::BorrowedDenseArray;
+use crate::value::BorrowedError;
+use crate::value::BorrowedHeapBuilder;
+use crate::value::BorrowedHeapValue;
+use crate::value::BorrowedMap;
+use crate::value::BorrowedObject;
+use crate::value::BorrowedPropertyKey;
+use crate::value::BorrowedRegExp;
+use crate::value::BorrowedSet;
+use crate::value::BorrowedSparseArray;
+use crate::value::Date;
+use crate::value::ErrorName;
+use crate::value::HostObject;
+use crate::value::SharedArrayBuffer;
+use crate::value::WasmMemory;
+use crate::value::WasmModule;
+use crate::BorrowedStringValue;
+use crate::BorrowedValue;
+use crate::HeapReference;
+use crate::ParseError;
+use crate::ParseErrorKind;
+use crate::ValueDeserializer;
+
+/// Receives the parts of a value as they are read by
+/// [ValueDeserializer::visit], without a [crate::Heap] being built.
+///
+/// Objects are reported by a `begin_` callback, followed by their contents and
+/// an `end_` callback. Objects that are stored in the heap are assigned object
+/// ids in the order in which their first callback is made, starting at 0.
+/// [ValueVisitor::object_reference] refers to objects by these ids. Every
+/// callback does nothing by default. An error returned from a callback stops
+/// reading.
+pub trait ValueVisitor<'a> {
+  fn undefined(&mut self) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn null(&mut self) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn bool(&mut self, _value: bool) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn int32(&mut self, _value: i32) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn uint32(&mut self, _value: u32) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn double(&mut self, _value: f64) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn bigint(&mut self, _value: BigInt) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn string(
+    &mut self,
+    _value: BorrowedStringValue<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// A reference to an object that was reported before, by its object id.
+  fn object_reference(&mut self, _id: u32) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// The start of an object. Its properties follow, each as a call to
+  /// [ValueVisitor::property_key] followed by the value.
+  fn begin_object(&mut self) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// The key of a property of an object or array.
+  fn property_key(
+    &mut self,
+    _key: BorrowedPropertyKey<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn end_object(&mut self, _num_properties: u32) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// The start of a dense array. Its elements follow, each either a value or
+  /// a [ValueVisitor::hole], and then its properties.
+  fn begin_dense_array(&mut self, _length: u32) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// A missing element of a dense array.
+  fn hole(&mut self) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn end_dense_array(
+    &mut self,
+    _num_properties: u32,
+    _length: u32,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// The start of a sparse array. Its elements and properties follow as
+  /// properties.
+  fn begin_sparse_array(&mut self, _length: u32) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn end_sparse_array(
+    &mut self,
+    _num_properties: u32,
+    _length: u32,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// The start of a map. Its entries follow as alternating keys and values.
+  fn begin_map(&mut self) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn end_map(&mut self, _num_entries: u32) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// The start of a set. Its values follow.
+  fn begin_set(&mut self) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn end_set(&mut self, _num_values: u32) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// The start of an error. Its cause follows, if it has one.
+  fn begin_error(&mut self) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn end_error(
+    &mut self,
+    _name: ErrorName,
+    _message: Option<BorrowedStringValue<'a>>,
+    _stack: Option<BorrowedStringValue<'a>>,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn date(&mut self, _date: Date) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn boolean_object(&mut self, _value: bool) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn number_object(&mut self, _value: f64) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn bigint_object(&mut self, _value: BigInt) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn string_object(
+    &mut self,
+    _value: BorrowedStringValue<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn regexp(
+    &mut self,
+    _regexp: BorrowedRegExp<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// An array buffer, which was either in the input or transferred.
+  fn array_buffer(
+    &mut self,
+    _array_buffer: BorrowedArrayBuffer<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn shared_array_buffer(
+    &mut self,
+    _sab: SharedArrayBuffer,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// A view of the array buffer or SharedArrayBuffer that was reported last,
+  /// either directly or by an object reference. The view takes the place of
+  /// the buffer in the containing object.
+  fn array_buffer_view(
+    &mut self,
+    _kind: ArrayBufferViewKind,
+    _byte_offset: u32,
+    _length: u32,
+    _is_length_tracking: bool,
+    _is_backed_by_rab: bool,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// An array buffer view that was written as a host object, with a copy of
+  /// the bytes that it covers (see [ValueDeserializer::set_node_compat]). It
+  /// is assigned a single object id, and its buffer none. By default, it is
+  /// reported as an array buffer followed by a view of all of it.
+  fn host_array_buffer_view(
+    &mut self,
+    kind: ArrayBufferViewKind,
+    buffer: BorrowedArrayBuffer<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    let length = buffer.byte_length() / kind.byte_width();
+    self.array_buffer(buffer)?;
+    self.array_buffer_view(kind, 0, length, false, false)
+  }
+
+  fn wasm_module(&mut self, _module: WasmModule) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  /// The start of a WebAssembly memory. Its buffer follows, either as a
+  /// SharedArrayBuffer or as a reference to one.
+  fn begin_wasm_memory(
+    &mut self,
+    _maximum_pages: i32,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn end_wasm_memory(&mut self) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn host_object(
+    &mut self,
+    _host_object: HostObject,
+  ) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+
+  fn shared_object(&mut self, _id: u32) -> Result<(), ParseErrorKind> {
+    Ok(())
+  }
+}
+
+/// What reading needs to know about an object that was assigned an object id:
+/// views may follow buffers, and WebAssembly memories must refer to a
+/// SharedArrayBuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ObjectKind {
+  /// An array buffer of the given byte length.
+  ArrayBuffer(u32),
+  /// A SharedArrayBuffer of the given byte length.
+  SharedArrayBuffer(u32),
+  /// A view that followed the object read before it.
+  ArrayBufferView,
+  Other,
+}
+
+impl ObjectKind {
+  /// The byte length of the object if it is a buffer that a view can follow.
+  pub(crate) fn buffer_byte_length(self) -> Option<u32> {
+    match self {
+      ObjectKind::ArrayBuffer(byte_length)
+      | ObjectKind::SharedArrayBuffer(byte_length) => Some(byte_length),
+      ObjectKind::ArrayBufferView | ObjectKind::Other => None,
+    }
+  }
+}
+
+/// What reading needs from a visitor beyond the [ValueVisitor] callbacks.
+/// Object references and lazy decoding need access to the deserializer and the
+/// input, which a [ValueVisitor] does not have.
+pub(crate) trait Visit<'a> {
+  fn visitor(&mut self) -> &mut dyn ValueVisitor<'a>;
+
+  /// Called when an object of the given kind, whose tag is at `offset`, is
+  /// assigned the next object id.
+  fn assign_id(&mut self, _kind: ObjectKind, _offset: usize) {}
+
+  /// Called when a host object, whose tag is at `offset`, is assigned the
+  /// next object id, but is the same object as the value that follows it,
+  /// which is assigned the id after.
+  fn alias_next_id(&mut self, offset: usize) {
+    self.assign_id(ObjectKind::Other, offset);
+  }
+
+  /// Report a reference to the object with the given id. Returns the kind of
+  /// the object.
+  fn object_reference(
+    &mut self,
+    de: &mut ValueDeserializer,
+    input: &mut Input<'a>,
+    id: u32,
+  ) -> Result<ObjectKind, ParseError>;
+
+  /// Called after [ValueVisitor::begin_dense_array] with the number of
+  /// elements to make room for up front.
+  fn reserve_elements(&mut self, _capacity: usize) {}
+
+  /// Called before a value is read. If the value was decoded already, skips
+  /// over it and reports it again, returning its byte length if it is an
+  /// array buffer.
+  fn reuse_decoded(
+    &mut self,
+    _de: &mut ValueDeserializer,
+    _input: &mut Input<'a>,
+  ) -> Result<Option<Option<u32>>, ParseError> {
+    Ok(None)
+  }
+
+  /// When salvaging, called in place of a value that could not be read.
+  fn placeholder(&mut self) {}
+}
+
+/// Adapts a [ValueVisitor] passed to [ValueDeserializer::visit]. Object ids
+/// are tracked here, to check object references and to know which referenced
+/// objects are buffers.
+pub(crate) struct ExternalVisitor<'v, V> {
+  visitor: &'v mut V,
+  /// The kind of the object with every object id.
+  objects: Vec<ObjectKind>,
+  /// The object ids that refer to the same object as the next id, in
+  /// ascending order. The visitor only sees the object once, so its ids are
+  /// shifted by the number of aliases before them.
+  aliases: Vec<u32>,
+}
+
+impl<'v, V> ExternalVisitor<'v, V> {
+  pub(crate) fn new(visitor: &'v mut V) -> Self {
+    Self {
+      visitor,
+      objects: vec![],
+      aliases: vec![],
+    }
+  }
+
+  /// The id that the visitor knows the object with the given id by.
+  fn visitor_id(&self, mut id: u32) -> u32 {
+    while self.aliases.binary_search(&id).is_ok() {
+      id += 1;
+    }
+    id - self.aliases.partition_point(|&alias| alias < id) as u32
+  }
+}
+
+impl<'a, V: ValueVisitor<'a>> Visit<'a> for ExternalVisitor<'_, V> {
+  fn visitor(&mut self) -> &mut dyn ValueVisitor<'a> {
+    self.visitor
+  }
+
+  fn assign_id(&mut self, kind: ObjectKind, _offset: usize) {
+    self.objects.push(kind);
+  }
+
+  fn alias_next_id(&mut self, _offset: usize) {
+    self.aliases.push(self.objects.len() as u32);
+    self.objects.push(ObjectKind::Other);
+  }
+
+  fn object_reference(
+    &mut self,
+    _de: &mut ValueDeserializer,
+    input: &mut Input<'a>,
+    id: u32,
+  ) -> Result<ObjectKind, ParseError> {
+    let Some(kind) = self.objects.get(id as usize).copied() else {
+      return Err(input.err(ParseErrorKind::InvalidObjectReference(id)));
+    };
+    emit(input, self.visitor.object_reference(self.visitor_id(id)))?;
+    Ok(kind)
+  }
+}
+
+/// An object whose contents are being added to the heap.
+enum Container<'a> {
+  Object {
+    reference: HeapReference,
+    properties: Vec<(BorrowedPropertyKey<'a>, BorrowedValue<'a>)>,
+    key: Option<BorrowedPropertyKey<'a>>,
+  },
+  SparseArray {
+    reference: HeapReference,
+    properties: Vec<(BorrowedPropertyKey<'a>, BorrowedValue<'a>)>,
+    key: Option<BorrowedPropertyKey<'a>>,
+  },
+  DenseArray {
+    reference: HeapReference,
+    elements: Vec<Option<BorrowedValue<'a>>>,
+    properties: Vec<(BorrowedPropertyKey<'a>, BorrowedValue<'a>)>,
+    key: Option<BorrowedPropertyKey<'a>>,
+  },
+  Map {
+    reference: HeapReference,
+    entries: Vec<(BorrowedValue<'a>, BorrowedValue<'a>)>,
+    /// The key of the entry whose value is reported next.
+    key: Option<BorrowedValue<'a>>,
+  },
+  Set {
+    reference: HeapReference,
+    values: Vec<BorrowedValue<'a>>,
+  },
+  Error {
+    reference: HeapReference,
+    cause: Option<BorrowedValue<'a>>,
+  },
+  WasmMemory {
+    reference: HeapReference,
+    maximum_pages: i32,
+    buffer: Option<BorrowedValue<'a>>,
+  },
+}
+
+/// Builds a value in a [HeapBuilder] from what is read.
+pub(crate) struct HeapVisitor<'h, 'a> {
+  heap: &'h mut BorrowedHeapBuilder<'a>,
+  stack: Vec<Container<'a>>,
+  /// The outermost value, once it is complete.
+  value: Option<BorrowedValue<'a>>,
+}
+
+impl<'h, 'a> HeapVisitor<'h, 'a> {
+  pub(crate) fn new(heap: &'h mut BorrowedHeapBuilder<'a>) -> Self {
+    Self {
+      heap,
+      stack: vec![],
+      value: None,
+    }
+  }
+
+  /// The value that was read. When salvaging, a value may not have been read
+  /// at all, in which case it is a placeholder.
+  pub(crate) fn finish(self) -> BorrowedValue<'a> {
+    self.value.unwrap_or_else(|| {
+      BorrowedValue::HeapReference(
+        self.heap.insert_without_id(BorrowedHeapValue::Placeholder),
+      )
+    })
+  }
+
+  /// Add a complete value to the object that is being read.
+  fn push(&mut self, value: BorrowedValue<'a>) {
+    match self.stack.last_mut() {
+      None => self.value = Some(value),
+      Some(
+        Container::Object {
+          properties, key, ..
+        }
+        | Container::SparseArray {
+          properties, key, ..
+        },
+      ) => {
+        if let Some(key) = key.take() {
+          properties.push((key, value));
+        }
+      }
+      Some(Container::DenseArray {
+        elements,
+        properties,
+        key,
+        ..
+      }) => match key.take() {
+        Some(key) => properties.push((key, value)),
+        None => elements.push(Some(value)),
+      },
+      Some(Container::Map { entries, key, .. }) => match key.take() {
+        Some(key) => entries.push((key, value)),
+        None => *key = Some(value),
+      },
+      Some(Container::Set { values, .. }) => values.push(value),
+      Some(Container::Error { cause, .. }) => *cause = Some(value),
+      Some(Container::WasmMemory { buffer, .. }) => *buffer = Some(value),
+    }
+  }
+
+  /// The value that was added last, which an ArrayBufferView replaces.
+  fn last_value(&mut self) -> Option<&mut BorrowedValue<'a>> {
+    match self.stack.last_mut() {
+      None => self.value.as_mut(),
+      Some(
+        Container::Object { properties, .. }
+        | Container::SparseArray { properties, .. },
+      ) => properties.last_mut().map(|(_, value)| value),
+      Some(Container::DenseArray {
+        elements,
+        properties,
+        ..
+      }) => match properties.last_mut() {
+        Some((_, value)) => Some(value),
+        None => elements.last_mut().and_then(Option::as_mut),
+      },
+      Some(Container::Map { entries, key, .. }) => match key {
+        Some(key) => Some(key),
+        None => entries.last_mut().map(|(_, value)| value),
+      },
+      Some(Container::Set { values, .. }) => values.last_mut(),
+      Some(Container::Error { cause, .. }) => cause.as_mut(),
+      Some(Container::WasmMemory { buffer, .. }) => buffer.as_mut(),
+    }
+  }
+
+  /// Add a value to the heap, and to the object that is being read.
+  fn insert(
+    &mut self,
+    heap_value: BorrowedHeapValue<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    let reference = self.heap.insert(heap_value);
+    self.push(BorrowedValue::HeapReference(reference));
+    Ok(())
+  }
+
+  fn begin(&mut self, container: impl FnOnce(HeapReference) -> Container<'a>) {
+    let reference = self.heap.reserve();
+    self.stack.push(container(reference));
+  }
+
+  /// Add the object that was read last to the heap.
+  fn end(&mut self) -> Result<(), ParseErrorKind> {
+    let container = self.stack.pop().expect("an object was begun");
+    let (reference, heap_value) = match container {
+      Container::Object {
+        reference,
+        properties,
+        ..
+      } => (
+        reference,
+        BorrowedHeapValue::Object(BorrowedObject { properties }),
+      ),
+      Container::SparseArray { .. } => unreachable!(),
+      Container::DenseArray {
+        reference,
+        elements,
+        properties,
+        ..
+      } => (
+        reference,
+        BorrowedHeapValue::DenseArray(BorrowedDenseArray {
+          elements,
+          properties,
+        }),
+      ),
+      Container::Map {
+        reference, entries, ..
+      } => (reference, BorrowedHeapValue::Map(BorrowedMap { entries })),
+      Container::Set { reference, values } => {
+        (reference, BorrowedHeapValue::Set(BorrowedSet { values }))
+      }
+      Container::Error { .. } | Container::WasmMemory { .. } => {
+        unreachable!()
+      }
+    };
+    self.heap.insert_reserved(reference, heap_value);
+    self.push(BorrowedValue::HeapReference(reference));
+    Ok(())
+  }
+
+  /// The kind of the object at `reference`.
+  fn object_kind(&self, reference: HeapReference) -> ObjectKind {
+    match self.heap.try_open(reference) {
+      Some(BorrowedHeapValue::ArrayBuffer(ab)) => {
+        ObjectKind::ArrayBuffer(ab.byte_length())
+      }
+      Some(BorrowedHeapValue::SharedArrayBuffer(sab)) => {
+        ObjectKind::SharedArrayBuffer(sab.byte_length())
+      }
+      Some(BorrowedHeapValue::ArrayBufferView(_)) => {
+        ObjectKind::ArrayBufferView
+      }
+      _ => ObjectKind::Other,
+    }
+  }
+}
+
+impl<'a> Visit<'a> for HeapVisitor<'_, 'a> {
+  fn visitor(&mut self) -> &mut dyn ValueVisitor<'a> {
+    self
+  }
+
+  fn reserve_elements(&mut self, capacity: usize) {
+    // The deserializer bounded the capacity by the size of the input.
+    if let Some(Container::DenseArray { elements, .. }) = self.stack.last_mut()
+    {
+      elements.reserve_exact(capacity);
+    }
+  }
+
+  fn object_reference(
+    &mut self,
+    de: &mut ValueDeserializer,
+    input: &mut Input<'a>,
+    id: u32,
+  ) -> Result<ObjectKind, ParseError> {
+    let reference = match self.heap.reference_by_id(id) {
+      Some(reference) => reference,
+      // When decoding lazily, the object may be in a part of the input that
+      // was skipped over.
+      None => read_object_by_id(de, input, self.heap, id)?
+        .ok_or_else(|| input.err(ParseErrorKind::InvalidObjectReference(id)))?,
+    };
+    self.push(BorrowedValue::HeapReference(reference));
+    Ok(self.object_kind(reference))
+  }
+
+  fn reuse_decoded(
+    &mut self,
+    de: &mut ValueDeserializer,
+    input: &mut Input<'a>,
+  ) -> Result<Option<Option<u32>>, ParseError> {
+    let Some(existing) = self.heap.existing_next() else {
+      return Ok(None);
+    };
+    // When decoding lazily, an object may have been decoded already because
+    // it was referenced from elsewhere. Skip over it, and reuse that one.
+    let cursor = input.cursor;
+    let mut next_id = self.heap.next_id();
+    skip_object_internal(de, input, &mut next_id)?;
+    if next_id == self.heap.next_id() {
+      input.cursor = cursor;
+      return Ok(None);
+    }
+    self.heap.set_next_id(next_id);
+    self.push(BorrowedValue::HeapReference(existing));
+    Ok(Some(self.object_kind(existing).buffer_byte_length()))
+  }
+
+  fn alias_next_id(&mut self, _offset: usize) {
+    self.heap.alias_next_id();
+  }
+
+  fn placeholder(&mut self) {
+    let reference = self.heap.insert_without_id(BorrowedHeapValue::Placeholder);
+    self.push(BorrowedValue::HeapReference(reference));
+  }
+}
+
+impl<'a> ValueVisitor<'a> for HeapVisitor<'_, 'a> {
+  fn undefined(&mut self) -> Result<(), ParseErrorKind> {
+    self.push(BorrowedValue::Undefined);
+    Ok(())
+  }
+
+  fn null(&mut self) -> Result<(), ParseErrorKind> {
+    self.push(BorrowedValue::Null);
+    Ok(())
+  }
+
+  fn bool(&mut self, value: bool) -> Result<(), ParseErrorKind> {
+    self.push(BorrowedValue::Bool(value));
+    Ok(())
+  }
+
+  fn int32(&mut self, value: i32) -> Result<(), ParseErrorKind> {
+    self.push(BorrowedValue::I32(value));
+    Ok(())
+  }
+
+  fn uint32(&mut self, value: u32) -> Result<(), ParseErrorKind> {
+    self.push(BorrowedValue::U32(value));
+    Ok(())
+  }
+
+  fn double(&mut self, value: f64) -> Result<(), ParseErrorKind> {
+    self.push(BorrowedValue::Double(value));
+    Ok(())
+  }
+
+  fn bigint(&mut self, value: BigInt) -> Result<(), ParseErrorKind> {
+    self.push(BorrowedValue::BigInt(value));
+    Ok(())
+  }
+
+  fn string(
+    &mut self,
+    value: BorrowedStringValue<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    self.push(BorrowedValue::String(value));
+    Ok(())
+  }
+
+  fn begin_object(&mut self) -> Result<(), ParseErrorKind> {
+    self.begin(|reference| Container::Object {
+      reference,
+      properties: vec![],
+      key: None,
+    });
+    Ok(())
+  }
+
+  fn property_key(
+    &mut self,
+    key: BorrowedPropertyKey<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    match self.stack.last_mut() {
+      Some(
+        Container::Object { key: pending, .. }
+        | Container::SparseArray { key: pending, .. }
+        | Container::DenseArray { key: pending, .. },
+      ) => *pending = Some(key),
+      _ => unreachable!(),
+    }
+    Ok(())
+  }
+
+  fn end_object(&mut self, _num_properties: u32) -> Result<(), ParseErrorKind> {
+    self.end()
+  }
+
+  fn begin_dense_array(&mut self, _length: u32) -> Result<(), ParseErrorKind> {
+    self.begin(|reference| Container::DenseArray {
+      reference,
+      elements: vec![],
+      properties: vec![],
+      key: None,
+    });
+    Ok(())
+  }
+
+  fn hole(&mut self) -> Result<(), ParseErrorKind> {
+    if let Some(Container::DenseArray { elements, .. }) = self.stack.last_mut()
+    {
+      elements.push(None);
+    }
+    Ok(())
+  }
+
+  fn end_dense_array(
+    &mut self,
+    _num_properties: u32,
+    _length: u32,
+  ) -> Result<(), ParseErrorKind> {
+    self.end()
+  }
+
+  fn begin_sparse_array(&mut self, _length: u32) -> Result<(), ParseErrorKind> {
+    self.begin(|reference| Container::SparseArray {
+      reference,
+      properties: vec![],
+      key: None,
+    });
+    Ok(())
+  }
+
+  fn end_sparse_array(
+    &mut self,
+    _num_properties: u32,
+    length: u32,
+  ) -> Result<(), ParseErrorKind> {
+    let Some(Container::SparseArray {
+      reference,
+      properties,
+      ..
+    }) = self.stack.pop()
+    else {
+      unreachable!();
+    };
+    let heap_value = BorrowedHeapValue::SparseArray(BorrowedSparseArray {
+      length,
+      properties,
+    });
+    self.heap.insert_reserved(reference, heap_value);
+    self.push(BorrowedValue::HeapReference(reference));
+    Ok(())
+  }
+
+  fn begin_map(&mut self) -> Result<(), ParseErrorKind> {
+    self.begin(|reference| Container::Map {
+      reference,
+      entries: vec![],
+      key: None,
+    });
+    Ok(())
+  }
+
+  fn end_map(&mut self, _num_entries: u32) -> Result<(), ParseErrorKind> {
+    self.end()
+  }
+
+  fn begin_set(&mut self) -> Result<(), ParseErrorKind> {
+    self.begin(|reference| Container::Set {
+      reference,
+      values: vec![],
+    });
+    Ok(())
+  }
+
+  fn end_set(&mut self, _num_values: u32) -> Result<(), ParseErrorKind> {
+    self.end()
+  }
+
+  fn begin_error(&mut self) -> Result<(), ParseErrorKind> {
+    self.begin(|reference| Container::Error {
+      reference,
+      cause: None,
+    });
+    Ok(())
+  }
+
+  fn end_error(
+    &mut self,
+    name: ErrorName,
+    message: Option<BorrowedStringValue<'a>>,
+    stack: Option<BorrowedStringValue<'a>>,
+  ) -> Result<(), ParseErrorKind> {
+    let Some(Container::Error { reference, cause }) = self.stack.pop() else {
+      unreachable!();
+    };
+    let error = BorrowedError {
+      name,
+      message,
+      stack,
+      cause,
+    };
+    self
+      .heap
+      .insert_reserved(reference, BorrowedHeapValue::Error(error));
+    self.push(BorrowedValue::HeapReference(reference));
+    Ok(())
+  }
+
+  fn date(&mut self, date: Date) -> Result<(), ParseErrorKind> {
+    self.insert(BorrowedHeapValue::Date(date))
+  }
+
+  fn boolean_object(&mut self, value: bool) -> Result<(), ParseErrorKind> {
+    self.insert(BorrowedHeapValue::BooleanObject(value))
+  }
+
+  fn number_object(&mut self, value: f64) -> Result<(), ParseErrorKind> {
+    self.insert(BorrowedHeapValue::NumberObject(value))
+  }
+
+  fn bigint_object(&mut self, value: BigInt) -> Result<(), ParseErrorKind> {
+    self.insert(BorrowedHeapValue::BigIntObject(value))
+  }
+
+  fn string_object(
+    &mut self,
+    value: BorrowedStringValue<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    self.insert(BorrowedHeapValue::StringObject(value))
+  }
+
+  fn regexp(
+    &mut self,
+    regexp: BorrowedRegExp<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    self.insert(BorrowedHeapValue::RegExp(regexp))
+  }
+
+  fn array_buffer(
+    &mut self,
+    array_buffer: BorrowedArrayBuffer<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    self.insert(BorrowedHeapValue::ArrayBuffer(array_buffer))
+  }
+
+  fn shared_array_buffer(
+    &mut self,
+    sab: SharedArrayBuffer,
+  ) -> Result<(), ParseErrorKind> {
+    self.insert(BorrowedHeapValue::SharedArrayBuffer(sab))
+  }
+
+  fn array_buffer_view(
+    &mut self,
+    kind: ArrayBufferViewKind,
+    byte_offset: u32,
+    length: u32,
+    is_length_tracking: bool,
+    is_backed_by_rab: bool,
+  ) -> Result<(), ParseErrorKind> {
+    let Some(BorrowedValue::HeapReference(buffer)) = self.last_value().cloned()
+    else {
+      unreachable!();
+    };
+    let reference = match self.heap.existing_next() {
+      Some(existing) => {
+        self.heap.set_next_id(self.heap.next_id() + 1);
+        existing
+      }
+      None => {
+        let view = ViewFields {
+          kind,
+          byte_offset,
+          length,
+          is_length_tracking,
+          is_backed_by_rab,
+        };
+        let heap_value =
+          BorrowedHeapValue::ArrayBufferView(view.into_view(buffer));
+        self.heap.insert(heap_value)
+      }
+    };
+    *self.last_value().unwrap() = BorrowedValue::HeapReference(reference);
+    Ok(())
+  }
+
+  fn host_array_buffer_view(
+    &mut self,
+    kind: ArrayBufferViewKind,
+    buffer: BorrowedArrayBuffer<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    let length = buffer.byte_length() / kind.byte_width();
+    let buffer = self
+      .heap
+      .insert_without_id(BorrowedHeapValue::ArrayBuffer(buffer));
+    let view = ViewFields {
+      kind,
+      byte_offset: 0,
+      length,
+      is_length_tracking: false,
+      is_backed_by_rab: false,
+    };
+    self.insert(BorrowedHeapValue::ArrayBufferView(view.into_view(buffer)))
+  }
+
+  fn wasm_module(&mut self, module: WasmModule) -> Result<(), ParseErrorKind> {
+    self.insert(BorrowedHeapValue::WasmModule(module))
+  }
+
+  fn begin_wasm_memory(
+    &mut self,
+    maximum_pages: i32,
+  ) -> Result<(), ParseErrorKind> {
+    self.begin(|reference| Container::WasmMemory {
+      reference,
+      maximum_pages,
+      buffer: None,
+    });
+    Ok(())
+  }
+
+  fn end_wasm_memory(&mut self) -> Result<(), ParseErrorKind> {
+    let Some(Container::WasmMemory {
+      reference,
+      maximum_pages,
+      buffer,
+    }) = self.stack.pop()
+    else {
+      unreachable!();
+    };
+    let buffer = match buffer {
+      Some(BorrowedValue::HeapReference(buffer)) => buffer,
+      // When salvaging, the memory may be closed off before its buffer was
+      // read.
+      _ => self.heap.insert_without_id(BorrowedHeapValue::Placeholder),
+    };
+    let memory = WasmMemory {
+      maximum_pages,
+      buffer,
+    };
+    self
+      .heap
+      .insert_reserved(reference, BorrowedHeapValue::WasmMemory(memory));
+    self.push(BorrowedValue::HeapReference(reference));
+    Ok(())
+  }
+
+  fn host_object(
+    &mut self,
+    host_object: HostObject,
+  ) -> Result<(), ParseErrorKind> {
+    self.insert(BorrowedHeapValue::HostObject(host_object))
+  }
+
+  fn shared_object(&mut self, id: u32) -> Result<(), ParseErrorKind> {
+    self.insert(BorrowedHeapValue::SharedObject(id))
+  }
+}
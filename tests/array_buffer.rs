@@ -0,0 +1,197 @@
+use v8_valueserializer::ArrayBuffer;
+use v8_valueserializer::ArrayBufferError;
+use v8_valueserializer::ArrayBufferView;
+use v8_valueserializer::ArrayBufferViewKind;
+use v8_valueserializer::Date;
+use v8_valueserializer::DenseArray;
+use v8_valueserializer::HeapBuilder;
+use v8_valueserializer::HeapValue;
+use v8_valueserializer::SerializationError;
+use v8_valueserializer::Value;
+use v8_valueserializer::ValueDeserializer;
+use v8_valueserializer::ValueSerializer;
+
+#[test]
+fn from_typed_slices() {
+  let ab = ArrayBuffer::from_u16_slice(&[1, 0x0203]).unwrap();
+  assert_eq!(ab.as_u8_slice(), [1, 0, 3, 2]);
+  let ab = ArrayBuffer::from_f64_slice(&[1.5]).unwrap();
+  assert_eq!(ab.as_u8_slice(), 1.5f64.to_ne_bytes());
+  let ab = ArrayBuffer::try_from(vec![1, 2, 3]).unwrap();
+  assert_eq!(ab.as_u8_slice(), [1, 2, 3]);
+  assert_eq!(ab.max_byte_length, None);
+}
+
+#[test]
+fn to_f16_vec() {
+  // 1, -2.5, Infinity, 2 ** -24 and -0 as half precision floats
+  let ab =
+    ArrayBuffer::from_u16_slice(&[0x3C00, 0xC100, 0x7C00, 0x0001, 0x8000])
+      .unwrap();
+  let values = ab.to_f16_vec();
+  assert_eq!(values, [1.0, -2.5, f32::INFINITY, 2f32.powi(-24), -0.0]);
+  assert!(values[4].is_sign_negative());
+}
+
+#[test]
+fn mutate_and_resize() {
+  let err = ArrayBuffer::new_resizable(8, 4).unwrap_err();
+  assert!(matches!(err, ArrayBufferError::ExceedsMaxByteLength { .. }));
+
+  let mut ab = ArrayBuffer::new_resizable(4, 16).unwrap();
+  ab.as_i32_slice_mut()[0] = -1;
+  ab.resize(8).unwrap();
+  assert_eq!(ab.as_i32_slice(), [-1, 0]);
+  ab.resize(2).unwrap();
+  assert_eq!(ab.as_u8_slice(), [0xFF, 0xFF]);
+  let err = ab.resize(17).unwrap_err();
+  assert!(matches!(err, ArrayBufferError::ExceedsMaxByteLength { .. }));
+
+  let mut ab = ArrayBuffer::new(4);
+  assert!(matches!(ab.resize(2), Err(ArrayBufferError::NotResizable)));
+}
+
+#[test]
+fn empty() {
+  let ab = ArrayBuffer::new(0);
+  assert_eq!(ab.byte_length(), 0);
+  assert_eq!(ab.as_f64_slice(), []);
+  assert_eq!(ab.clone().as_u8_slice(), []);
+
+  let mut ab = ArrayBuffer::new_resizable(0, 8).unwrap();
+  ab.resize(8).unwrap();
+  ab.as_u8_slice_mut()[7] = 1;
+  ab.resize(0).unwrap();
+  assert_eq!(ab.as_u64_slice(), []);
+  ab.resize(1).unwrap();
+  assert_eq!(ab.as_u8_slice(), [0]);
+}
+
+#[test]
+fn serialize_and_transfer() {
+  let mut heap = HeapBuilder::default();
+  let ab = ArrayBuffer::from_u8_slice(&[1, 2, 3]).unwrap();
+  let reference = heap.insert(HeapValue::ArrayBuffer(ab));
+  let heap = heap.build().unwrap();
+  let bytes = ValueSerializer::default()
+    .finish(&heap, &Value::HeapReference(reference))
+    .unwrap();
+  assert_eq!(bytes, [0xFF, 0x0F, b'B', 0x03, 0x01, 0x02, 0x03]);
+
+  let mut de = ValueDeserializer::default();
+  de.transfer_array_buffer(0, ArrayBuffer::from_u8_slice(&[4, 5]).unwrap());
+  let (value, heap) = de.read(&[0xFF, 0x0F, b't', 0x00]).unwrap();
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::ArrayBuffer(ab) = reference.open(&heap) else {
+    panic!("expected an array buffer");
+  };
+  assert_eq!(ab.as_u8_slice(), [4, 5]);
+}
+
+#[test]
+fn transfer_from_serializer() {
+  // [new Uint8Array(buffer), buffer]
+  let mut heap = HeapBuilder::default();
+  let array = heap.reserve();
+  let ab = ArrayBuffer::from_u8_slice(&[1, 2]).unwrap();
+  let buffer = heap.insert(HeapValue::ArrayBuffer(ab));
+  let view = heap.insert(HeapValue::ArrayBufferView(ArrayBufferView {
+    kind: ArrayBufferViewKind::Uint8Array,
+    buffer,
+    byte_offset: 0,
+    length: 2,
+    is_length_tracking: false,
+    is_backed_by_rab: false,
+  }));
+  heap.insert_reserved(
+    array,
+    HeapValue::DenseArray(DenseArray {
+      elements: vec![
+        Some(Value::HeapReference(view)),
+        Some(Value::HeapReference(buffer)),
+      ],
+      properties: vec![],
+    }),
+  );
+  let mut heap = heap.build().unwrap();
+  let value = Value::HeapReference(array);
+
+  let mut ser = ValueSerializer::default();
+  ser.transfer_array_buffer(7, buffer);
+  let (bytes, mut transferred) =
+    ser.finish_transferring(&mut heap, &value).unwrap();
+  assert_eq!(
+    bytes,
+    [
+      0xFF, 0x0F, b'A', 0x02, b't', 0x07, b'V', b'B', 0x00, 0x02, 0x00, b'^',
+      0x01, b'$', 0x00, 0x02
+    ]
+  );
+  let HeapValue::ArrayBuffer(detached) = buffer.open(&heap) else {
+    panic!("expected an array buffer");
+  };
+  assert_eq!(detached.byte_length(), 0);
+
+  let mut de = ValueDeserializer::default();
+  de.transfer_array_buffer(7, transferred.remove(&7).unwrap());
+  let (value, heap) = de.read(&bytes).unwrap();
+  assert_eq!(
+    v8_valueserializer::display(
+      &heap,
+      &value,
+      v8_valueserializer::DisplayOptions {
+        format: v8_valueserializer::DisplayFormat::Eval,
+      }
+    ),
+    "const v0 = new Uint8Array([\n  0x01, 0x02,\n]);\n[\n  v0,\n  v0.buffer,\n]"
+  );
+}
+
+#[test]
+fn transfer_detaches_views() {
+  // new Float64Array(new ArrayBuffer(16, { maxByteLength: 32 }), 8, 1)
+  let mut heap = HeapBuilder::default();
+  let ab = ArrayBuffer::new_resizable(16, 32).unwrap();
+  let buffer = heap.insert(HeapValue::ArrayBuffer(ab));
+  let view = heap.insert(HeapValue::ArrayBufferView(ArrayBufferView {
+    kind: ArrayBufferViewKind::Float64Array,
+    buffer,
+    byte_offset: 8,
+    length: 1,
+    is_length_tracking: false,
+    is_backed_by_rab: true,
+  }));
+  let mut heap = heap.build().unwrap();
+  let value = Value::HeapReference(view);
+
+  let mut ser = ValueSerializer::default();
+  ser.transfer_array_buffer(0, buffer);
+  ser.finish_transferring(&mut heap, &value).unwrap();
+  let HeapValue::ArrayBuffer(detached) = buffer.open(&heap) else {
+    panic!("expected an array buffer");
+  };
+  assert_eq!(detached.byte_length(), 0);
+  assert_eq!(detached.max_byte_length, Some(32));
+  let HeapValue::ArrayBufferView(detached_view) = view.open(&heap) else {
+    panic!("expected an array buffer view");
+  };
+  assert_eq!(detached_view.byte_offset, 0);
+  assert_eq!(detached_view.length, 0);
+
+  // The detached heap is still consistent, so it can be written and read.
+  let bytes = ValueSerializer::default().finish(&heap, &value).unwrap();
+  ValueDeserializer::default().read(&bytes).unwrap();
+}
+
+#[test]
+fn transfer_not_array_buffer() {
+  let mut heap = HeapBuilder::default();
+  let date = heap.insert(HeapValue::Date(Date::new(0.0)));
+  let heap = heap.build().unwrap();
+  let mut ser = ValueSerializer::default();
+  ser.transfer_array_buffer(0, date);
+  let err = ser.finish(&heap, &Value::HeapReference(date)).unwrap_err();
+  assert!(matches!(err, SerializationError::TransferNotArrayBuffer));
+}
@@ -0,0 +1,223 @@
+use v8_valueserializer::BlinkDeserializer;
+use v8_valueserializer::BlinkEnvelope;
+use v8_valueserializer::BlinkFile;
+use v8_valueserializer::BlinkHostObject;
+use v8_valueserializer::BlinkImage;
+use v8_valueserializer::Heap;
+use v8_valueserializer::HeapValue;
+use v8_valueserializer::ParseErrorKind;
+use v8_valueserializer::Value;
+use v8_valueserializer::ValueDeserializer;
+
+/// The Blink version 21 envelope, without a trailer.
+const ENVELOPE: [u8; 15] = [
+  0xFF, 0x15, 0xFE, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+  0x00, 0x00,
+];
+
+fn string(bytes: &mut Vec<u8>, string: &str) {
+  bytes.push(string.len() as u8);
+  bytes.extend_from_slice(string.as_bytes());
+}
+
+fn doubles(bytes: &mut Vec<u8>, doubles: &[f64]) {
+  for double in doubles {
+    bytes.extend_from_slice(&double.to_le_bytes());
+  }
+}
+
+fn host_objects(heap: &Heap, value: &Value) -> Vec<BlinkHostObject> {
+  let Value::HeapReference(reference) = value else {
+    panic!("expected an array");
+  };
+  let HeapValue::DenseArray(array) = reference.open(heap) else {
+    panic!("expected an array");
+  };
+  array
+    .elements
+    .iter()
+    .map(|element| {
+      let Some(Value::HeapReference(reference)) = element else {
+        panic!("expected a host object");
+      };
+      let HeapValue::HostObject(host_object) = reference.open(heap) else {
+        panic!("expected a host object");
+      };
+      host_object
+        .downcast_ref::<BlinkHostObject>()
+        .unwrap()
+        .clone()
+    })
+    .collect()
+}
+
+#[test]
+fn read_host_objects() {
+  // [new DOMPointReadOnly(1, 2, 3, 4), blob, port, new DOMException("m")]
+  let mut bytes = ENVELOPE.to_vec();
+  bytes.extend_from_slice(&[0xFF, 0x0F, b'A', 0x04, b'\\', b'W']);
+  doubles(&mut bytes, &[1.0, 2.0, 3.0, 4.0]);
+  bytes.extend_from_slice(&[b'\\', b'b']);
+  string(&mut bytes, "0b5e");
+  string(&mut bytes, "text/plain");
+  bytes.push(0x05);
+  bytes.extend_from_slice(&[b'\\', b'M', 0x01, b'\\', b'x']);
+  string(&mut bytes, "Error");
+  string(&mut bytes, "m");
+  string(&mut bytes, "");
+  bytes.extend_from_slice(&[b'$', 0x00, 0x04]);
+
+  let (value, heap) = BlinkDeserializer::new(ValueDeserializer::default())
+    .read(&bytes)
+    .unwrap();
+  assert_eq!(
+    host_objects(&heap, &value),
+    [
+      BlinkHostObject::DomPoint {
+        x: 1.0,
+        y: 2.0,
+        z: 3.0,
+        w: 4.0,
+        read_only: true,
+      },
+      BlinkHostObject::Blob {
+        uuid: "0b5e".to_string(),
+        content_type: "text/plain".to_string(),
+        size: 5,
+      },
+      BlinkHostObject::MessagePort(1),
+      BlinkHostObject::DomException {
+        name: "Error".to_string(),
+        message: "m".to_string(),
+      },
+    ]
+  );
+  assert_eq!(
+    v8_valueserializer::display(
+      &heap,
+      &value,
+      v8_valueserializer::DisplayOptions {
+        format: v8_valueserializer::DisplayFormat::Repl,
+      }
+    ),
+    r#"[
+  new DOMPointReadOnly(1, 2, 3, 4),
+  undefined /* Blink host object */,
+  undefined /* Blink host object */,
+  new DOMException("m", "Error"),
+]"#
+  );
+}
+
+#[test]
+fn read_file_in_older_version() {
+  // Version 17 has an envelope without a trailer offset.
+  let mut bytes = vec![0xFF, 0x11, 0xFF, 0x0F, b'A', 0x01, b'\\', b'f'];
+  string(&mut bytes, "");
+  string(&mut bytes, "a.txt");
+  string(&mut bytes, "");
+  string(&mut bytes, "f11e");
+  string(&mut bytes, "text/plain");
+  bytes.extend_from_slice(&[0x01, 0x03]);
+  doubles(&mut bytes, &[1000.0]);
+  bytes.extend_from_slice(&[0x01, b'$', 0x00, 0x01]);
+
+  let envelope = BlinkEnvelope::read(&bytes).unwrap();
+  assert_eq!(
+    envelope,
+    BlinkEnvelope {
+      version: 17,
+      length: 2,
+      trailer: None,
+    }
+  );
+  let (value, heap) = BlinkDeserializer::new(ValueDeserializer::default())
+    .read(&bytes)
+    .unwrap();
+  assert_eq!(
+    host_objects(&heap, &value),
+    [BlinkHostObject::File(BlinkFile {
+      path: String::new(),
+      name: "a.txt".to_string(),
+      relative_path: String::new(),
+      uuid: "f11e".to_string(),
+      content_type: "text/plain".to_string(),
+      snapshot: Some((3, 1000.0)),
+      is_user_visible: true,
+    })]
+  );
+}
+
+#[test]
+fn read_images_in_older_version() {
+  // Before version 18, only ImageBitmaps have the origin-clean and
+  // premultiplied flags, and they are written without tags.
+  let bytes = [
+    0xFF, 0x11, 0xFF, 0x0F, b'A', 0x02, b'\\', b'#', 0x01, 0x01, 0x04, 0x01,
+    0x02, 0x03, 0x04, b'\\', b'g', 0x01, 0x00, 0x01, 0x01, 0x04, 0x05, 0x06,
+    0x07, 0x08, b'$', 0x00, 0x02,
+  ];
+  let (value, heap) = BlinkDeserializer::new(ValueDeserializer::default())
+    .read(&bytes)
+    .unwrap();
+  assert_eq!(
+    host_objects(&heap, &value),
+    [
+      BlinkHostObject::ImageData(BlinkImage {
+        settings: vec![],
+        parametric_color_space: None,
+        width: 1,
+        height: 1,
+        pixels: vec![1, 2, 3, 4],
+      }),
+      BlinkHostObject::ImageBitmap(BlinkImage {
+        settings: vec![(4, 1), (5, 0)],
+        parametric_color_space: None,
+        width: 1,
+        height: 1,
+        pixels: vec![5, 6, 7, 8],
+      }),
+    ]
+  );
+}
+
+#[test]
+fn trailer_is_not_read_as_value() {
+  // 1, followed by a trailer that requires no interfaces.
+  let mut bytes = vec![0xFF, 0x15, 0xFE];
+  bytes.extend_from_slice(&19u64.to_be_bytes());
+  bytes.extend_from_slice(&5u32.to_be_bytes());
+  bytes.extend_from_slice(&[0xFF, 0x0F, b'I', 0x02]);
+  bytes.extend_from_slice(&[0xA0, 0x00, 0x00, 0x00, 0x00]);
+
+  let envelope = BlinkEnvelope::read(&bytes).unwrap();
+  assert_eq!(envelope.length, 15);
+  assert_eq!(envelope.trailer, Some((19, 5)));
+  let de = ValueDeserializer::with_options(
+    v8_valueserializer::DeserializerOptions::default().strict(true),
+  );
+  let (value, _) = BlinkDeserializer::new(de).read(&bytes).unwrap();
+  assert!(matches!(value, Value::I32(1)));
+}
+
+#[test]
+fn invalid_trailer_offset() {
+  let mut bytes = vec![0xFF, 0x15, 0xFE];
+  bytes.extend_from_slice(&100u64.to_be_bytes());
+  bytes.extend_from_slice(&5u32.to_be_bytes());
+  bytes.extend_from_slice(&[0xFF, 0x0F, b'I', 0x02]);
+  let err = BlinkEnvelope::read(&bytes).unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::InvalidBlinkEnvelope(_)));
+}
+
+#[test]
+fn unsupported_host_object() {
+  // A MediaStreamTrack.
+  let mut bytes = ENVELOPE.to_vec();
+  bytes.extend_from_slice(&[0xFF, 0x0F, b'\\', b's']);
+  let err = BlinkDeserializer::new(ValueDeserializer::default())
+    .read(&bytes)
+    .unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::InvalidHostObject(_)));
+  assert_eq!(err.position(), 18);
+}
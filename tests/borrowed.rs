@@ -0,0 +1,122 @@
+use std::ops::Range;
+
+use v8_valueserializer::value_eq;
+use v8_valueserializer::BorrowedHeapValue;
+use v8_valueserializer::BorrowedStringValue;
+use v8_valueserializer::BorrowedValue;
+use v8_valueserializer::Heap;
+use v8_valueserializer::HeapValue;
+use v8_valueserializer::Value;
+use v8_valueserializer::ValueDeserializer;
+
+// ["abc", "\u1234\u5678", new Uint8Array([1, 2, 3, 4]).buffer]
+const BYTES: &[u8] = &[
+  0xFF, 0x0F, b'A', 0x03, b'"', 0x03, b'a', b'b', b'c', 0x00, b'c', 0x04, 0x34,
+  0x12, 0x78, 0x56, b'B', 0x04, 0x01, 0x02, 0x03, 0x04, b'$', 0x00, 0x03,
+];
+/// The offset of the ArrayBuffer contents in [BYTES].
+const ARRAY_BUFFER_OFFSET: usize = 18;
+
+/// Copy [BYTES] into a buffer, such that the ArrayBuffer contents are placed
+/// at `misalignment` bytes past an 8 byte boundary.
+fn place(storage: &mut Vec<u8>, misalignment: usize) -> &[u8] {
+  storage.resize(BYTES.len() + 16, 0);
+  let base = storage.as_ptr() as usize + ARRAY_BUFFER_OFFSET;
+  let start = (8 - base % 8 + misalignment) % 8;
+  storage[start..start + BYTES.len()].copy_from_slice(BYTES);
+  &storage[start..start + BYTES.len()]
+}
+
+fn contains(range: &Range<usize>, bytes: &[u8]) -> bool {
+  let ptr = bytes.as_ptr() as usize;
+  range.contains(&ptr) && range.contains(&(ptr + bytes.len() - 1))
+}
+
+#[test]
+fn borrows_from_input() {
+  let mut storage = vec![];
+  let bytes = place(&mut storage, 0);
+  let range = bytes.as_ptr_range();
+  let range = range.start as usize..range.end as usize;
+
+  let (value, heap) =
+    ValueDeserializer::default().read_borrowed(bytes).unwrap();
+  let BorrowedValue::HeapReference(array) = &value else {
+    panic!("expected a heap reference");
+  };
+  let BorrowedHeapValue::DenseArray(array) = array.open(&heap) else {
+    panic!("expected a dense array");
+  };
+  let Some(BorrowedValue::String(BorrowedStringValue::OneByte(one_byte))) =
+    &array.elements[0]
+  else {
+    panic!("expected a one byte string");
+  };
+  assert!(contains(&range, one_byte.as_bytes()));
+  let Some(BorrowedValue::String(BorrowedStringValue::TwoByte(two_byte))) =
+    &array.elements[1]
+  else {
+    panic!("expected a two byte string");
+  };
+  assert!(contains(&range, two_byte.as_u8_bytes()));
+  let Some(BorrowedValue::HeapReference(buffer)) = &array.elements[2] else {
+    panic!("expected a heap reference");
+  };
+  let BorrowedHeapValue::ArrayBuffer(ab) = buffer.open(&heap) else {
+    panic!("expected an array buffer");
+  };
+  assert!(ab.is_borrowed());
+  assert!(contains(&range, ab.as_u8_slice()));
+
+  let (expected_value, expected_heap) =
+    ValueDeserializer::default().read(BYTES).unwrap();
+  let value = value.into_owned();
+  let heap = heap.into_owned();
+  drop(storage);
+  assert!(value_eq((&value, &heap), (&expected_value, &expected_heap)));
+}
+
+#[test]
+fn copies_unaligned_data() {
+  let mut storage = vec![];
+  let bytes = place(&mut storage, 1);
+  let (value, heap) =
+    ValueDeserializer::default().read_borrowed(bytes).unwrap();
+  let BorrowedValue::HeapReference(array) = &value else {
+    panic!("expected a heap reference");
+  };
+  let BorrowedHeapValue::DenseArray(array) = array.open(&heap) else {
+    panic!("expected a dense array");
+  };
+  let Some(BorrowedValue::String(two_byte)) = &array.elements[1] else {
+    panic!("expected a string");
+  };
+  assert_eq!(two_byte.to_string(), "\u{1234}\u{5678}");
+  let Some(BorrowedValue::HeapReference(buffer)) = &array.elements[2] else {
+    panic!("expected a heap reference");
+  };
+  let BorrowedHeapValue::ArrayBuffer(ab) = buffer.open(&heap) else {
+    panic!("expected an array buffer");
+  };
+  assert!(!ab.is_borrowed());
+  assert_eq!(ab.as_u32_slice(), [u32::from_ne_bytes([1, 2, 3, 4])]);
+}
+
+/// Values that do not borrow from the input keep their lifetime-free names.
+struct Stored {
+  value: Value,
+  heap: Heap,
+}
+
+#[test]
+fn owned_names() {
+  let (value, heap) = ValueDeserializer::default().read(BYTES).unwrap();
+  let stored = Stored { value, heap };
+  let Value::HeapReference(array) = &stored.value else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::DenseArray(array) = array.open(&stored.heap) else {
+    panic!("expected a dense array");
+  };
+  assert_eq!(array.elements.len(), 3);
+}
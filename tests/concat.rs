@@ -0,0 +1,86 @@
+use v8_valueserializer::BorrowedValue;
+use v8_valueserializer::DeserializerOptions;
+use v8_valueserializer::ParseErrorKind;
+use v8_valueserializer::Value;
+use v8_valueserializer::ValueDeserializer;
+
+#[test]
+fn read_prefix() {
+  // 1, followed by other data.
+  let bytes = [0xFF, 0x0F, b'I', 0x02, 0xFF, 0x0F, b'I', 0x04];
+  let (value, _, length) =
+    ValueDeserializer::default().read_prefix(&bytes).unwrap();
+  assert!(matches!(value, Value::I32(1)));
+  assert_eq!(length, 4);
+}
+
+#[test]
+fn trailing_bytes() {
+  // 1, followed by a 0 byte.
+  let bytes = [0xFF, 0x0F, b'I', 0x02, 0x00];
+  let err = ValueDeserializer::default().read(&bytes).unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::ExpectedEof));
+  assert_eq!(err.position(), 4);
+  let options = DeserializerOptions::default().strict(true);
+  let err = ValueDeserializer::with_options(options)
+    .read(&bytes)
+    .unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::ExpectedEof));
+}
+
+#[test]
+fn read_concatenated() {
+  // 1, "a", [] in different versions.
+  let bytes = [
+    0xFF, 0x0F, b'I', 0x02, 0xFF, 0x0D, b'"', 0x01, b'a', 0xFF, 0x0F, b'A',
+    0x00, b'$', 0x00, 0x00,
+  ];
+  let mut values = ValueDeserializer::default().read_concatenated(&bytes);
+  let (value, _) = values.next().unwrap().unwrap();
+  assert!(matches!(value, BorrowedValue::I32(1)));
+  assert_eq!(values.position(), 4);
+  let (value, _) = values.next().unwrap().unwrap();
+  let BorrowedValue::String(string) = value else {
+    panic!("expected a string");
+  };
+  assert_eq!(string.to_string(), "a");
+  let (value, _) = values.next().unwrap().unwrap();
+  assert!(matches!(value, BorrowedValue::HeapReference(_)));
+  assert!(values.next().is_none());
+  assert_eq!(values.position(), bytes.len());
+}
+
+#[test]
+fn stops_at_error() {
+  // 1, followed by something that is not a header.
+  let bytes = [0xFF, 0x0F, b'I', 0x02, b'I', 0x02, 0xFF, 0x0F, b'I', 0x02];
+  let mut values = ValueDeserializer::default().read_concatenated(&bytes);
+  values.next().unwrap().unwrap();
+  let err = values.next().unwrap().unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::ExpectedTag(_, b'I')));
+  assert_eq!(err.position(), 4);
+  assert!(values.next().is_none());
+}
+
+#[test]
+fn empty() {
+  let mut values = ValueDeserializer::default().read_concatenated(&[]);
+  assert!(values.next().is_none());
+}
+
+#[test]
+fn strict_alignment_per_value() {
+  // undefined, then "a" as a two byte string. Its contents are aligned in its
+  // own value, but not in the whole input.
+  let bytes = [0xFF, 0x0F, b'_', 0xFF, 0x0F, b'c', 0x02, b'a', 0x00];
+  let options = DeserializerOptions::default().strict(true);
+  let mut values =
+    ValueDeserializer::with_options(options).read_concatenated(&bytes);
+  let (value, _) = values.next().unwrap().unwrap();
+  assert!(matches!(value, BorrowedValue::Undefined));
+  let (value, _) = values.next().unwrap().unwrap();
+  let BorrowedValue::String(string) = value else {
+    panic!("expected a string");
+  };
+  assert_eq!(string.to_string(), "a");
+}
@@ -0,0 +1,110 @@
+use v8_valueserializer::display;
+use v8_valueserializer::value_eq;
+use v8_valueserializer::BorrowedHeap;
+use v8_valueserializer::BorrowedHeapValue;
+use v8_valueserializer::BorrowedPropertyKey;
+use v8_valueserializer::BorrowedValue;
+use v8_valueserializer::DisplayFormat;
+use v8_valueserializer::DisplayOptions;
+use v8_valueserializer::HeapBuilder;
+use v8_valueserializer::HeapValue;
+use v8_valueserializer::Object;
+use v8_valueserializer::PropertyKey;
+use v8_valueserializer::StringValue;
+use v8_valueserializer::Value;
+use v8_valueserializer::ValueDeserializer;
+use v8_valueserializer::ValueSerializer;
+
+/// Far deeper than the native stack would allow when recursing per level.
+const DEPTH: usize = 100_000;
+
+/// The number of objects in the linked list `{ next: { next: ... null } }`.
+fn list_length(heap: &BorrowedHeap, value: &BorrowedValue) -> usize {
+  let mut length = 0;
+  let mut value = value;
+  while let BorrowedValue::HeapReference(reference) = value {
+    let BorrowedHeapValue::Object(object) = reference.open(heap) else {
+      panic!("expected an object");
+    };
+    let [(BorrowedPropertyKey::String(key), next)] = &object.properties[..]
+    else {
+      panic!("expected a single property");
+    };
+    assert_eq!(key.to_string(), "next");
+    value = next;
+    length += 1;
+  }
+  assert!(matches!(value, BorrowedValue::Null));
+  length
+}
+
+/// The serialized linked list `{ next: { next: ... null } }` of `length`
+/// objects.
+fn linked_list(length: usize) -> Vec<u8> {
+  let mut heap = HeapBuilder::default();
+  let mut value = Value::Null;
+  for _ in 0..length {
+    let key = PropertyKey::String(StringValue::new("next".to_string()));
+    let object = Object {
+      properties: vec![(key, value)],
+    };
+    value = Value::HeapReference(heap.insert(HeapValue::Object(object)));
+  }
+  let heap = heap.build().unwrap();
+  ValueSerializer::default().finish(&heap, &value).unwrap()
+}
+
+#[test]
+fn linked_list_round_trip() {
+  let bytes = linked_list(DEPTH);
+  let (value, heap) = ValueDeserializer::default().read(&bytes).unwrap();
+  assert_eq!(list_length(&heap, &value), DEPTH);
+
+  let lazy = ValueDeserializer::default().read_lazy(&bytes).unwrap();
+  let next = lazy.root().get("next").unwrap().unwrap();
+  let (value, heap) = next.read().unwrap();
+  assert_eq!(list_length(&heap, &value), DEPTH - 1);
+}
+
+#[test]
+fn nested_arrays() {
+  // [[[ ... [] ... ]]]
+  let mut bytes = vec![0xFF, 0x0F];
+  bytes.extend([b'A', 0x01].repeat(DEPTH));
+  bytes.extend([b'A', 0x00, b'$', 0x00, 0x00]);
+  bytes.extend([b'$', 0x00, 0x01].repeat(DEPTH));
+  let (value, heap) = ValueDeserializer::default().read(&bytes).unwrap();
+  assert_eq!(
+    ValueSerializer::default().finish(&heap, &value).unwrap(),
+    bytes
+  );
+}
+
+#[test]
+fn display_and_compare() {
+  let (value, heap) = ValueDeserializer::default()
+    .read(&linked_list(DEPTH))
+    .unwrap();
+  let code = display(
+    &heap,
+    &value,
+    DisplayOptions {
+      format: DisplayFormat::Eval,
+    },
+  );
+  // Deeply nested objects are assigned to variables instead of inlined.
+  assert!(code.starts_with("const v"));
+  let indent = code
+    .lines()
+    .map(|line| line.len() - line.trim_start().len());
+  assert!(indent.max().unwrap() < 200);
+
+  let (other_value, other_heap) = ValueDeserializer::default()
+    .read(&linked_list(DEPTH))
+    .unwrap();
+  assert!(value_eq((&value, &heap), (&other_value, &other_heap)));
+  let (shorter_value, shorter_heap) = ValueDeserializer::default()
+    .read(&linked_list(DEPTH - 1))
+    .unwrap();
+  assert!(!value_eq((&value, &heap), (&shorter_value, &shorter_heap)));
+}
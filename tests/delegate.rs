@@ -0,0 +1,368 @@
+use std::any::Any;
+
+use v8_valueserializer::HeapValue;
+use v8_valueserializer::HostObject;
+use v8_valueserializer::HostObjectPayload;
+use v8_valueserializer::HostObjectReader;
+use v8_valueserializer::HostObjectWriter;
+use v8_valueserializer::ParseError;
+use v8_valueserializer::ParseErrorKind;
+use v8_valueserializer::SerializationError;
+use v8_valueserializer::SharedArrayBuffer;
+use v8_valueserializer::Value;
+use v8_valueserializer::ValueDeserializer;
+use v8_valueserializer::ValueDeserializerDelegate;
+use v8_valueserializer::ValueSerializer;
+use v8_valueserializer::ValueSerializerDelegate;
+use v8_valueserializer::WasmModule;
+
+#[derive(Debug, PartialEq)]
+struct Handle {
+  id: u32,
+  generation: u64,
+  weight: f64,
+  name: Vec<u8>,
+}
+
+impl HostObjectPayload for Handle {
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+
+  fn payload_eq(&self, other: &dyn HostObjectPayload) -> bool {
+    other.as_any().downcast_ref::<Self>() == Some(self)
+  }
+}
+
+struct HandleDelegate;
+
+impl ValueDeserializerDelegate for HandleDelegate {
+  fn read_host_object(
+    &mut self,
+    reader: &mut HostObjectReader<'_, '_>,
+  ) -> Result<HostObject, ParseError> {
+    let id = reader.read_uint32()?;
+    let generation = reader.read_uint64()?;
+    let weight = reader.read_double()?;
+    let name_length = reader.read_uint32()?;
+    let name = reader.read_raw_bytes(name_length as usize)?.to_vec();
+    if name.is_empty() {
+      return Err(reader.error("handle name is empty"));
+    }
+    Ok(HostObject::new(Handle {
+      id,
+      generation,
+      weight,
+      name,
+    }))
+  }
+}
+
+impl ValueSerializerDelegate for HandleDelegate {
+  fn write_host_object(
+    &mut self,
+    writer: &mut HostObjectWriter<'_>,
+    host_object: &HostObject,
+  ) -> Result<(), SerializationError> {
+    let Some(handle) = host_object.downcast_ref::<Handle>() else {
+      return Err(SerializationError::InvalidHostObject(
+        "not a handle".to_string(),
+      ));
+    };
+    writer.write_uint32(handle.id);
+    writer.write_uint64(handle.generation);
+    writer.write_double(handle.weight);
+    writer.write_uint32(handle.name.len() as u32);
+    writer.write_raw_bytes(&handle.name);
+    Ok(())
+  }
+}
+
+fn host_object_bytes() -> Vec<u8> {
+  let mut bytes = vec![0xFF, 0x0F, b'\\', 0x2A];
+  // generation: u64::MAX as a varint
+  bytes.extend_from_slice(&[0xFF; 9]);
+  bytes.push(0x01);
+  bytes.extend_from_slice(&1.5f64.to_le_bytes());
+  bytes.extend_from_slice(&[0x03, b'f', b'o', b'o']);
+  bytes
+}
+
+#[test]
+fn host_object_without_delegate() {
+  let de = ValueDeserializer::default();
+  let err = de.read(&host_object_bytes()).unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::HostObjectNotSupported));
+}
+
+#[test]
+fn host_object_with_delegate() {
+  let mut de = ValueDeserializer::default();
+  de.set_delegate(Box::new(HandleDelegate));
+  let (value, heap) = de.read(&host_object_bytes()).unwrap();
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::HostObject(host_object) = reference.open(&heap) else {
+    panic!("expected a host object");
+  };
+  assert_eq!(
+    host_object.downcast_ref::<Handle>(),
+    Some(&Handle {
+      id: 42,
+      generation: u64::MAX,
+      weight: 1.5,
+      name: b"foo".to_vec(),
+    })
+  );
+  assert_eq!(
+    v8_valueserializer::display(
+      &heap,
+      &value,
+      v8_valueserializer::DisplayOptions {
+        format: v8_valueserializer::DisplayFormat::Repl,
+      }
+    ),
+    "undefined /* host object */"
+  );
+}
+
+#[test]
+fn host_object_back_reference() {
+  // [host, host]
+  let mut bytes = vec![0xFF, 0x0F, b'A', 0x02];
+  bytes.extend_from_slice(&host_object_bytes()[2..]);
+  bytes.extend_from_slice(&[b'^', 0x01, b'$', 0x00, 0x02]);
+  let mut de = ValueDeserializer::default();
+  de.set_delegate(Box::new(HandleDelegate));
+  let (value, heap) = de.read(&bytes).unwrap();
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::DenseArray(array) = reference.open(&heap) else {
+    panic!("expected a dense array");
+  };
+  let (Some(Value::HeapReference(a)), Some(Value::HeapReference(b))) =
+    (&array.elements[0], &array.elements[1])
+  else {
+    panic!("expected two heap references");
+  };
+  assert_eq!(a, b);
+  assert!(v8_valueserializer::value_eq(
+    (&value, &heap),
+    (&value, &heap)
+  ));
+}
+
+#[test]
+fn host_object_varint_overflow() {
+  // generation: a varint with bits beyond the 64th
+  let mut bytes = host_object_bytes();
+  bytes[13] = 0x02;
+  let mut de = ValueDeserializer::default();
+  de.set_delegate(Box::new(HandleDelegate));
+  let err = de.read(&bytes).unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::VarintOverflow));
+  assert!(err.to_string().starts_with("parse error at position 13:"));
+
+  // 2^32 as a varint
+  let bytes = [0xFF, 0x0F, b'I', 0x80, 0x80, 0x80, 0x80, 0x10];
+  let err = ValueDeserializer::default().read(&bytes).unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::VarintOverflow));
+}
+
+#[test]
+fn host_object_delegate_error() {
+  let mut bytes = host_object_bytes();
+  bytes.truncate(bytes.len() - 4);
+  bytes.push(0x00);
+  let mut de = ValueDeserializer::default();
+  de.set_delegate(Box::new(HandleDelegate));
+  let err = de.read(&bytes).unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::InvalidHostObject(_)));
+}
+
+#[test]
+fn host_object_roundtrip() {
+  let mut bytes = vec![0xFF, 0x0F, b'A', 0x02];
+  bytes.extend_from_slice(&host_object_bytes()[2..]);
+  bytes.extend_from_slice(&[b'^', 0x01, b'$', 0x00, 0x02]);
+  let mut de = ValueDeserializer::default();
+  de.set_delegate(Box::new(HandleDelegate));
+  let (value, heap) = de.read(&bytes).unwrap();
+
+  let ser = ValueSerializer::default();
+  let err = ser.finish(&heap, &value).unwrap_err();
+  assert!(matches!(err, SerializationError::HostObjectNotSupported));
+
+  let mut ser = ValueSerializer::default();
+  ser.set_delegate(Box::new(HandleDelegate));
+  let serialized = ser.finish(&heap, &value).unwrap();
+  assert_eq!(serialized, bytes);
+}
+
+/// Hands out ids for shared array buffers in the order they are encountered.
+#[derive(Default)]
+struct SharedArrayBufferIds(std::rc::Rc<std::cell::RefCell<Vec<u32>>>);
+
+impl ValueSerializerDelegate for SharedArrayBufferIds {
+  fn get_shared_array_buffer_id(
+    &mut self,
+    sab: &SharedArrayBuffer,
+  ) -> Result<u32, SerializationError> {
+    let mut ids = self.0.borrow_mut();
+    ids.push(sab.byte_length());
+    Ok(ids.len() as u32 + 6)
+  }
+}
+
+#[test]
+fn shared_array_buffer_missing() {
+  let de = ValueDeserializer::default();
+  let err = de.read(&[0xFF, 0x0F, b'u', 0x07]).unwrap_err();
+  assert!(matches!(
+    err.kind,
+    ParseErrorKind::SharedArrayBufferNotSupported
+  ));
+}
+
+#[test]
+fn shared_array_buffer_ids() {
+  // const sab = new SharedArrayBuffer(4);
+  // [new Uint8Array(sab), new Uint16Array(sab, 2, 1)]
+  let bytes = [
+    0xFF, 0x0F, b'A', 0x02, b'u', 0x07, b'V', b'B', 0x00, 0x04, 0x00, b'^',
+    0x01, b'V', b'W', 0x02, 0x02, 0x00, b'$', 0x00, 0x02,
+  ];
+  let sab = SharedArrayBuffer::new(&[1, 2, 3, 0]);
+  let mut de = ValueDeserializer::default();
+  de.transfer_shared_array_buffer(7, sab.clone());
+  let (value, heap) = de.read(&bytes).unwrap();
+
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::DenseArray(array) = reference.open(&heap) else {
+    panic!("expected a dense array");
+  };
+  let Some(Value::HeapReference(view)) = &array.elements[1] else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::ArrayBufferView(view) = view.open(&heap) else {
+    panic!("expected an array buffer view");
+  };
+  let HeapValue::SharedArrayBuffer(buffer) = view.buffer.open(&heap) else {
+    panic!("expected a shared array buffer");
+  };
+  assert!(buffer.ptr_eq(&sab));
+  assert_ne!(buffer, &SharedArrayBuffer::new(&[1, 2, 3, 0]));
+
+  let ids = SharedArrayBufferIds::default();
+  let recorded = ids.0.clone();
+  let mut ser = ValueSerializer::default();
+  ser.set_delegate(Box::new(ids));
+  assert_eq!(ser.finish(&heap, &value).unwrap(), bytes);
+  assert_eq!(*recorded.borrow(), vec![4]);
+}
+
+#[test]
+fn shared_array_buffer_without_delegate() {
+  let mut de = ValueDeserializer::default();
+  de.transfer_shared_array_buffer(0, SharedArrayBuffer::new(&[]));
+  let (value, heap) = de.read(&[0xFF, 0x0F, b'u', 0x00]).unwrap();
+  let err = ValueSerializer::default()
+    .finish(&heap, &value)
+    .unwrap_err();
+  assert!(matches!(
+    err,
+    SerializationError::SharedArrayBufferNotSupported
+  ));
+}
+
+#[test]
+fn wasm_memory_buffer_reference_to_array_buffer() {
+  // [new ArrayBuffer(0), <memory backed by the array buffer>]
+  let bytes = [
+    0xFF, 0x0F, b'A', 0x02, b'B', 0x00, b'm', 0x04, b'^', 0x01, b'$', 0x00,
+    0x02,
+  ];
+  let err = ValueDeserializer::default().read(&bytes).unwrap_err();
+  assert!(matches!(
+    err.kind,
+    ParseErrorKind::InvalidObjectReference(1)
+  ));
+}
+
+struct KnownModules(Vec<u32>);
+
+impl ValueDeserializerDelegate for KnownModules {
+  fn get_wasm_module_from_id(
+    &mut self,
+    transfer_id: u32,
+  ) -> Option<WasmModule> {
+    self
+      .0
+      .contains(&transfer_id)
+      .then_some(WasmModule { transfer_id })
+  }
+}
+
+// [module, module, module2]
+const WASM_MODULES: &[u8] = &[
+  0xFF, 0x0F, b'A', 0x03, b'w', 0x07, b'^', 0x01, b'w', 0x08, b'$', 0x00, 0x03,
+];
+
+#[test]
+fn wasm_module_without_delegate() {
+  let de = ValueDeserializer::default();
+  let err = de.read(WASM_MODULES).unwrap_err();
+  assert!(matches!(
+    err.kind,
+    ParseErrorKind::WasmModuleTransferNotSupported
+  ));
+}
+
+#[test]
+fn wasm_module_unknown_id() {
+  let mut de = ValueDeserializer::default();
+  de.set_delegate(Box::new(KnownModules(vec![7])));
+  let err = de.read(WASM_MODULES).unwrap_err();
+  assert!(matches!(
+    err.kind,
+    ParseErrorKind::WasmModuleTransferNotSupported
+  ));
+}
+
+#[test]
+fn wasm_module_roundtrip() {
+  let mut de = ValueDeserializer::default();
+  de.set_delegate(Box::new(KnownModules(vec![7, 8])));
+  let (value, heap) = de.read(WASM_MODULES).unwrap();
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::DenseArray(array) = reference.open(&heap) else {
+    panic!("expected a dense array");
+  };
+  let Some(Value::HeapReference(module)) = &array.elements[2] else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::WasmModule(module) = module.open(&heap) else {
+    panic!("expected a wasm module");
+  };
+  assert_eq!(module.transfer_id, 8);
+
+  assert_eq!(
+    v8_valueserializer::display(
+      &heap,
+      &value,
+      v8_valueserializer::DisplayOptions {
+        format: v8_valueserializer::DisplayFormat::Eval,
+      }
+    ),
+    "const v0 = undefined /* WebAssembly.Module 7 */;\n[\n  v0,\n  v0,\n  undefined /* WebAssembly.Module 8 */,\n]"
+  );
+
+  let serialized = ValueSerializer::default().finish(&heap, &value).unwrap();
+  assert_eq!(serialized, WASM_MODULES);
+}
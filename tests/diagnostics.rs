@@ -0,0 +1,86 @@
+use v8_valueserializer::ParseError;
+use v8_valueserializer::ValueDeserializer;
+
+fn read_err(bytes: &[u8]) -> ParseError {
+  ValueDeserializer::default().read(bytes).unwrap_err()
+}
+
+#[test]
+fn property_path() {
+  // { users: [0, 0, 0, { avatar: <unknown tag 0x01> }] }
+  let bytes = [
+    0xFF, 0x0F, b'o', b'"', 0x05, b'u', b's', b'e', b'r', b's', b'A', 0x04,
+    b'I', 0x00, b'I', 0x00, b'I', 0x00, b'o', b'"', 0x06, b'a', b'v', b'a',
+    b't', b'a', b'r', 0x01,
+  ];
+  let err = read_err(&bytes);
+  assert_eq!(err.position(), 27);
+  assert_eq!(
+    err.to_string(),
+    "parse error at position 27 in $.users[3].avatar: unexpected tag 1"
+  );
+}
+
+#[test]
+fn map_entry_path() {
+  // new Map([[1, 2], [<unknown tag 0x01>, ...
+  let bytes = [0xFF, 0x0F, b';', b'I', 0x02, b'I', 0x04, 0x01];
+  let err = read_err(&bytes);
+  assert_eq!(
+    err.to_string(),
+    "parse error at position 7 in $<map entry 1 key>: unexpected tag 1"
+  );
+}
+
+#[test]
+fn top_level_error_has_no_path() {
+  let err = read_err(&[0xFF, 0x0F, 0x01]);
+  assert!(err.path().is_empty());
+  assert_eq!(
+    err.to_string(),
+    "parse error at position 2: unexpected tag 1"
+  );
+}
+
+#[test]
+fn hexdump_context() {
+  let bytes = [0xFF, 0x0F, b'A', 0x02, b'I', 0x02, 0x01];
+  let err = read_err(&bytes);
+  assert_eq!(
+    err.with_context(&bytes).to_string(),
+    "parse error at position 6 in $[1]: unexpected tag 1\n\
+     \x20 00000000  ff  .  Version 15\n\
+     \x20 00000001  0f  .\n\
+     \x20 00000002  41  A  BeginDenseJsArray length=2 #0\n\
+     \x20 00000003  02  .\n\
+     \x20 00000004  49  I  Int32 1\n\
+     \x20 00000005  02  .\n\
+     > 00000006  01  .  unknown tag 0x01\n"
+  );
+
+  let err = read_err(&bytes[..6]);
+  assert!(err
+    .with_context(&bytes[..6])
+    .to_string()
+    .ends_with("> 00000006  end of input\n"));
+}
+
+#[test]
+fn hexdump_context_labels_tokens() {
+  // ["I", <unknown tag 0x01>]. The contents of the string are not labeled
+  // as a tag.
+  let bytes = [0xFF, 0x0F, b'A', 0x02, b'"', 0x01, b'I', 0x01];
+  let err = read_err(&bytes);
+  assert_eq!(
+    err.with_context(&bytes).to_string(),
+    "parse error at position 7 in $[1]: unexpected tag 1\n\
+     \x20 00000000  ff  .  Version 15\n\
+     \x20 00000001  0f  .\n\
+     \x20 00000002  41  A  BeginDenseJsArray length=2 #0\n\
+     \x20 00000003  02  .\n\
+     \x20 00000004  22  \"  OneByteString byteLength=1 \"I\"\n\
+     \x20 00000005  01  .\n\
+     \x20 00000006  49  I\n\
+     > 00000007  01  .  unknown tag 0x01\n"
+  );
+}
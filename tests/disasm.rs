@@ -0,0 +1,80 @@
+use v8_valueserializer::disassemble;
+
+#[test]
+fn nested_values() {
+  // { a: [, -123n], e: new TypeError("hi") }
+  let bytes = [
+    0xFF, 0x0F, b'o', b'"', 0x01, b'a', b'A', 0x02, b'-', b'Z', 0x11, 0x7B,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, b'$', 0x00, 0x02, b'"', 0x01,
+    b'e', b'r', b'T', b'm', b'"', 0x02, b'h', b'i', b'.', b'{', 0x02,
+  ];
+  let expected = "\
+00000000  ff 0f                       Version 15
+00000002  6f                          BeginJsObject #0
+00000003  22 01 61                      OneByteString byteLength=1 \"a\"
+00000006  41 02                         BeginDenseJsArray length=2 #1
+00000008  2d                              TheHole
+00000009  5a 11 7b 00 00 00 00 00 ..      \
+BigInt -123 bitfield=0x11 (negative, 8 bytes)
+00000013  24 00 02                      EndDenseJsArray numProperties=0 length=2
+00000016  22 01 65                      OneByteString byteLength=1 \"e\"
+00000019  72                            Error #2
+0000001a  54                              TypeErrorPrototype
+0000001b  6d                              Message
+0000001c  22 02 68 69                       OneByteString byteLength=2 \"hi\"
+00000020  2e                            End
+00000021  7b 02                       EndJsObject numProperties=2
+";
+  assert_eq!(disassemble(&bytes), expected);
+}
+
+#[test]
+fn buffer_and_view() {
+  let bytes = [
+    0xFF, 0x0F, 0x00, b'B', 0x02, 0x01, 0x02, b'V', b'B', 0x00, 0x02, 0x00,
+  ];
+  let expected = "\
+00000000  ff 0f                       Version 15
+00000002  00                          Padding
+00000003  42 02 01 02                 ArrayBuffer byteLength=2 #0
+00000007  56 42 00 02 00              \
+ArrayBufferView Uint8Array byteOffset=0 byteLength=2 flags=0x0 #1
+";
+  assert_eq!(disassemble(&bytes), expected);
+}
+
+#[test]
+fn continues_past_errors() {
+  let bytes = [
+    0xFF, 0x0F, b'A', 0x01, 0x99, b'^', 0x07, b'?', 0x01, b'"', 0x05, b'a',
+  ];
+  let expected = "\
+00000000  ff 0f                       Version 15
+00000002  41 01                       BeginDenseJsArray length=1 #0
+00000004  99                            unknown tag 0x99
+00000005  5e 07                         ObjectReference 7 (invalid)
+00000007  3f 01                         VerifyObjectCount 1
+0000000b                                error: unexpected end of file
+";
+  assert_eq!(disassemble(&bytes), expected);
+}
+
+#[test]
+fn deep_nesting() {
+  // [[[ ... [] ... ]]], 100 levels deep.
+  let mut bytes = vec![0xFF, 0x0F];
+  bytes.extend([b'A', 0x01].repeat(100));
+  bytes.extend([b'A', 0x00, b'$', 0x00, 0x00]);
+  bytes.extend([b'$', 0x00, 0x01].repeat(100));
+  let listing = disassemble(&bytes);
+  let line = listing.lines().nth(101).unwrap();
+  assert_eq!(
+    line,
+    format!(
+      "000000ca  41 00                       {:128}[depth 100] \
+       BeginDenseJsArray length=0 #100",
+      ""
+    )
+  );
+  assert!(listing.lines().all(|line| line.len() < 220));
+}
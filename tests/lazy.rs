@@ -0,0 +1,113 @@
+use v8_valueserializer::value_eq;
+use v8_valueserializer::BorrowedHeapValue;
+use v8_valueserializer::BorrowedValue;
+use v8_valueserializer::ParseErrorKind;
+use v8_valueserializer::SharedArrayBuffer;
+use v8_valueserializer::ValueDeserializer;
+
+#[test]
+fn nested_property() {
+  // { a: 1, user: { email: "x" } }
+  let bytes = [
+    0xFF, 0x0F, b'o', b'"', 0x01, b'a', b'I', 0x02, b'"', 0x04, b'u', b's',
+    b'e', b'r', b'o', b'"', 0x05, b'e', b'm', b'a', b'i', b'l', b'"', 0x01,
+    b'x', b'{', 0x01, b'{', 0x02,
+  ];
+  let de = ValueDeserializer::default();
+  let lazy = de.read_lazy(&bytes).unwrap();
+  let email = lazy.root().get("user").unwrap().unwrap();
+  let email = email.get("email").unwrap().unwrap();
+  let (value, _) = email.read().unwrap();
+  let BorrowedValue::String(email) = value else {
+    panic!("expected a string");
+  };
+  assert_eq!(email.to_string(), "x");
+  assert!(lazy.root().get("b").unwrap().is_none());
+  let a = lazy.root().get("a").unwrap().unwrap();
+  assert!(a.get("a").unwrap().is_none());
+}
+
+#[test]
+fn reference_into_skipped_value() {
+  // const o = { v: 1 }; [o, o]
+  let bytes = [
+    0xFF, 0x0F, b'A', 0x02, b'o', b'"', 0x01, b'v', b'I', 0x02, b'{', 0x01,
+    b'^', 0x01, b'$', 0x00, 0x02,
+  ];
+  let lazy = ValueDeserializer::default().read_lazy(&bytes).unwrap();
+  let second = lazy.root().index(1).unwrap().unwrap();
+  let (value, _) = second.get("v").unwrap().unwrap().read().unwrap();
+  assert!(matches!(value, BorrowedValue::I32(1)));
+
+  let (value, heap) = lazy.root().read().unwrap();
+  let (expected_value, expected_heap) =
+    ValueDeserializer::default().read(&bytes).unwrap();
+  assert!(value_eq((&value, &heap), (&expected_value, &expected_heap)));
+}
+
+#[test]
+fn reference_to_view_buffer() {
+  // const a = new Uint8Array([1, 2]); [a, a.buffer]
+  let bytes = [
+    0xFF, 0x0F, b'A', 0x02, b'B', 0x02, 0x01, 0x02, b'V', b'B', 0x00, 0x02,
+    0x00, b'^', 0x01, b'$', 0x00, 0x02,
+  ];
+  let lazy = ValueDeserializer::default().read_lazy(&bytes).unwrap();
+  let (value, heap) = lazy.root().index(1).unwrap().unwrap().read().unwrap();
+  let BorrowedValue::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let BorrowedHeapValue::ArrayBuffer(ab) = reference.open(&heap) else {
+    panic!("expected an array buffer");
+  };
+  assert_eq!(ab.as_u8_slice(), [1, 2]);
+
+  let (value, heap) = lazy.root().index(0).unwrap().unwrap().read().unwrap();
+  let BorrowedValue::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let BorrowedHeapValue::ArrayBufferView(view) = reference.open(&heap) else {
+    panic!("expected an array buffer view");
+  };
+  assert_eq!(view.length, 2);
+  assert!(matches!(
+    view.buffer.open(&heap),
+    BorrowedHeapValue::ArrayBuffer(_)
+  ));
+}
+
+#[test]
+fn legacy_version_unsupported() {
+  let err = ValueDeserializer::default()
+    .read_lazy(&[b'I', 0x02])
+    .err()
+    .unwrap();
+  assert!(matches!(
+    err.kind,
+    ParseErrorKind::UnsupportedLazyReadVersion(0)
+  ));
+}
+
+#[test]
+fn wasm_memory_buffer_reference() {
+  // const m = new WebAssembly.Memory({ initial: 0, maximum: 1, shared: true });
+  // [m.buffer, m]
+  let bytes = [
+    0xFF, 0x0F, b'A', 0x02, b'u', 0x00, b'm', 0x02, b'^', 0x01, b'$', 0x00,
+    0x02,
+  ];
+  let mut de = ValueDeserializer::default();
+  de.transfer_shared_array_buffer(0, SharedArrayBuffer::new(&[]));
+  let lazy = de.read_lazy(&bytes).unwrap();
+  let (value, heap) = lazy.root().index(1).unwrap().unwrap().read().unwrap();
+  let BorrowedValue::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let BorrowedHeapValue::WasmMemory(memory) = reference.open(&heap) else {
+    panic!("expected a wasm memory");
+  };
+  assert!(matches!(
+    memory.buffer.open(&heap),
+    BorrowedHeapValue::SharedArrayBuffer(_)
+  ));
+}
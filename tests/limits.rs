@@ -0,0 +1,128 @@
+use v8_valueserializer::DeserializerOptions;
+use v8_valueserializer::ParseErrorKind;
+use v8_valueserializer::ValueDeserializer;
+
+fn read_err(options: DeserializerOptions, bytes: &[u8]) -> ParseErrorKind {
+  ValueDeserializer::with_options(options)
+    .read(bytes)
+    .unwrap_err()
+    .kind
+}
+
+#[test]
+fn max_depth() {
+  // [[[[]]]]
+  let bytes = [
+    0xFF, 0x0F, b'A', 0x01, b'A', 0x01, b'A', 0x01, b'A', 0x00, b'$', 0x00,
+    0x00, b'$', 0x00, 0x01, b'$', 0x00, 0x01, b'$', 0x00, 0x01,
+  ];
+  ValueDeserializer::default().read(&bytes).unwrap();
+  let err = read_err(DeserializerOptions::default().max_depth(2), &bytes);
+  assert!(matches!(err, ParseErrorKind::TooDeeplyNested));
+}
+
+#[test]
+fn max_string_length() {
+  let bytes = [0xFF, 0x0F, b'"', 0x05, b'h', b'e', b'l', b'l', b'o'];
+  let options = DeserializerOptions::default().max_string_length(5);
+  ValueDeserializer::with_options(options)
+    .read(&bytes)
+    .unwrap();
+  let err =
+    read_err(DeserializerOptions::default().max_string_length(4), &bytes);
+  assert!(matches!(
+    err,
+    ParseErrorKind::StringLengthLimitExceeded {
+      byte_length: 5,
+      limit: 4
+    }
+  ));
+}
+
+#[test]
+fn max_allocation() {
+  let bytes = [0xFF, 0x0F, b'"', 0x05, b'h', b'e', b'l', b'l', b'o'];
+  let err = read_err(DeserializerOptions::default().max_allocation(4), &bytes);
+  assert!(matches!(
+    err,
+    ParseErrorKind::AllocationLimitExceeded { limit: 4 }
+  ));
+}
+
+/// The smallest allocation limit with which `bytes` can be read.
+fn min_allocation(bytes: &[u8]) -> usize {
+  (0..)
+    .find(|&limit| {
+      let options = DeserializerOptions::default().max_allocation(limit);
+      ValueDeserializer::with_options(options).read(bytes).is_ok()
+    })
+    .unwrap()
+}
+
+#[test]
+fn max_allocation_counts_contents() {
+  // 0n and 1n
+  let empty = [0xFF, 0x0F, b'Z', 0x00];
+  let bigint = [0xFF, 0x0F, b'Z', 0x10, 0x01, 0, 0, 0, 0, 0, 0, 0];
+  assert_eq!(min_allocation(&bigint), min_allocation(&empty) + 8);
+  // {} and {1: 1}
+  let empty = [0xFF, 0x0F, b'o', b'{', 0x00];
+  let object = [0xFF, 0x0F, b'o', b'I', 0x02, b'I', 0x02, b'{', 0x01];
+  assert!(min_allocation(&object) > min_allocation(&empty));
+  // new Map() and new Map([[1, 1]])
+  let empty = [0xFF, 0x0F, b';', b':', 0x00];
+  let map = [0xFF, 0x0F, b';', b'I', 0x02, b'I', 0x02, b':', 0x02];
+  assert!(min_allocation(&map) > min_allocation(&empty));
+  // new Set() and new Set([1])
+  let empty = [0xFF, 0x0F, b'\'', b',', 0x00];
+  let set = [0xFF, 0x0F, b'\'', b'I', 0x02, b',', 0x01];
+  assert!(min_allocation(&set) > min_allocation(&empty));
+}
+
+#[test]
+fn max_array_buffer_length() {
+  let bytes = [0xFF, 0x0F, b'B', 0x04, 0x01, 0x02, 0x03, 0x04];
+  let options = DeserializerOptions::default().max_array_buffer_length(3);
+  let err = read_err(options, &bytes);
+  assert!(matches!(
+    err,
+    ParseErrorKind::ArrayBufferLengthLimitExceeded {
+      byte_length: 4,
+      limit: 3
+    }
+  ));
+}
+
+#[test]
+fn max_array_length() {
+  // A dense array that claims to have u32::MAX elements.
+  let bytes = [0xFF, 0x0F, b'A', 0xFF, 0xFF, 0xFF, 0xFF, 0x0F];
+  let options = DeserializerOptions::default().max_array_length(1000);
+  let err = read_err(options, &bytes);
+  assert!(matches!(
+    err,
+    ParseErrorKind::ArrayLengthLimitExceeded {
+      length: u32::MAX,
+      limit: 1000
+    }
+  ));
+}
+
+#[test]
+fn max_heap_objects() {
+  // [{}, {}]
+  let bytes = [
+    0xFF, 0x0F, b'A', 0x02, b'o', b'{', 0x00, b'o', b'{', 0x00, b'$', 0x00,
+    0x02,
+  ];
+  let options = DeserializerOptions::default().max_heap_objects(3);
+  ValueDeserializer::with_options(options)
+    .read(&bytes)
+    .unwrap();
+  let options = DeserializerOptions::default().max_heap_objects(2);
+  let err = read_err(options, &bytes);
+  assert!(matches!(
+    err,
+    ParseErrorKind::HeapObjectLimitExceeded { limit: 2 }
+  ));
+}
@@ -0,0 +1,246 @@
+use v8_valueserializer::ArrayBuffer;
+use v8_valueserializer::ArrayBufferView;
+use v8_valueserializer::ArrayBufferViewKind;
+use v8_valueserializer::Heap;
+use v8_valueserializer::HeapBuilder;
+use v8_valueserializer::HeapValue;
+use v8_valueserializer::NodeIpcError;
+use v8_valueserializer::NodeIpcReader;
+use v8_valueserializer::NodeIpcWriter;
+use v8_valueserializer::ParseErrorKind;
+use v8_valueserializer::Value;
+use v8_valueserializer::ValueDeserializer;
+use v8_valueserializer::ValueSerializer;
+
+/// A message sent by `process.send()` in Node.js 20:
+/// { a: Buffer.from([1, 2, 3]), b: new Uint16Array([1, 2]),
+///   c: new Map([[1, "x"]]), d: "ሴ" }
+const MESSAGE: [u8; 48] = [
+  0x00, 0x00, 0x00, 0x2C, 0xFF, 0x0F, b'o', b'"', 0x01, b'a', b'\\', 0x00,
+  0x0A, 0x03, 0x01, 0x02, 0x03, b'"', 0x01, b'b', b'\\', 0x00, 0x04, 0x04,
+  0x01, 0x00, 0x02, 0x00, b'"', 0x01, b'c', b';', b'I', 0x02, b'"', 0x01, b'x',
+  b':', 0x02, b'"', 0x01, b'd', b'c', 0x02, 0x34, 0x12, b'{', 0x04,
+];
+
+/// Written by `v8.serialize()` in Node.js 20:
+/// const u = new Int16Array([-1, 2]);
+/// { a: Buffer.from([1, 2, 3]), b: [u, u], c: new DataView(new ArrayBuffer(2)) }
+const SERIALIZED: [u8; 39] = [
+  0xFF, 0x0F, b'o', b'"', 0x01, b'a', b'\\', 0x0A, 0x03, 0x01, 0x02, 0x03,
+  b'"', 0x01, b'b', b'A', 0x02, b'\\', 0x03, 0x04, 0xFF, 0xFF, 0x02, 0x00,
+  b'^', 0x03, b'$', 0x00, 0x02, b'"', 0x01, b'c', b'\\', 0x09, 0x02, 0x00,
+  0x00, b'{', 0x03,
+];
+
+fn node_deserializer() -> ValueDeserializer {
+  let mut de = ValueDeserializer::default();
+  de.set_node_compat(true);
+  de
+}
+
+fn open<'h>(heap: &'h Heap, value: &Value) -> &'h HeapValue {
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  reference.open(heap)
+}
+
+fn property<'h>(heap: &'h Heap, value: &Value, index: usize) -> &'h Value {
+  let HeapValue::Object(object) = open(heap, value) else {
+    panic!("expected an object");
+  };
+  &object.properties[index].1
+}
+
+fn view_contents(heap: &Heap, value: &Value) -> (ArrayBufferViewKind, Vec<u8>) {
+  let HeapValue::ArrayBufferView(view) = open(heap, value) else {
+    panic!("expected a view");
+  };
+  let HeapValue::ArrayBuffer(buffer) = view.buffer.open(heap) else {
+    panic!("expected an array buffer");
+  };
+  (view.kind, buffer.as_u8_slice().to_vec())
+}
+
+#[test]
+fn read_message() {
+  let mut reader = NodeIpcReader::new(&MESSAGE[..]);
+  let (value, heap) = reader.read_message().unwrap().unwrap();
+  assert_eq!(
+    view_contents(&heap, property(&heap, &value, 0)),
+    (ArrayBufferViewKind::Uint8Array, vec![1, 2, 3])
+  );
+  assert_eq!(
+    view_contents(&heap, property(&heap, &value, 1)),
+    (ArrayBufferViewKind::Uint16Array, vec![1, 0, 2, 0])
+  );
+  assert!(matches!(
+    open(&heap, property(&heap, &value, 2)),
+    HeapValue::Map(_)
+  ));
+  let Value::String(string) = property(&heap, &value, 3) else {
+    panic!("expected a string");
+  };
+  assert_eq!(string.to_string(), "\u{1234}");
+  assert!(reader.read_message().unwrap().is_none());
+}
+
+#[test]
+fn write_message() {
+  let (value, heap) = NodeIpcReader::new(&MESSAGE[..])
+    .read_message()
+    .unwrap()
+    .unwrap();
+  let mut writer = NodeIpcWriter::new(vec![]);
+  writer.write_message(&heap, &value).unwrap();
+  // The Buffer is written back as a Uint8Array.
+  let mut expected = MESSAGE.to_vec();
+  expected[12] = 0x01;
+  assert_eq!(writer.into_inner(), expected);
+}
+
+#[test]
+fn read_several_messages() {
+  // 1, followed by true, and a message that is cut short.
+  let bytes = [
+    0x00, 0x00, 0x00, 0x04, 0xFF, 0x0F, b'I', 0x02, 0x00, 0x00, 0x00, 0x03,
+    0xFF, 0x0F, b'T', 0x00, 0x00, 0x00, 0x04, 0xFF,
+  ];
+  let mut reader = NodeIpcReader::new(&bytes[..]);
+  let (value, _) = reader.read_message().unwrap().unwrap();
+  assert!(matches!(value, Value::I32(1)));
+  let (value, _) = reader.read_message().unwrap().unwrap();
+  assert!(matches!(value, Value::Bool(true)));
+  let err = reader.read_message().unwrap_err();
+  assert!(matches!(err, NodeIpcError::Io(_)));
+}
+
+#[test]
+fn read_nested_host_object() {
+  // A message sent by `process.send()` in Node.js 20:
+  // const req = new FSReqCallback(); req.x = 1; { a: req, b: req }
+  // The host object and its nested value have the ids 1 and 2, and the
+  // back-reference refers to the host object.
+  let bytes = [
+    0x00, 0x00, 0x00, 0x17, 0xFF, 0x0F, b'o', b'"', 0x01, b'a', b'\\', 0x01,
+    b'o', b'"', 0x01, b'x', b'I', 0x02, b'{', 0x01, b'"', 0x01, b'b', b'^',
+    0x01, b'{', 0x02,
+  ];
+  let (value, heap) = NodeIpcReader::new(&bytes[..])
+    .read_message()
+    .unwrap()
+    .unwrap();
+  let a = open(&heap, property(&heap, &value, 0));
+  let b = open(&heap, property(&heap, &value, 1));
+  assert!(std::ptr::eq(a, b));
+  let HeapValue::Object(object) = a else {
+    panic!("expected an object");
+  };
+  assert_eq!(object.properties.len(), 1);
+  assert!(matches!(object.properties[0].1, Value::I32(1)));
+
+  // A nested value that is not an object.
+  let bytes = [0x00, 0x00, 0x00, 0x06, 0xFF, 0x0F, b'\\', 0x01, b'I', 0x02];
+  let err = NodeIpcReader::new(&bytes[..]).read_message().unwrap_err();
+  let NodeIpcError::Parse(err) = err else {
+    panic!("expected a parse error");
+  };
+  assert!(matches!(err.kind, ParseErrorKind::InvalidHostObject(_)));
+}
+
+#[test]
+fn read_node_host_objects() {
+  let (value, heap) = node_deserializer().read(&SERIALIZED).unwrap();
+  assert_eq!(
+    view_contents(&heap, property(&heap, &value, 0)),
+    (ArrayBufferViewKind::Uint8Array, vec![1, 2, 3])
+  );
+  let HeapValue::DenseArray(array) = open(&heap, property(&heap, &value, 1))
+  else {
+    panic!("expected an array");
+  };
+  let (Some(first), Some(second)) = (&array.elements[0], &array.elements[1])
+  else {
+    panic!("expected two elements");
+  };
+  assert_eq!(
+    view_contents(&heap, first),
+    (
+      ArrayBufferViewKind::Int16Array,
+      vec![0xFF, 0xFF, 0x02, 0x00]
+    )
+  );
+  // The second element is a reference to the first view.
+  assert!(std::ptr::eq(open(&heap, first), open(&heap, second)));
+  assert_eq!(
+    view_contents(&heap, property(&heap, &value, 2)),
+    (ArrayBufferViewKind::DataView, vec![0, 0])
+  );
+  node_deserializer().validate(&SERIALIZED).unwrap();
+}
+
+#[test]
+fn write_node_host_objects() {
+  let (value, heap) = node_deserializer().read(&SERIALIZED).unwrap();
+  let mut ser = ValueSerializer::default();
+  ser.set_node_compat(true);
+  let bytes = ser.finish(&heap, &value).unwrap();
+  // The Buffer is written back as a Uint8Array.
+  let mut expected = SERIALIZED.to_vec();
+  expected[7] = 0x01;
+  assert_eq!(bytes, expected);
+}
+
+#[test]
+fn write_part_of_buffer() {
+  // new Uint8Array(new ArrayBuffer(8), 2, 3)
+  let mut heap = HeapBuilder::default();
+  let buffer = heap.insert(HeapValue::ArrayBuffer(ArrayBuffer::new(8)));
+  let view = heap.insert(HeapValue::ArrayBufferView(ArrayBufferView {
+    kind: ArrayBufferViewKind::Uint8Array,
+    buffer,
+    byte_offset: 2,
+    length: 3,
+    is_length_tracking: false,
+    is_backed_by_rab: false,
+  }));
+  let heap = heap.build().unwrap();
+  let mut ser = ValueSerializer::default();
+  ser.set_node_compat(true);
+  let bytes = ser.finish(&heap, &Value::HeapReference(view)).unwrap();
+  assert_eq!(bytes, [0xFF, 0x0F, b'\\', 0x01, 0x03, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn float16_array_type_index() {
+  // new Float16Array([1.5])
+  let bytes = [0xFF, 0x0F, b'\\', 0x0D, 0x02, 0x00, 0x3E];
+  let (value, heap) = node_deserializer().read(&bytes).unwrap();
+  assert_eq!(
+    view_contents(&heap, &value),
+    (ArrayBufferViewKind::Float16Array, vec![0x00, 0x3E])
+  );
+  let mut ser = ValueSerializer::default();
+  ser.set_node_compat(true);
+  assert_eq!(ser.finish(&heap, &value).unwrap(), bytes);
+}
+
+#[test]
+fn unaligned_length() {
+  // A Uint16Array of 3 bytes.
+  let bytes = [0xFF, 0x0F, b'\\', 0x04, 0x03, 0x00, 0x00, 0x00];
+  let err = node_deserializer().read(&bytes).unwrap_err();
+  assert!(matches!(
+    err.kind,
+    ParseErrorKind::UnalignedArrayBufferViewLength {
+      byte_length: 3,
+      element_size: 2
+    }
+  ));
+}
+
+#[test]
+fn host_objects_need_node_compat() {
+  let err = ValueDeserializer::default().read(&SERIALIZED).unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::HostObjectNotSupported));
+}
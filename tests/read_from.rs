@@ -0,0 +1,99 @@
+use std::io::Read;
+
+use v8_valueserializer::value_eq;
+use v8_valueserializer::ParseErrorKind;
+use v8_valueserializer::ValueDeserializer;
+
+/// A reader that returns at most `chunk` bytes per read.
+struct Trickle<'a> {
+  bytes: &'a [u8],
+  chunk: usize,
+}
+
+impl Read for Trickle<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let len = buf.len().min(self.chunk).min(self.bytes.len());
+    buf[..len].copy_from_slice(&self.bytes[..len]);
+    self.bytes = &self.bytes[len..];
+    Ok(len)
+  }
+}
+
+fn assert_read_from_matches_read(bytes: &[u8]) {
+  let (expected_value, expected_heap) =
+    ValueDeserializer::default().read(bytes).unwrap();
+  for chunk in [1, 7, 1 << 20] {
+    let reader = Trickle { bytes, chunk };
+    let (value, heap) = ValueDeserializer::default().read_from(reader).unwrap();
+    assert!(value_eq((&value, &heap), (&expected_value, &expected_heap)));
+  }
+}
+
+#[test]
+fn read_from_large_payloads() {
+  // [new Uint8Array(buffer), "aaa...", "ሴሴ...", buffer]
+  let length = 100_000u32;
+  let mut bytes = vec![0xFF, 0x0F, b'A', 0x04, b'B', 0xA0, 0x8D, 0x06];
+  bytes.extend((0..length).map(|i| i as u8));
+  bytes.extend_from_slice(&[b'V', b'B', 0x00, 0xA0, 0x8D, 0x06, 0x00]);
+  bytes.extend_from_slice(&[b'"', 0xA0, 0x8D, 0x06]);
+  bytes.extend(std::iter::repeat(b'a').take(length as usize));
+  bytes.extend_from_slice(&[0x00, b'c', 0xA0, 0x8D, 0x06]);
+  bytes.extend(std::iter::repeat([0x34, 0x12]).take(50_000).flatten());
+  bytes.extend_from_slice(&[b'^', 0x01, b'$', 0x00, 0x04]);
+  assert_read_from_matches_read(&bytes);
+}
+
+#[test]
+fn read_from_legacy_versions() {
+  // Versionless { a: 1 }
+  assert_read_from_matches_read(&[b'"', 0x01, b'a', b'I', 0x02, b'{', 0x01]);
+  // Version 13 data with array buffer view flags.
+  assert_read_from_matches_read(&[
+    0xFF, 0x0D, b'A', 0x01, b'~', 0x02, 0x04, 0x01, 0x02, b'V', b'B', 0x00,
+    0x02, 0x02, b'$', 0x00, 0x01,
+  ]);
+}
+
+#[test]
+fn read_from_errors() {
+  let reader = Trickle {
+    bytes: &[0xFF, 0x0F, b'"', 0x05, b'a', b'b'],
+    chunk: 1,
+  };
+  let err = ValueDeserializer::default().read_from(reader).unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::UnexpectedEof));
+
+  let reader = [0xFF, 0x0F, b'"'].chain(FailingReader);
+  let err = ValueDeserializer::default().read_from(reader).unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::Io(_)));
+}
+
+#[test]
+fn read_from_corrupt_lengths() {
+  // A dense array, an array buffer, a two byte string and a one byte string,
+  // each claiming to be about 4 GiB long. Nothing that large may be allocated
+  // before the input turns out to end.
+  for tag in [b'A', b'B', b'c', b'"'] {
+    let bytes = [0xFF, 0x0F, tag, 0xF0, 0xFF, 0xFF, 0xFF, 0x0F];
+    let err = ValueDeserializer::default()
+      .read_from(&bytes[..])
+      .unwrap_err();
+    assert!(
+      matches!(err.kind, ParseErrorKind::UnexpectedEof),
+      "{:?}",
+      err
+    );
+  }
+}
+
+struct FailingReader;
+
+impl Read for FailingReader {
+  fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+    Err(std::io::Error::new(
+      std::io::ErrorKind::Other,
+      "broken pipe",
+    ))
+  }
+}
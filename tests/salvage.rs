@@ -0,0 +1,134 @@
+use v8_valueserializer::HeapValue;
+use v8_valueserializer::ParseErrorKind;
+use v8_valueserializer::PropertyKey;
+use v8_valueserializer::Value;
+use v8_valueserializer::ValueDeserializer;
+
+#[test]
+fn truncated_payload() {
+  // { a: 1, b: [1, 2, ... cut short.
+  let bytes = [
+    0xFF, 0x0F, b'o', b'"', 0x01, b'a', b'I', 0x02, b'"', 0x01, b'b', b'A',
+    0x03, b'I', 0x02, b'I', 0x04,
+  ];
+  let (value, heap, errors) =
+    ValueDeserializer::default().read_salvaged(&bytes);
+  let [err] = &errors[..] else {
+    panic!("expected a single error");
+  };
+  assert!(matches!(err.kind, ParseErrorKind::UnexpectedEof));
+  assert_eq!(err.position(), bytes.len());
+
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::Object(object) = reference.open(&heap) else {
+    panic!("expected an object");
+  };
+  let [(PropertyKey::String(a), a_value), (PropertyKey::String(b), b_value)] =
+    &object.properties[..]
+  else {
+    panic!("expected two properties");
+  };
+  assert!(matches!(a_value, Value::I32(1)));
+  let Value::HeapReference(array) = b_value else {
+    panic!("expected a heap reference");
+  };
+  assert_eq!(a.to_string(), "a");
+  assert_eq!(b.to_string(), "b");
+  let HeapValue::DenseArray(array) = array.open(&heap) else {
+    panic!("expected a dense array");
+  };
+  assert!(matches!(
+    array.elements[..],
+    [Some(Value::I32(1)), Some(Value::I32(2))]
+  ));
+}
+
+#[test]
+fn unknown_tag() {
+  // [1, <unknown tag 0x01 and a stray byte>, 2]
+  let bytes = [
+    0xFF, 0x0F, b'A', 0x03, b'I', 0x02, 0x01, 0x02, b'I', 0x04, b'$', 0x00,
+    0x03,
+  ];
+  let (value, heap, errors) =
+    ValueDeserializer::default().read_salvaged(&bytes);
+  let [err] = &errors[..] else {
+    panic!("expected a single error");
+  };
+  assert!(matches!(err.kind, ParseErrorKind::UnexpectedTag(0x01)));
+  assert_eq!(err.position(), 6);
+
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::DenseArray(array) = reference.open(&heap) else {
+    panic!("expected a dense array");
+  };
+  let [Some(Value::I32(1)), Some(Value::HeapReference(unreadable)), Some(Value::I32(2))] =
+    array.elements[..]
+  else {
+    panic!("expected three elements");
+  };
+  assert!(matches!(unreadable.open(&heap), HeapValue::Placeholder));
+}
+
+#[test]
+fn wrong_property_count() {
+  // { a: 1 }, but with a property count of 5.
+  let bytes = [0xFF, 0x0F, b'o', b'"', 0x01, b'a', b'I', 0x02, b'{', 0x05];
+  assert!(ValueDeserializer::default().read(&bytes).is_err());
+  let (value, heap, errors) =
+    ValueDeserializer::default().read_salvaged(&bytes);
+  let [err] = &errors[..] else {
+    panic!("expected a single error");
+  };
+  assert!(matches!(
+    err.kind,
+    ParseErrorKind::InvalidPropertyCount {
+      expected: 5,
+      actual: 1
+    }
+  ));
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::Object(object) = reference.open(&heap) else {
+    panic!("expected an object");
+  };
+  assert_eq!(object.properties.len(), 1);
+}
+
+#[test]
+fn placeholder() {
+  // [<a host object, without a delegate to read it>]
+  let bytes = [0xFF, 0x0F, b'A', 0x01, b'\\', 0x00];
+  let (value, heap, errors) =
+    ValueDeserializer::default().read_salvaged(&bytes);
+  assert!(matches!(
+    errors[0].kind,
+    ParseErrorKind::HostObjectNotSupported
+  ));
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::DenseArray(array) = reference.open(&heap) else {
+    panic!("expected a dense array");
+  };
+  let [Some(Value::HeapReference(unreadable))] = array.elements[..] else {
+    panic!("expected a single element");
+  };
+  assert!(matches!(unreadable.open(&heap), HeapValue::Placeholder));
+
+  let (value, heap, errors) =
+    ValueDeserializer::default().read_salvaged(&[0xFF, 0x0F]);
+  let [err] = &errors[..] else {
+    panic!("expected a single error");
+  };
+  assert!(matches!(err.kind, ParseErrorKind::UnexpectedEof));
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  assert!(matches!(reference.open(&heap), HeapValue::Placeholder));
+}
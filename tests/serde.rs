@@ -354,3 +354,40 @@ serde_test!(error_prototype_syntax r#"new SyntaxError("foo")"#);
 serde_test!(error_prototype_type r#"new TypeError("foo")"#);
 serde_test!(error_prototype_uri r#"new URIError("foo")"#);
 serde_test!(error_cause_obj r#"new Error("foo", { cause: { a: 1 } })"#);
+
+// shared array buffer
+serde_test!(shared_array_buffer r#"const b = new SharedArrayBuffer(4); new Uint8Array(b).set([1, 2, 3]); b"#, shared);
+serde_test!(shared_array_buffer_views r#"const b = new SharedArrayBuffer(4); [b, new Uint8Array(b), new Uint16Array(b, 2, 1)]"#, shared);
+
+// wasm memory
+serde_test!(wasm_memory r#"new WebAssembly.Memory({ initial: 0, maximum: 1, shared: true })"#, shared);
+serde_test!(wasm_memory_buffer r#"const m = new WebAssembly.Memory({ initial: 0, maximum: 1, shared: true }); [m.buffer, m]"#, shared);
+
+// shared object
+// Written by V8 with --harmony-struct, through a delegate that gives the
+// shared struct the id 5.
+serde_test!(shared_object r#"const s = new (new SharedStructType([]))(); ({ a: s, b: s })"#, bytes = [0xFF, 0x0F, b'o', b'"', 0x01, b'a', b'p', 0x05, b'"', 0x01, b'b', b'^', 0x01, b'{', 0x02]);
+
+// legacy versions
+serde_test!(legacy_versionless_object r#"const d = new Date(0); ({ o: { a: 2 }, d, e: d })"#, legacy = [b'"', 0x01, b'o', b'"', 0x01, b'a', b'I', 0x04, b'{', 0x01, b'"', 0x01, b'd', b'D', 0, 0, 0, 0, 0, 0, 0, 0, b'"', 0x01, b'e', b'^', 0x00, b'{', 0x03, 0x00]);
+serde_test!(legacy_versionless_sparse_array r#"const a = new Array(3); a[1] = "x"; a"#, legacy = [b'I', 0x02, b'"', 0x01, b'x', b'@', 0x01, 0x03]);
+serde_test!(legacy_version_10_holes r#"[/a/g, ,]"#, legacy = [0xFF, 0x0A, b'A', 0x02, b'R', 0x01, b'a', 0x01, b'_', b'$', 0x00, 0x02]);
+serde_test!(legacy_version_13_view r#"new Uint8Array([1, 2])"#, legacy = [0xFF, 0x0D, b'B', 0x02, 0x01, 0x02, b'V', b'B', 0x00, 0x02]);
+// Written with the view flags of the broken version 13 format, see
+// https://crbug.com/1284506.
+serde_test!(legacy_version_13_view_flags r#"const b = new ArrayBuffer(2, { maxByteLength: 4 }); new Uint8Array(b).set([1, 2]); [new Uint8Array(b, 0, 2)]"#, legacy = [0xFF, 0x0D, b'A', 0x01, b'~', 0x02, 0x04, 0x01, 0x02, b'V', b'B', 0x00, 0x02, 0x02, b'$', 0x00, 0x01]);
+
+// float16array
+// Written by V8 with --js-float16array, which the V8 of these tests does not
+// have yet.
+serde_test!(float16array r#"new Float16Array([1, -2.5, Infinity, 2 ** -24, -0])"#, bytes = [0xFF, 0x0F, b'B', 0x0A, 0x00, 0x3C, 0x00, 0xC1, 0x00, 0x7C, 0x01, 0x00, 0x00, 0x80, b'V', b'h', 0x00, 0x0A, 0x00]);
+serde_test!(float16array_empty r#"new Float16Array()"#, bytes = [0xFF, 0x0F, b'B', 0x00, b'V', b'h', 0x00, 0x00, 0x00]);
+serde_test!(float16array_offset r#"new Float16Array(new Uint8Array([0, 0, 0, 0x3C]).buffer, 2, 1)"#, bytes = [0xFF, 0x0F, b'B', 0x04, 0x00, 0x00, 0x00, 0x3C, b'V', b'h', 0x02, 0x02, 0x00]);
+
+// node host objects
+// Buffer.from([1, 2, 3])
+serde_test!(node_buffer r#"new Uint8Array([1, 2, 3])"#, node = [0xFF, 0x0F, b'\\', 0x0A, 0x03, 0x01, 0x02, 0x03]);
+serde_test!(node_typed_arrays r#"const u = new Int16Array([-1, 2]); ({ a: u, b: [u, new Float64Array([0.5])], c: new DataView(new ArrayBuffer(2)) })"#, node = [0xFF, 0x0F, b'o', b'"', 0x01, b'a', b'\\', 0x03, 0x04, 0xFF, 0xFF, 0x02, 0x00, b'"', 0x01, b'b', b'A', 0x02, b'^', 0x01, b'\\', 0x08, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0, 0x3F, b'$', 0x00, 0x02, b'"', 0x01, b'c', b'\\', 0x09, 0x02, 0x00, 0x00, b'{', 0x03]);
+// new Uint8Array([1, 2, 3, 4]).subarray(1, 3)
+serde_test!(node_subarray r#"new Uint8Array([2, 3])"#, node = [0xFF, 0x0F, b'\\', 0x01, 0x02, 0x02, 0x03]);
+serde_test!(node_bigint64array r#"new BigUint64Array([1n, 2n ** 64n - 1n])"#, node = [0xFF, 0x0F, b'\\', 0x0C, 0x10, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
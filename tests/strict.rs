@@ -0,0 +1,81 @@
+use v8_valueserializer::DeserializerOptions;
+use v8_valueserializer::HeapBuilder;
+use v8_valueserializer::HeapValue;
+use v8_valueserializer::Object;
+use v8_valueserializer::ParseErrorKind;
+use v8_valueserializer::PropertyKey;
+use v8_valueserializer::StringValue;
+use v8_valueserializer::TwoByteString;
+use v8_valueserializer::Value;
+use v8_valueserializer::ValueDeserializer;
+use v8_valueserializer::ValueSerializer;
+
+fn read_strict(bytes: &[u8]) -> Result<(), ParseErrorKind> {
+  let options = DeserializerOptions::default().strict(true);
+  ValueDeserializer::with_options(options)
+    .read(bytes)
+    .map(|_| ())
+    .map_err(|err| err.kind)
+}
+
+/// Check that `bytes` are read by default, but rejected in strict mode.
+fn non_canonical(bytes: &[u8]) -> ParseErrorKind {
+  ValueDeserializer::default().read(bytes).unwrap();
+  read_strict(bytes).unwrap_err()
+}
+
+#[test]
+fn serialized_values_are_canonical() {
+  // { "\u{1234}": 300, a: "\u{5678}" }
+  let mut heap = HeapBuilder::default();
+  let two_byte = |c: u16| StringValue::TwoByte(TwoByteString::new(vec![c]));
+  let object = Object {
+    properties: vec![
+      (PropertyKey::String(two_byte(0x1234)), Value::I32(300)),
+      (
+        PropertyKey::String(StringValue::new("a".to_string())),
+        Value::String(two_byte(0x5678)),
+      ),
+    ],
+  };
+  let value = Value::HeapReference(heap.insert(HeapValue::Object(object)));
+  let heap = heap.build().unwrap();
+  let bytes = ValueSerializer::default().finish(&heap, &value).unwrap();
+  read_strict(&bytes).unwrap();
+}
+
+#[test]
+fn overlong_varint() {
+  let err = non_canonical(&[0xFF, 0x0F, b'I', 0x82, 0x00]);
+  assert!(matches!(err, ParseErrorKind::NonCanonicalVarint));
+}
+
+#[test]
+fn padding() {
+  // Padding that does not align a two byte string.
+  let err = non_canonical(&[0xFF, 0x0F, 0x00, b'I', 0x02]);
+  assert!(matches!(err, ParseErrorKind::NonCanonicalPadding));
+  // A two byte string that is not aligned: { "a": true }
+  let err = non_canonical(&[
+    0xFF, 0x0F, b'o', b'c', 0x02, b'a', 0x00, b'T', b'{', 0x01,
+  ]);
+  assert!(matches!(err, ParseErrorKind::NonCanonicalPadding));
+  read_strict(&[
+    0xFF, 0x0F, b'o', 0x00, b'c', 0x02, b'a', 0x00, b'T', b'{', 0x01,
+  ])
+  .unwrap();
+}
+
+#[test]
+fn verify_object_count() {
+  let err = non_canonical(&[0xFF, 0x0F, b'?', 0x00, b'I', 0x02]);
+  assert!(matches!(err, ParseErrorKind::NonCanonicalVerifyObjectCount));
+}
+
+#[test]
+fn utf8_string() {
+  let err = non_canonical(&[0xFF, 0x0F, b'S', 0x01, b'a']);
+  assert!(matches!(err, ParseErrorKind::NonCanonicalUtf8String));
+  // "€" does not fit in a one byte string.
+  read_strict(&[0xFF, 0x0F, b'S', 0x03, 0xE2, 0x82, 0xAC]).unwrap();
+}
@@ -1,9 +1,16 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use v8::Global;
 use v8::Local;
 use v8::Value;
 use v8::ValueDeserializerHelper;
 use v8::ValueSerializerHelper;
 use v8_valueserializer::Heap;
+use v8_valueserializer::SerializationError;
+use v8_valueserializer::ValueDeserializer;
+use v8_valueserializer::ValueSerializer;
+use v8_valueserializer::ValueSerializerDelegate;
 
 static ONCE: std::sync::Once = std::sync::Once::new();
 
@@ -18,9 +25,18 @@ pub struct JsError {
   message: String,
 }
 
+/// The shared array buffers that V8 wrote, by id, and a copy of their contents
+/// at the time they were first written.
+#[derive(Default)]
+struct SharedArrayBuffers {
+  v8: Vec<Global<v8::SharedArrayBuffer>>,
+  rs: Vec<v8_valueserializer::SharedArrayBuffer>,
+}
+
 pub struct Isolate {
   isolate: v8::OwnedIsolate,
   context: v8::Global<v8::Context>,
+  shared_array_buffers: Rc<RefCell<SharedArrayBuffers>>,
 }
 
 impl Default for Isolate {
@@ -31,7 +47,11 @@ impl Default for Isolate {
     let context = v8::Context::new(&mut scope);
     let context = v8::Global::new(&mut scope, context);
     drop(scope);
-    Isolate { isolate, context }
+    Isolate {
+      isolate,
+      context,
+      shared_array_buffers: Default::default(),
+    }
   }
 }
 
@@ -48,9 +68,12 @@ impl Isolate {
     let tc = &mut v8::TryCatch::new(scope);
     let mut deserializer = v8::ValueDeserializer::new(
       tc,
-      Box::new(ValueDeserializerImpl),
+      Box::new(ValueDeserializerImpl {
+        shared_array_buffers: self.shared_array_buffers.clone(),
+      }),
       serialized,
     );
+    deserializer.set_supports_legacy_wire_format(true);
 
     let Some(true) = deserializer.read_header(context) else {
       let message = tc.message().unwrap();
@@ -111,8 +134,12 @@ impl Isolate {
     let scope = &mut v8::ContextScope::new(scope, context);
 
     let tc = &mut v8::TryCatch::new(scope);
-    let mut serializer =
-      v8::ValueSerializer::new(tc, Box::new(ValueSerializerImpl));
+    let mut serializer = v8::ValueSerializer::new(
+      tc,
+      Box::new(ValueSerializerImpl {
+        shared_array_buffers: self.shared_array_buffers.clone(),
+      }),
+    );
 
     let value = Local::new(tc, &value);
 
@@ -126,9 +153,46 @@ impl Isolate {
     let buf = serializer.release();
     Ok(buf)
   }
+
+  /// A deserializer that knows every shared array buffer V8 wrote so far.
+  pub fn rs_deserializer(&self) -> ValueDeserializer {
+    let mut de = ValueDeserializer::default();
+    let shared_array_buffers = self.shared_array_buffers.borrow();
+    for (id, sab) in shared_array_buffers.rs.iter().enumerate() {
+      de.transfer_shared_array_buffer(id as u32, sab.clone());
+    }
+    de
+  }
+
+  /// A serializer that writes the shared array buffers V8 wrote so far with
+  /// the ids that V8 gave them.
+  pub fn rs_serializer(&self) -> ValueSerializer {
+    let mut ser = ValueSerializer::default();
+    let shared_array_buffers = self.shared_array_buffers.borrow();
+    ser.set_delegate(Box::new(SharedArrayBufferIds(
+      shared_array_buffers.rs.clone(),
+    )));
+    ser
+  }
+}
+
+struct SharedArrayBufferIds(Vec<v8_valueserializer::SharedArrayBuffer>);
+
+impl ValueSerializerDelegate for SharedArrayBufferIds {
+  fn get_shared_array_buffer_id(
+    &mut self,
+    sab: &v8_valueserializer::SharedArrayBuffer,
+  ) -> Result<u32, SerializationError> {
+    let Some(id) = self.0.iter().position(|known| known.ptr_eq(sab)) else {
+      return Err(SerializationError::SharedArrayBufferNotSupported);
+    };
+    Ok(id as u32)
+  }
 }
 
-struct ValueDeserializerImpl;
+struct ValueDeserializerImpl {
+  shared_array_buffers: Rc<RefCell<SharedArrayBuffers>>,
+}
 
 impl v8::ValueDeserializerImpl for ValueDeserializerImpl {
   fn read_host_object<'s>(
@@ -149,11 +213,15 @@ impl v8::ValueDeserializerImpl for ValueDeserializerImpl {
   fn get_shared_array_buffer_from_id<'s>(
     &mut self,
     scope: &mut v8::HandleScope<'s>,
-    _transfer_id: u32,
+    transfer_id: u32,
   ) -> Option<v8::Local<'s, v8::SharedArrayBuffer>> {
+    let shared_array_buffers = self.shared_array_buffers.borrow();
+    if let Some(sab) = shared_array_buffers.v8.get(transfer_id as usize) {
+      return Some(v8::Local::new(scope, sab));
+    }
     let msg = v8::String::new(
       scope,
-      "Deno deserializer: get_shared_array_buffer_from_id not implemented",
+      "Deno deserializer: unknown shared array buffer id",
     )
     .unwrap();
     let exc = v8::Exception::error(scope, msg);
@@ -177,7 +245,9 @@ impl v8::ValueDeserializerImpl for ValueDeserializerImpl {
   }
 }
 
-struct ValueSerializerImpl;
+struct ValueSerializerImpl {
+  shared_array_buffers: Rc<RefCell<SharedArrayBuffers>>,
+}
 
 impl v8::ValueSerializerImpl for ValueSerializerImpl {
   fn throw_data_clone_error<'s>(
@@ -188,6 +258,32 @@ impl v8::ValueSerializerImpl for ValueSerializerImpl {
     let exc = v8::Exception::error(scope, message);
     scope.throw_exception(exc);
   }
+
+  /// Shared array buffers get ids in the order they are first written.
+  fn get_shared_array_buffer_id<'s>(
+    &mut self,
+    scope: &mut v8::HandleScope<'s>,
+    sab: v8::Local<'s, v8::SharedArrayBuffer>,
+  ) -> Option<u32> {
+    let mut shared_array_buffers = self.shared_array_buffers.borrow_mut();
+    if let Some(id) = shared_array_buffers
+      .v8
+      .iter()
+      .position(|known| *known == sab)
+    {
+      return Some(id as u32);
+    }
+    let contents = sab
+      .get_backing_store()
+      .iter()
+      .map(|byte| byte.get())
+      .collect::<Vec<u8>>();
+    shared_array_buffers.v8.push(Global::new(scope, sab));
+    shared_array_buffers
+      .rs
+      .push(v8_valueserializer::SharedArrayBuffer::new(&contents));
+    Some(shared_array_buffers.v8.len() as u32 - 1)
+  }
 }
 
 pub struct Assert {
@@ -207,38 +303,86 @@ impl std::fmt::Debug for Assert {
   }
 }
 
+/// The code that is evaluated for the source of a test.
+pub fn source(code: &str) -> String {
+  if code.starts_with('{') {
+    format!("({})", code)
+  } else {
+    code.to_string()
+  }
+}
+
+/// A deserializer that reads the host objects of Node's `v8.serialize()`.
+pub fn node_deserializer() -> ValueDeserializer {
+  let mut de = ValueDeserializer::default();
+  de.set_node_compat(true);
+  de
+}
+
+/// Panic with `message` if `bytes` do not read to the expected value.
+pub fn assert_reads_to(
+  expected: &Assert,
+  de: ValueDeserializer,
+  bytes: &[u8],
+  message: &str,
+) {
+  let (value, heap) = de.read(bytes).expect("parse_v8 failed");
+  if !v8_valueserializer::value_eq(
+    (&expected.value, &expected.heap),
+    (&value, &heap),
+  ) {
+    println!("=== EXPECTED ===");
+    println!("{:?}", expected);
+
+    let actual = Assert { value, heap };
+    println!("=== ACTUAL ===");
+    println!("{:?}", actual);
+
+    panic!("{}", message);
+  }
+}
+
 #[macro_export]
 macro_rules! serde_test {
-  ($name:ident $code:expr) => {
+  (@snapshot $name:ident $code:expr, $assert:expr) => {
+    insta::with_settings!({
+      description => format!("=== SOURCE ===\n{}", $code),
+      omit_expression => true,
+    }, {
+      insta::assert_debug_snapshot!(concat!("de_", stringify!($name)), $assert);
+    });
+
+    let display = v8_valueserializer::display(&$assert.heap, &$assert.value, v8_valueserializer::DisplayOptions {
+      format: v8_valueserializer::DisplayFormat::Repl,
+    });
+    insta::with_settings!({
+      description => format!("=== SOURCE ===\n{}", $code),
+      omit_expression => true,
+    }, {
+      insta::assert_snapshot!(concat!("display_", stringify!($name)), display);
+    });
+  };
+  (@v8 $name:ident $code:expr, $display_roundtrip:expr) => {
     #[test]
     fn $name() {
       let mut isolate = $crate::util::Isolate::default();
-      let code = if $code.starts_with("{") {
-        format!("({})", $code)
-      } else {
-        $code.to_string()
-      };
+      let code = $crate::util::source($code);
 
       let input_js_value = isolate.eval(code.as_str()).expect("eval failed");
       let input_bytes = isolate
         .serialize_value(input_js_value)
         .expect("serialize_value failed");
       println!("injs_bytes {:?}", input_bytes);
-      let de = v8_valueserializer::ValueDeserializer::default();
+      let de = isolate.rs_deserializer();
 
       let (value, heap) = de.read(&input_bytes).expect("original parse_v8 failed");
       let assert = $crate::util::Assert {
         value,
         heap,
       };
-      insta::with_settings!({
-        description => format!("=== SOURCE ===\n{}", $code),
-        omit_expression => true,
-      }, {
-        insta::assert_debug_snapshot!(concat!("de_", stringify!($name)), assert);
-      });
+      $crate::serde_test!(@snapshot $name $code, assert);
 
-      let ser = v8_valueserializer::ValueSerializer::default();
+      let ser = isolate.rs_serializer();
       let rs_ser_bytes = ser.finish(&assert.heap, &assert.value).expect("serialize failed");
       println!("rsserbytes {:?}", rs_ser_bytes);
 
@@ -250,59 +394,117 @@ macro_rules! serde_test {
         .expect("rs serialize_value failed");
       println!("rsrt_bytes {:?}", rs_roundtripped_bytes);
 
-      let de = v8_valueserializer::ValueDeserializer::default();
-      let (rs_rt_value, rs_rt_heap) = de.read(&rs_roundtripped_bytes).expect("parse_v8 failed");
-
-      if !v8_valueserializer::value_eq((&assert.value, &assert.heap), (&rs_rt_value, &rs_rt_heap)) {
-        println!("=== EXPECTED ===");
-        println!("{:?}", assert);
-
-        let rt_assert = $crate::util::Assert {
-          value: rs_rt_value,
-          heap: rs_rt_heap,
-        };
-        println!("=== ROUNDTRIPPED ===");
-        println!("{:?}", rt_assert);
-
-        panic!("serialized roundtrip failed");
+      let de = isolate.rs_deserializer();
+      $crate::util::assert_reads_to(&assert, de, &rs_roundtripped_bytes, "serialized roundtrip failed");
+
+      if $display_roundtrip {
+        let eval = v8_valueserializer::display(&assert.heap, &assert.value, v8_valueserializer::DisplayOptions {
+          format: v8_valueserializer::DisplayFormat::Eval,
+        });
+        let display_rt_js_value = isolate.eval(eval.as_str()).expect("eval failed");
+        let display_rt_bytes = isolate
+          .serialize_value(display_rt_js_value)
+          .expect("serialize_value failed");
+        println!("display_rt_bytes {:?}", display_rt_bytes);
+
+        let de = isolate.rs_deserializer();
+        $crate::util::assert_reads_to(&assert, de, &display_rt_bytes, "display roundtrip failed");
       }
+    }
+  };
+  ($name:ident $code:expr) => {
+    $crate::serde_test!(@v8 $name $code, true);
+  };
+  // Shared array buffers are only equal to themselves, so the buffers that
+  // the displayed code creates never equal the original ones.
+  ($name:ident $code:expr, shared) => {
+    $crate::serde_test!(@v8 $name $code, false);
+  };
+  // Bytes in a wire format version that V8 no longer writes, which both V8 and
+  // this crate read to the value that the code evaluates to.
+  ($name:ident $code:expr, legacy = $bytes:expr) => {
+    #[test]
+    fn $name() {
+      let mut isolate = $crate::util::Isolate::default();
+      let bytes: &[u8] = &$bytes;
 
-      let display = v8_valueserializer::display(&assert.heap, &assert.value, v8_valueserializer::DisplayOptions {
-        format: v8_valueserializer::DisplayFormat::Repl,
-      });
-      insta::with_settings!({
-        description => format!("=== SOURCE ===\n{}", $code),
-        omit_expression => true,
-      }, {
-        insta::assert_snapshot!(concat!("display_", stringify!($name)), display);
-      });
+      let de = v8_valueserializer::ValueDeserializer::default();
+      let (value, heap) = de.read(bytes).expect("legacy parse_v8 failed");
+      let assert = $crate::util::Assert {
+        value,
+        heap,
+      };
+      $crate::serde_test!(@snapshot $name $code, assert);
 
-      let eval = v8_valueserializer::display(&assert.heap, &assert.value, v8_valueserializer::DisplayOptions {
-        format: v8_valueserializer::DisplayFormat::Eval,
-      });
-      let display_rt_js_value = isolate.eval(eval.as_str()).expect("eval failed");
-      let display_rt_bytes = isolate
-        .serialize_value(display_rt_js_value)
+      let v8_value = isolate.deserialize(bytes).expect("legacy deserialize failed");
+      let v8_bytes = isolate
+        .serialize_value(v8_value)
         .expect("serialize_value failed");
-      println!("display_rt_bytes {:?}", display_rt_bytes);
+      println!("v8_bytes {:?}", v8_bytes);
+      let de = v8_valueserializer::ValueDeserializer::default();
+      $crate::util::assert_reads_to(&assert, de, &v8_bytes, "legacy read differs from V8");
 
+      let code = $crate::util::source($code);
+      let eval_value = isolate.eval(code.as_str()).expect("eval failed");
+      let eval_bytes = isolate
+        .serialize_value(eval_value)
+        .expect("serialize_value failed");
+      println!("eval_bytes {:?}", eval_bytes);
       let de = v8_valueserializer::ValueDeserializer::default();
-      let (display_rt_value, display_rt_heap) = de.read(&display_rt_bytes).expect("parse_v8 failed");
+      $crate::util::assert_reads_to(&assert, de, &eval_bytes, "legacy read differs from the source");
+    }
+  };
+  // Bytes written by Node's `v8.serialize()`, which are read in Node
+  // compatibility mode to the value that the code evaluates to.
+  ($name:ident $code:expr, node = $bytes:expr) => {
+    #[test]
+    fn $name() {
+      let mut isolate = $crate::util::Isolate::default();
+      let bytes: &[u8] = &$bytes;
 
-      if !v8_valueserializer::value_eq((&assert.value, &assert.heap), (&display_rt_value, &display_rt_heap)) {
-        println!("=== EXPECTED ===");
-        println!("{:?}", assert);
+      let de = $crate::util::node_deserializer();
+      let (value, heap) = de.read(bytes).expect("node parse_v8 failed");
+      let assert = $crate::util::Assert {
+        value,
+        heap,
+      };
+      $crate::serde_test!(@snapshot $name $code, assert);
 
-        let rt_assert = $crate::util::Assert {
-          value: display_rt_value,
-          heap: display_rt_heap,
-        };
-        println!("=== ROUNDTRIPPED ===");
-        println!("{:?}", rt_assert);
+      let code = $crate::util::source($code);
+      let eval_value = isolate.eval(code.as_str()).expect("eval failed");
+      let eval_bytes = isolate
+        .serialize_value(eval_value)
+        .expect("serialize_value failed");
+      println!("eval_bytes {:?}", eval_bytes);
+      let de = v8_valueserializer::ValueDeserializer::default();
+      $crate::util::assert_reads_to(&assert, de, &eval_bytes, "node read differs from the source");
 
-        panic!("display roundtrip failed");
-      }
+      let mut ser = v8_valueserializer::ValueSerializer::default();
+      ser.set_node_compat(true);
+      let rs_ser_bytes = ser.finish(&assert.heap, &assert.value).expect("serialize failed");
+      println!("rsserbytes {:?}", rs_ser_bytes);
+      let de = $crate::util::node_deserializer();
+      $crate::util::assert_reads_to(&assert, de, &rs_ser_bytes, "node roundtrip failed");
+    }
+  };
+  // Bytes written by a V8 with values that the V8 of these tests does not
+  // have yet, which are written back unchanged.
+  ($name:ident $code:expr, bytes = $bytes:expr) => {
+    #[test]
+    fn $name() {
+      let bytes: &[u8] = &$bytes;
 
+      let de = v8_valueserializer::ValueDeserializer::default();
+      let (value, heap) = de.read(bytes).expect("parse_v8 failed");
+      let assert = $crate::util::Assert {
+        value,
+        heap,
+      };
+      $crate::serde_test!(@snapshot $name $code, assert);
+
+      let ser = v8_valueserializer::ValueSerializer::default();
+      let rs_ser_bytes = ser.finish(&assert.heap, &assert.value).expect("serialize failed");
+      assert_eq!(rs_ser_bytes, bytes, "serialize changed the bytes");
     }
   };
 }
@@ -0,0 +1,80 @@
+use v8_valueserializer::DeserializerOptions;
+use v8_valueserializer::ValueDeserializer;
+
+/// Validate `bytes`, and check that reading them has the same outcome.
+fn validate(options: DeserializerOptions, bytes: &[u8]) -> Result<(), String> {
+  let validated = ValueDeserializer::with_options(options.clone())
+    .validate(bytes)
+    .map_err(|err| err.to_string());
+  let read = ValueDeserializer::with_options(options)
+    .read(bytes)
+    .map(|_| ())
+    .map_err(|err| err.to_string());
+  assert_eq!(validated, read);
+  validated
+}
+
+#[test]
+fn valid() {
+  let options = DeserializerOptions::default();
+  // { a: [1, "x"], b: new Uint16Array([1]) } with a two byte string key.
+  let bytes = [
+    0xFF, 0x0F, b'o', b'"', 0x01, b'a', b'A', 0x02, b'I', 0x02, b'"', 0x01,
+    b'x', b'$', 0x00, 0x02, b'c', 0x02, b'b', 0x00, b'B', 0x02, 0x01, 0x00,
+    b'V', b'W', 0x00, 0x02, 0x00, b'{', 0x02,
+  ];
+  validate(options.clone(), &bytes).unwrap();
+  // Versionless data.
+  validate(options.clone(), &[b'I', 0x02]).unwrap();
+  // Version 13 data in the broken format with view flags.
+  let bytes = [
+    0xFF, 0x0D, b'A', 0x01, b'~', 0x02, 0x04, 0x01, 0x02, b'V', b'B', 0x00,
+    0x02, 0x02, b'$', 0x00, 0x01,
+  ];
+  validate(options, &bytes).unwrap();
+}
+
+#[test]
+fn invalid() {
+  let options = DeserializerOptions::default();
+  let cases: &[&[u8]] = &[
+    // Truncated.
+    &[0xFF, 0x0F, b'o', b'"', 0x01, b'a'],
+    // Unknown tag.
+    &[0xFF, 0x0F, b'!'],
+    // Reference to an object that does not exist.
+    &[0xFF, 0x0F, b'A', 0x01, b'^', 0x05, b'$', 0x00, 0x01],
+    // Unaligned view.
+    &[
+      0xFF, 0x0F, b'B', 0x02, 0x01, 0x02, b'V', b'W', 0x01, 0x00, 0x00,
+    ],
+    // View out of bounds.
+    &[
+      0xFF, 0x0F, b'B', 0x02, 0x01, 0x02, b'V', b'B', 0x00, 0x04, 0x00,
+    ],
+    // ArrayBuffer longer than the input.
+    &[0xFF, 0x0F, b'B', 0x04, 0x01, 0x02],
+    // A value after a two byte string key that fails with the key in its path.
+    &[
+      0xFF, 0x0F, b'a', 0x03, b'c', 0x02, b'I', b'w', b'@', 0x01, 0x03,
+    ],
+    // Two byte string longer than the input.
+    &[0xFF, 0x0F, b'c', 0x04, 0x01, 0x00],
+    // Invalid regexp flags.
+    &[0xFF, 0x0F, b'R', b'"', 0x01, b'a', 0xC0, 0x00],
+    // Wrong property count.
+    &[0xFF, 0x0F, b'o', b'"', 0x01, b'a', b'T', b'{', 0x02],
+  ];
+  for bytes in cases {
+    validate(options.clone(), bytes).unwrap_err();
+  }
+}
+
+#[test]
+fn limits() {
+  let bytes = [0xFF, 0x0F, b'B', 0x04, 0x01, 0x02, 0x03, 0x04];
+  let options = DeserializerOptions::default().max_allocation(3);
+  validate(options, &bytes).unwrap_err();
+  let options = DeserializerOptions::default().max_heap_objects(0);
+  validate(options, &bytes).unwrap_err();
+}
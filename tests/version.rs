@@ -0,0 +1,114 @@
+use v8_valueserializer::HeapValue;
+use v8_valueserializer::ParseErrorKind;
+use v8_valueserializer::SerializationError;
+use v8_valueserializer::Value;
+use v8_valueserializer::ValueDeserializer;
+use v8_valueserializer::ValueSerializer;
+
+#[test]
+fn versionless_invalid_value_count() {
+  let bytes = [b'I', 0x02, b'I', 0x04];
+  let err = ValueDeserializer::default().read(&bytes).unwrap_err();
+  assert!(matches!(
+    err.kind,
+    ParseErrorKind::InvalidLegacyValueCount(2)
+  ));
+}
+
+#[test]
+fn version_12_unknown_tag_is_host_object() {
+  let bytes = [0xFF, 0x0C, b'!'];
+  let err = ValueDeserializer::default().read(&bytes).unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::HostObjectNotSupported));
+  let bytes = [0xFF, 0x0D, b'!'];
+  let err = ValueDeserializer::default().read(&bytes).unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::UnexpectedTag(b'!')));
+}
+
+// new Uint8Array(new ArrayBuffer(2, { maxByteLength: 4 }))
+const RESIZABLE_VIEW: &[u8] = &[
+  0xFF, 0x0F, b'~', 0x02, 0x04, 0x01, 0x02, b'V', b'B', 0x00, 0x02, 0x03,
+];
+
+#[test]
+fn unsupported_version() {
+  let mut ser = ValueSerializer::default();
+  let err = ser.set_version(12).unwrap_err();
+  assert!(matches!(err, SerializationError::UnsupportedVersion(12)));
+  let err = ser.set_version(16).unwrap_err();
+  assert!(matches!(err, SerializationError::UnsupportedVersion(16)));
+}
+
+#[test]
+fn version_14_keeps_resizable_buffers() {
+  let (value, heap) =
+    ValueDeserializer::default().read(RESIZABLE_VIEW).unwrap();
+  let mut ser = ValueSerializer::default();
+  ser.set_version(14).unwrap();
+  let bytes = ser.finish(&heap, &value).unwrap();
+  assert_eq!(bytes[1], 0x0E);
+  assert_eq!(bytes[2..], RESIZABLE_VIEW[2..]);
+}
+
+#[test]
+fn version_13_downlevels_resizable_buffers() {
+  let (value, heap) =
+    ValueDeserializer::default().read(RESIZABLE_VIEW).unwrap();
+  let mut ser = ValueSerializer::default();
+  ser.set_version(13).unwrap();
+  let bytes = ser.finish(&heap, &value).unwrap();
+  assert_eq!(
+    bytes,
+    [0xFF, 0x0D, b'B', 0x02, 0x01, 0x02, b'V', b'B', 0x00, 0x02]
+  );
+
+  let (value, heap) = ValueDeserializer::default().read(&bytes).unwrap();
+  let Value::HeapReference(reference) = value else {
+    panic!("expected a heap reference");
+  };
+  let HeapValue::ArrayBufferView(view) = reference.open(&heap) else {
+    panic!("expected an array buffer view");
+  };
+  assert!(!view.is_length_tracking);
+  assert!(!view.is_backed_by_rab);
+  let HeapValue::ArrayBuffer(buffer) = view.buffer.open(&heap) else {
+    panic!("expected an array buffer");
+  };
+  assert_eq!(buffer.max_byte_length, None);
+  assert_eq!(buffer.as_u8_slice(), [1, 2]);
+}
+
+#[test]
+fn version_14_rejects_shared_objects() {
+  let bytes = [0xFF, 0x0F, b'p', 0x00];
+  let (value, heap) = ValueDeserializer::default().read(&bytes).unwrap();
+  let mut ser = ValueSerializer::default();
+  ser.set_version(14).unwrap();
+  let err = ser.finish(&heap, &value).unwrap_err();
+  assert!(matches!(
+    err,
+    SerializationError::UnsupportedInVersion { version: 14, .. }
+  ));
+}
+
+#[test]
+fn version_14_rejects_float16_arrays() {
+  // new Float16Array([1.5])
+  let bytes = [
+    0xFF, 0x0F, b'B', 0x02, 0x00, 0x3E, b'V', b'h', 0x00, 0x02, 0x00,
+  ];
+  let (value, heap) = ValueDeserializer::default().read(&bytes).unwrap();
+  let mut ser = ValueSerializer::default();
+  ser.set_version(14).unwrap();
+  let err = ser.finish(&heap, &value).unwrap_err();
+  assert!(matches!(
+    err,
+    SerializationError::UnsupportedInVersion {
+      value: "Float16Array",
+      version: 14,
+    }
+  ));
+  let mut ser = ValueSerializer::default();
+  ser.set_version(15).unwrap();
+  assert_eq!(ser.finish(&heap, &value).unwrap(), bytes);
+}
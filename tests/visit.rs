@@ -0,0 +1,187 @@
+use v8_valueserializer::ArrayBufferViewKind;
+use v8_valueserializer::BorrowedArrayBuffer;
+use v8_valueserializer::BorrowedPropertyKey;
+use v8_valueserializer::BorrowedStringValue;
+use v8_valueserializer::ParseErrorKind;
+use v8_valueserializer::ValueDeserializer;
+use v8_valueserializer::ValueVisitor;
+
+/// Records the events it receives as strings.
+#[derive(Default)]
+struct Recorder {
+  events: Vec<String>,
+}
+
+impl<'a> ValueVisitor<'a> for Recorder {
+  fn bool(&mut self, value: bool) -> Result<(), ParseErrorKind> {
+    self.events.push(format!("bool {}", value));
+    Ok(())
+  }
+
+  fn int32(&mut self, value: i32) -> Result<(), ParseErrorKind> {
+    self.events.push(format!("int32 {}", value));
+    Ok(())
+  }
+
+  fn string(
+    &mut self,
+    value: BorrowedStringValue<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    self.events.push(format!("string {}", value.to_string()));
+    Ok(())
+  }
+
+  fn object_reference(&mut self, id: u32) -> Result<(), ParseErrorKind> {
+    self.events.push(format!("reference {}", id));
+    Ok(())
+  }
+
+  fn begin_object(&mut self) -> Result<(), ParseErrorKind> {
+    self.events.push("begin object".to_string());
+    Ok(())
+  }
+
+  fn property_key(
+    &mut self,
+    key: BorrowedPropertyKey<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    let BorrowedPropertyKey::String(key) = key else {
+      panic!("expected a string key");
+    };
+    self.events.push(format!("key {}", key.to_string()));
+    Ok(())
+  }
+
+  fn end_object(&mut self, num_properties: u32) -> Result<(), ParseErrorKind> {
+    self.events.push(format!("end object {}", num_properties));
+    Ok(())
+  }
+
+  fn begin_dense_array(&mut self, length: u32) -> Result<(), ParseErrorKind> {
+    self.events.push(format!("begin array {}", length));
+    Ok(())
+  }
+
+  fn end_dense_array(
+    &mut self,
+    num_properties: u32,
+    length: u32,
+  ) -> Result<(), ParseErrorKind> {
+    self
+      .events
+      .push(format!("end array {} {}", num_properties, length));
+    Ok(())
+  }
+
+  fn array_buffer(
+    &mut self,
+    array_buffer: BorrowedArrayBuffer<'a>,
+  ) -> Result<(), ParseErrorKind> {
+    let bytes = array_buffer.as_u8_slice();
+    self.events.push(format!("array buffer {:?}", bytes));
+    Ok(())
+  }
+
+  fn array_buffer_view(
+    &mut self,
+    kind: ArrayBufferViewKind,
+    byte_offset: u32,
+    length: u32,
+    _is_length_tracking: bool,
+    _is_backed_by_rab: bool,
+  ) -> Result<(), ParseErrorKind> {
+    self
+      .events
+      .push(format!("view {:?} {} {}", kind, byte_offset, length));
+    Ok(())
+  }
+}
+
+fn visit(bytes: &[u8]) -> Vec<String> {
+  let mut recorder = Recorder::default();
+  ValueDeserializer::default()
+    .visit(bytes, &mut recorder)
+    .unwrap();
+  recorder.events
+}
+
+#[test]
+fn nested_values() {
+  // [1, "x", { a: true }]
+  let bytes = [
+    0xFF, 0x0F, b'A', 0x03, b'I', 0x02, b'"', 0x01, b'x', b'o', b'"', 0x01,
+    b'a', b'T', b'{', 0x01, b'$', 0x00, 0x03,
+  ];
+  assert_eq!(
+    visit(&bytes),
+    [
+      "begin array 3",
+      "int32 1",
+      "string x",
+      "begin object",
+      "key a",
+      "bool true",
+      "end object 1",
+      "end array 0 3",
+    ]
+  );
+}
+
+#[test]
+fn views_and_references() {
+  // const ab = new ArrayBuffer(2);
+  // [new Uint8Array(ab), new Uint8Array(ab, 1, 1)]
+  let bytes = [
+    0xFF, 0x0F, b'A', 0x02, b'B', 0x02, 0x01, 0x02, b'V', b'B', 0x00, 0x02,
+    0x00, b'^', 0x01, b'V', b'B', 0x01, 0x01, 0x00, b'$', 0x00, 0x02,
+  ];
+  assert_eq!(
+    visit(&bytes),
+    [
+      "begin array 2",
+      "array buffer [1, 2]",
+      "view Uint8Array 0 2",
+      "reference 1",
+      "view Uint8Array 1 1",
+      "end array 0 2",
+    ]
+  );
+}
+
+#[test]
+fn invalid_reference() {
+  let bytes = [0xFF, 0x0F, b'A', 0x01, b'^', 0x05, b'$', 0x00, 0x01];
+  let err = ValueDeserializer::default()
+    .visit(&bytes, &mut Recorder::default())
+    .unwrap_err();
+  assert!(matches!(
+    err.kind,
+    ParseErrorKind::InvalidObjectReference(5)
+  ));
+}
+
+#[test]
+fn visitor_error() {
+  struct Stop;
+  impl ValueVisitor<'_> for Stop {
+    fn int32(&mut self, _value: i32) -> Result<(), ParseErrorKind> {
+      Err(ParseErrorKind::Visitor("stop".to_string()))
+    }
+  }
+  let bytes = [0xFF, 0x0F, b'I', 0x02];
+  let err = ValueDeserializer::default()
+    .visit(&bytes, &mut Stop)
+    .unwrap_err();
+  assert!(matches!(err.kind, ParseErrorKind::Visitor(_)));
+}
+
+#[test]
+fn versionless_data_is_unsupported() {
+  let err = ValueDeserializer::default()
+    .visit(&[b'I', 0x02], &mut Recorder::default())
+    .unwrap_err();
+  assert!(matches!(
+    err.kind,
+    ParseErrorKind::UnsupportedVisitVersion(0)
+  ));
+}